@@ -13,6 +13,11 @@ pub mod real_ring;
 #[cfg(not(target_os = "linux"))]
 pub mod windows_stubs;
 
+// Platform-independent, shared with the stub UMEM
+#[cfg(not(target_os = "linux"))]
+#[path = "umem/error.rs"]
+mod umem_error;
+
 #[cfg(not(target_os = "linux"))]
 pub use windows_stubs::*;
 
@@ -14,13 +14,15 @@ unsafe impl<T> Send for ConsumerRing<T> {}
 
 impl<T: Copy> ConsumerRing<T> {
     /// # Safety
-    /// Pointers must be valid and mapped from the kernel
+    /// Pointers must be valid and mapped from the kernel.
+    /// `size` must be a power of two: indices are reduced with `size - 1` as a mask.
     pub unsafe fn new(
         producer: *mut u32,
         consumer: *mut u32,
         descriptors: *mut T,
         size: u32,
     ) -> Self {
+        debug_assert!(size.is_power_of_two(), "ring size {} is not a power of two", size);
         Self {
             producer: producer as *const AtomicU32,
             consumer: consumer as *mut AtomicU32,
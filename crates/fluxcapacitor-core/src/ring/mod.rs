@@ -63,6 +63,40 @@ mod tests {
         assert!(idx3.is_none()); // Full (prod=4, cons=0, size=4)
     }
 
+    #[test]
+    fn test_producer_reserve_up_to() {
+        let mut producer_val = 3u32;
+        let mut consumer_val = 0u32;
+        let mut descriptors = vec![0u64; 4];
+
+        let mut ring = unsafe {
+            ProducerRing::new(&mut producer_val, &mut consumer_val, descriptors.as_mut_ptr(), 4)
+        };
+
+        // One slot left: a request for three gets one, a full ring gets none
+        assert_eq!(ring.reserve(3), None);
+        assert_eq!(ring.reserve_up_to(3), (3, 1));
+        ring.submit(4);
+        assert_eq!(ring.reserve_up_to(3), (4, 0));
+    }
+
+    #[test]
+    fn test_producer_needs_wakeup() {
+        let mut producer_val = 0u32;
+        let mut consumer_val = 0u32;
+        let mut flags = 0u32;
+        let mut descriptors = vec![0u64; 4];
+
+        // No flags word: never asks for a wakeup
+        let ring = unsafe { ProducerRing::new(&mut producer_val, &mut consumer_val, descriptors.as_mut_ptr(), 4) };
+        assert!(!ring.needs_wakeup());
+
+        let ring = unsafe { ring.with_flags(&mut flags) };
+        assert!(!ring.needs_wakeup());
+        unsafe { std::ptr::write_volatile(&mut flags, crate::sys::if_xdp::XDP_RING_NEED_WAKEUP) };
+        assert!(ring.needs_wakeup());
+    }
+
     #[test]
     fn test_consumer_ring_basic_flow() {
         let mut producer_val = 0u32;
@@ -173,4 +207,22 @@ mod tests {
         // cons = MAX-1 + 2 = 0.
         assert_eq!(consumer_val, 0);
     }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a power of two")]
+    fn test_ring_size_must_be_power_of_two() {
+        let mut producer_val = 0u32;
+        let mut consumer_val = 0u32;
+        let mut descriptors = [0u64; 6];
+
+        let _ring = unsafe {
+            ProducerRing::new(
+                &mut producer_val,
+                &mut consumer_val,
+                descriptors.as_mut_ptr(),
+                6,
+            )
+        };
+    }
 }
@@ -8,19 +8,23 @@ pub struct ProducerRing<T> {
     mask: u32,
     size: u32,
     cached_consumer: u32,
+    // The kernel's flags word, or null if the ring has none.
+    flags: *const AtomicU32,
 }
 
 unsafe impl<T> Send for ProducerRing<T> {}
 
 impl<T: Copy> ProducerRing<T> {
     /// # Safety
-    /// Pointers must be valid and mapped from the kernel
+    /// Pointers must be valid and mapped from the kernel.
+    /// `size` must be a power of two: indices are reduced with `size - 1` as a mask.
     pub unsafe fn new(
         producer: *mut u32,
         consumer: *mut u32,
         descriptors: *mut T,
         size: u32,
     ) -> Self {
+        debug_assert!(size.is_power_of_two(), "ring size {} is not a power of two", size);
         Self {
             producer: producer as *mut AtomicU32,
             consumer: consumer as *const AtomicU32,
@@ -28,9 +32,32 @@ impl<T: Copy> ProducerRing<T> {
             mask: size - 1,
             size,
             cached_consumer: 0,
+            flags: ptr::null(),
         }
     }
 
+    /// Attach the ring's flags word (`XdpRingOffset::flags`), for `needs_wakeup`.
+    ///
+    /// # Safety
+    /// `flags` must be valid for as long as the ring, like the other pointers.
+    pub unsafe fn with_flags(mut self, flags: *mut u32) -> Self {
+        self.flags = flags as *const AtomicU32;
+        self
+    }
+
+    /// Whether the kernel set `XDP_RING_NEED_WAKEUP`: it will not look at the ring
+    /// again until woken by a syscall (`sendto` for TX, `poll` or `recvfrom` for
+    /// Fill). Only sockets bound with `XDP_USE_NEED_WAKEUP` get the flag; always
+    /// `false` without a flags word.
+    #[inline]
+    pub fn needs_wakeup(&self) -> bool {
+        if self.flags.is_null() {
+            return false;
+        }
+        let flags = unsafe { (*self.flags).load(Ordering::Acquire) };
+        flags & crate::sys::if_xdp::XDP_RING_NEED_WAKEUP != 0
+    }
+
     #[inline]
     pub fn available(&self) -> u32 {
         let producer_idx = unsafe { (*self.producer).load(Ordering::Relaxed) };
@@ -57,6 +84,14 @@ impl<T: Copy> ProducerRing<T> {
         Some(producer_idx)
     }
 
+    /// Like `reserve`, but settle for the free slots when fewer than `count` are left.
+    /// Returns the start index and the number of slots reserved (possibly 0).
+    #[inline]
+    pub fn reserve_up_to(&mut self, count: u32) -> (u32, u32) {
+        let producer_idx = unsafe { (*self.producer).load(Ordering::Relaxed) };
+        (producer_idx, self.available().min(count))
+    }
+
     #[inline]
     pub fn submit(&mut self, idx: u32) {
          unsafe { (*self.producer).store(idx, Ordering::Release) };
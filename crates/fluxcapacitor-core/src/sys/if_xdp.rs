@@ -4,6 +4,9 @@
 pub const XDP_SHARED_UMEM: u16 = 1;
 pub const XDP_COPY: u16 = 2;
 pub const XDP_ZEROCOPY: u16 = 4;
+pub const XDP_USE_NEED_WAKEUP: u16 = 8;
+// Multi-buffer: a packet may span several frames chained with XDP_PKT_CONTD.
+pub const XDP_USE_SG: u16 = 16;
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -23,6 +26,23 @@ pub struct XdpRingOffset {
     pub flags: u64,
 }
 
+/// Kernel-side counters from the `XDP_STATISTICS` getsockopt. Kernels before 5.9
+/// only report the first three; the others then read as 0.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XdpStatistics {
+    /// Dropped for reasons other than invalid descriptors.
+    pub rx_dropped: u64,
+    pub rx_invalid_descs: u64,
+    pub tx_invalid_descs: u64,
+    /// Dropped because the RX ring was full.
+    pub rx_ring_full: u64,
+    /// Times the kernel found the Fill Ring empty.
+    pub rx_fill_ring_empty_descs: u64,
+    /// Times the kernel found the TX ring empty.
+    pub tx_ring_empty_descs: u64,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct XdpUmemReg {
@@ -31,6 +51,26 @@ pub struct XdpUmemReg {
     pub chunk_size: u32,
     pub headroom: u32,
     pub flags: u32,
+    /// Bytes of `XskTxMetadata` in front of each TX frame. Linux 6.8+.
+    pub tx_metadata_len: u32,
+}
+
+// `XdpUmemReg::flags`: `tx_metadata_len` is set.
+pub const XDP_UMEM_TX_METADATA_LEN: u32 = 1 << 2;
+
+// `XskTxMetadata::flags`: request a hardware launch time. Linux 6.14+.
+pub const XDP_TXMD_FLAGS_LAUNCH_TIME: u64 = 1 << 2;
+
+/// Per-frame TX request read by the kernel `tx_metadata_len` bytes before the
+/// frame of a descriptor carrying `XDP_TX_METADATA`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XskTxMetadata {
+    pub flags: u64,
+    pub csum_start: u16,
+    pub csum_offset: u16,
+    /// Transmit time in nanoseconds of the NIC's clock (normally TAI).
+    pub launch_time: u64,
 }
 
 #[repr(C)]
@@ -52,3 +92,16 @@ pub const XDP_UMEM_FILL_RING: i32 = 5;
 pub const XDP_UMEM_COMPLETION_RING: i32 = 6;
 pub const XDP_RX_RING: i32 = 2;
 pub const XDP_TX_RING: i32 = 3;
+pub const XDP_STATISTICS: i32 = 7;
+pub const XDP_OPTIONS: i32 = 8;
+
+// Set in a ring's flags word when the kernel waits for a syscall to process it
+// (sockets bound with XDP_USE_NEED_WAKEUP).
+pub const XDP_RING_NEED_WAKEUP: u32 = 1;
+
+// Flags returned by the XDP_OPTIONS getsockopt
+pub const XDP_OPTIONS_ZEROCOPY: u32 = 1;
+
+// Flags in the `options` field of an RX/TX descriptor
+pub const XDP_PKT_CONTD: u32 = 1 << 0;
+pub const XDP_TX_METADATA: u32 = 1 << 1;
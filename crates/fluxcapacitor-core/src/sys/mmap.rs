@@ -16,11 +16,28 @@ impl MmapArea {
     /// Create a new MmapArea from a raw pointer and length.
     /// SAFETY: The pointer must be a valid mmap'd region of `len` bytes.
     /// The caller transfers ownership of the mapping to this struct.
-    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Self {
-        Self {
-            ptr: NonNull::new(ptr).expect("mmap returned null"),
-            len,
+    /// Returns `InvalidInput` if `ptr` is null.
+    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> io::Result<Self> {
+        let ptr = NonNull::new(ptr).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "mmap returned null"))?;
+        Ok(Self { ptr, len })
+    }
+
+    /// Map `len` bytes of private, zeroed, anonymous memory.
+    pub fn anonymous(len: usize) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
         }
+        unsafe { Self::from_raw(ptr as *mut u8, len) }
     }
 
     pub fn as_ptr(&self) -> *mut u8 {
@@ -39,3 +56,21 @@ impl Drop for MmapArea {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_raw_rejects_null() {
+        let res = unsafe { MmapArea::from_raw(std::ptr::null_mut(), 4096) };
+        assert_eq!(res.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let area = MmapArea::anonymous(4096).expect("Failed to map");
+        assert_eq!(area.len(), 4096);
+        unsafe { *area.as_ptr() = 1 };
+    }
+}
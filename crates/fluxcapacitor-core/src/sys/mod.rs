@@ -2,3 +2,4 @@ pub mod socket;
 pub mod if_xdp;
 pub mod utils;
 pub mod mmap;
+pub mod packet;
@@ -0,0 +1,122 @@
+//! AF_PACKET socket with a TPACKET_V3 RX ring.
+//!
+//! Fallback transport for hosts without AF_XDP (old kernels, some VMs and CI runners).
+//! Every packet is copied by the kernel into the ring, and TX is one `send` per frame.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use libc::{c_int, c_void, socklen_t};
+use crate::sys::mmap::MmapArea;
+use crate::sys::socket::{mmap_range, RawFd};
+
+/// RX ring geometry. Blocks are handed to userspace whole, either when full or after
+/// `RETIRE_TIMEOUT_MS`, so the timeout bounds latency at low packet rates.
+const BLOCK_SIZE: u32 = 1 << 18;
+const BLOCK_COUNT: u32 = 16;
+const RETIRE_TIMEOUT_MS: u32 = 1;
+
+pub struct PacketSocket {
+    fd: OwnedFd,
+    ring: MmapArea,
+    next_block: u32,
+}
+
+impl PacketSocket {
+    /// Open a socket receiving every frame on `ifindex` and bind TX to it.
+    pub fn open(ifindex: u32, frame_size: u32) -> io::Result<Self> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let raw = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, protocol as c_int) };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let version = libc::tpacket_versions::TPACKET_V3 as c_int;
+        set_opt(&fd, libc::PACKET_VERSION, &version)?;
+
+        let req = libc::tpacket_req3 {
+            tp_block_size: BLOCK_SIZE,
+            tp_block_nr: BLOCK_COUNT,
+            tp_frame_size: frame_size,
+            tp_frame_nr: (BLOCK_SIZE / frame_size) * BLOCK_COUNT,
+            tp_retire_blk_tov: RETIRE_TIMEOUT_MS,
+            tp_sizeof_priv: 0,
+            tp_feature_req_word: 0,
+        };
+        set_opt(&fd, libc::PACKET_RX_RING, &req)?;
+
+        let len = (BLOCK_SIZE * BLOCK_COUNT) as usize;
+        let ptr = unsafe { mmap_range(fd.as_raw_fd(), len, 0) }?;
+        let ring = unsafe { MmapArea::from_raw(ptr, len) }?;
+
+        let mut sa: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        sa.sll_family = libc::AF_PACKET as u16;
+        sa.sll_protocol = protocol;
+        sa.sll_ifindex = ifindex as c_int;
+        let ret = unsafe {
+            libc::bind(fd.as_raw_fd(), &sa as *const _ as *const libc::sockaddr, mem::size_of::<libc::sockaddr_ll>() as socklen_t)
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Don't receive our own transmissions (Linux 4.20+). Best effort.
+        let _ = set_opt(&fd, libc::PACKET_IGNORE_OUTGOING, &(1 as c_int));
+
+        Ok(Self { fd, ring, next_block: 0 })
+    }
+
+    pub fn fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Call `f` for every frame in the blocks the kernel has retired, then hand the
+    /// blocks back. Returns the number of frames visited.
+    pub fn recv(&mut self, mut f: impl FnMut(&[u8])) -> usize {
+        let mut count = 0;
+        loop {
+            let block = unsafe { self.ring.as_ptr().add((self.next_block * BLOCK_SIZE) as usize) };
+            let desc = block as *mut libc::tpacket_block_desc;
+            let status = unsafe { &*(std::ptr::addr_of!((*desc).hdr.bh1.block_status) as *const AtomicU32) };
+            if status.load(Ordering::Acquire) & libc::TP_STATUS_USER == 0 {
+                return count;
+            }
+
+            let (num_pkts, first) = unsafe { ((*desc).hdr.bh1.num_pkts, (*desc).hdr.bh1.offset_to_first_pkt) };
+            let mut pkt = unsafe { block.add(first as usize) };
+            for _ in 0..num_pkts {
+                let hdr = unsafe { &*(pkt as *const libc::tpacket3_hdr) };
+                let data = unsafe { std::slice::from_raw_parts(pkt.add(hdr.tp_mac as usize), hdr.tp_snaplen as usize) };
+                f(data);
+                count += 1;
+                pkt = unsafe { pkt.add(hdr.tp_next_offset as usize) };
+            }
+
+            status.store(libc::TP_STATUS_KERNEL, Ordering::Release);
+            self.next_block = (self.next_block + 1) % BLOCK_COUNT;
+        }
+    }
+
+    /// Transmit one frame on the bound interface without blocking.
+    pub fn send(&self, frame: &[u8]) -> io::Result<()> {
+        let ret = unsafe {
+            libc::send(self.fd.as_raw_fd(), frame.as_ptr() as *const c_void, frame.len(), libc::MSG_DONTWAIT)
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+fn set_opt<T>(fd: &OwnedFd, opt: c_int, val: &T) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(fd.as_raw_fd(), libc::SOL_PACKET, opt, val as *const T as *const c_void, mem::size_of::<T>() as socklen_t)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -5,7 +5,7 @@ use libc::{
     socket, bind, setsockopt, mmap, sendto, poll, pollfd,
     AF_XDP, SOCK_RAW, SOL_XDP,
     PROT_READ, PROT_WRITE, MAP_SHARED, MAP_POPULATE,
-    MSG_DONTWAIT, POLLIN,
+    MSG_DONTWAIT, POLLIN, POLLERR, POLLHUP, POLLNVAL,
     sockaddr, socklen_t, c_void,
 };
 use crate::sys::if_xdp::*;
@@ -35,14 +35,17 @@ pub fn bind_socket(fd: RawFd, ifindex: u32, queue_id: u32, bind_flags: u16) -> i
     Ok(())
 }
 
-pub fn set_umem_reg(fd: RawFd, umem_addr: u64, len: u64, chunk_size: u32, headroom: u32) -> io::Result<()> {
+/// Register the UMEM. A non-zero `tx_metadata_len` reserves that many bytes in
+/// front of each TX frame for `XskTxMetadata`; kernels before 6.8 reject it.
+pub fn set_umem_reg(fd: RawFd, umem_addr: u64, len: u64, chunk_size: u32, headroom: u32, tx_metadata_len: u32) -> io::Result<()> {
     // XDP_UMEM_REG = 4
     let mr = XdpUmemReg {
         addr: umem_addr,
         len,
         chunk_size,
         headroom,
-        flags: 0,
+        flags: if tx_metadata_len > 0 { XDP_UMEM_TX_METADATA_LEN } else { 0 },
+        tx_metadata_len,
     };
     
     let ret = unsafe {
@@ -65,6 +68,51 @@ pub fn set_ring_size(fd: RawFd, ring_type: i32, size: u32) -> io::Result<()> {
     Ok(())
 }
 
+/// Set the socket's receive buffer (`SO_RCVBUF`). The kernel doubles `bytes` for
+/// its own bookkeeping and caps it at `net.core.rmem_max`.
+pub fn set_rcvbuf(fd: RawFd, bytes: usize) -> io::Result<()> {
+    set_buffer_size(fd, libc::SO_RCVBUF, bytes)
+}
+
+/// Set the socket's send buffer (`SO_SNDBUF`), capped at `net.core.wmem_max`.
+pub fn set_sndbuf(fd: RawFd, bytes: usize) -> io::Result<()> {
+    set_buffer_size(fd, libc::SO_SNDBUF, bytes)
+}
+
+/// The socket's receive buffer as the kernel reports it (`SO_RCVBUF`).
+pub fn get_rcvbuf(fd: RawFd) -> io::Result<usize> {
+    get_buffer_size(fd, libc::SO_RCVBUF)
+}
+
+/// The socket's send buffer as the kernel reports it (`SO_SNDBUF`).
+pub fn get_sndbuf(fd: RawFd) -> io::Result<usize> {
+    get_buffer_size(fd, libc::SO_SNDBUF)
+}
+
+fn set_buffer_size(fd: RawFd, opt: i32, bytes: usize) -> io::Result<()> {
+    let value = i32::try_from(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("buffer size {} too large", bytes)))?;
+    let ret = unsafe {
+        setsockopt(fd, libc::SOL_SOCKET, opt, &value as *const _ as *const c_void, mem::size_of::<i32>() as socklen_t)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn get_buffer_size(fd: RawFd, opt: i32) -> io::Result<usize> {
+    let mut value: i32 = 0;
+    let mut len = mem::size_of::<i32>() as socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, opt, &mut value as *mut _ as *mut c_void, &mut len)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(value.max(0) as usize)
+}
+
 pub fn get_mmap_offsets(fd: RawFd) -> io::Result<XdpMmapOffsets> {
     let mut off: XdpMmapOffsets = unsafe { mem::zeroed() };
     let mut len = mem::size_of::<XdpMmapOffsets>() as socklen_t;
@@ -80,6 +128,37 @@ pub fn get_mmap_offsets(fd: RawFd) -> io::Result<XdpMmapOffsets> {
     Ok(off)
 }
 
+/// Query the socket's negotiated options (`XDP_OPTIONS_*` flags). Linux 5.3+.
+pub fn get_xdp_options(fd: RawFd) -> io::Result<u32> {
+    let mut flags: u32 = 0;
+    let mut len = mem::size_of::<u32>() as socklen_t;
+
+    let ret = unsafe {
+        libc::getsockopt(fd, SOL_XDP, XDP_OPTIONS, &mut flags as *mut _ as *mut c_void, &mut len)
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(flags)
+}
+
+/// Read the socket's kernel drop and ring counters (`XDP_STATISTICS`).
+pub fn get_xdp_statistics(fd: RawFd) -> io::Result<XdpStatistics> {
+    let mut stats = XdpStatistics::default();
+    // Older kernels fill in a prefix of the struct and shrink `len` accordingly.
+    let mut len = mem::size_of::<XdpStatistics>() as socklen_t;
+
+    let ret = unsafe {
+        libc::getsockopt(fd, SOL_XDP, XDP_STATISTICS, &mut stats as *mut _ as *mut c_void, &mut len)
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stats)
+}
+
 pub unsafe fn mmap_range(fd: RawFd, len: usize, offset: u64) -> io::Result<*mut u8> {
     let ptr = mmap(
         std::ptr::null_mut(),
@@ -106,11 +185,16 @@ pub unsafe fn munmap(ptr: *mut u8, len: usize) -> io::Result<()> {
 }
 
 pub fn kick_tx(fd: RawFd) -> io::Result<()> {
-    let ret = unsafe {
-        sendto(fd, std::ptr::null(), 0, MSG_DONTWAIT, std::ptr::null(), 0)
-    };
-    if ret < 0 {
-        let err = io::Error::last_os_error();
+    let res = retry_eintr(|| {
+        let ret = unsafe {
+            sendto(fd, std::ptr::null(), 0, MSG_DONTWAIT, std::ptr::null(), 0)
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    });
+    if let Err(err) = res {
         // EAGAIN / EBUSY are fine, just means busy or nothing to do, but strictly it failed to send NOW.
         // For a wake-up, we often ignore harmless errors, but let's propagate.
         if err.kind() != io::ErrorKind::WouldBlock {
@@ -120,16 +204,197 @@ pub fn kick_tx(fd: RawFd) -> io::Result<()> {
     Ok(())
 }
 
-pub fn wait_rx(fd: RawFd, timeout_ms: i32) -> io::Result<bool> {
-    let mut pfd = pollfd {
-        fd,
-        events: POLLIN,
-        revents: 0,
-    };
-    
-    let ret = unsafe { poll(&mut pfd, 1, timeout_ms) };
-    if ret < 0 {
-        return Err(io::Error::last_os_error());
+/// Outcome of waiting for RX readiness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollStatus {
+    /// Data is ready.
+    Readable,
+    /// The timeout elapsed with nothing to read.
+    Timeout,
+    /// The socket reported `POLLERR`, `POLLHUP` or `POLLNVAL` (the raw `revents`)
+    /// and nothing to read: it is closed or broken and will not become readable.
+    Error(i16),
+}
+
+/// A `pollfd` for one socket, set up once and reused by every `wait`.
+pub struct RxPoll {
+    pfd: pollfd,
+}
+
+impl RxPoll {
+    pub fn new(fd: RawFd) -> Self {
+        Self { pfd: pollfd { fd, events: POLLIN, revents: 0 } }
+    }
+
+    /// Poll for RX readiness. An interrupted poll is retried with the full timeout.
+    pub fn wait(&mut self, timeout_ms: i32) -> io::Result<PollStatus> {
+        let pfd = &mut self.pfd;
+        retry_eintr(|| {
+            pfd.revents = 0;
+            let ret = unsafe { poll(pfd, 1, timeout_ms) };
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Pending data wins over a hangup, so it can still be drained.
+            Ok(if ret == 0 {
+                PollStatus::Timeout
+            } else if pfd.revents & POLLIN != 0 {
+                PollStatus::Readable
+            } else if pfd.revents & (POLLERR | POLLHUP | POLLNVAL) != 0 {
+                PollStatus::Error(pfd.revents)
+            } else {
+                PollStatus::Timeout
+            })
+        })
+    }
+}
+
+/// One-off `RxPoll::wait` on `fd`.
+pub fn wait_rx(fd: RawFd, timeout_ms: i32) -> io::Result<PollStatus> {
+    RxPoll::new(fd).wait(timeout_ms)
+}
+
+/// `pollfd`s for several sockets, set up once and reused by every `wait`.
+pub struct RxPollSet {
+    pfds: Vec<pollfd>,
+}
+
+impl RxPollSet {
+    pub fn new(fds: &[RawFd]) -> Self {
+        Self { pfds: fds.iter().map(|&fd| pollfd { fd, events: POLLIN, revents: 0 }).collect() }
+    }
+
+    /// Poll every socket for RX readiness at once. Returns true if at least one
+    /// of them has data before the timeout. An interrupted poll is retried with
+    /// the full timeout.
+    pub fn wait(&mut self, timeout_ms: i32) -> io::Result<bool> {
+        let pfds = &mut self.pfds;
+        retry_eintr(|| {
+            let ret = unsafe { poll(pfds.as_mut_ptr(), pfds.len() as _, timeout_ms) };
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(ret > 0)
+        })
+    }
+}
+
+/// One-off `RxPollSet::wait` over `fds`.
+pub fn wait_rx_any(fds: &[RawFd], timeout_ms: i32) -> io::Result<bool> {
+    RxPollSet::new(fds).wait(timeout_ms)
+}
+
+// Bound on consecutive EINTR retries, so a signal storm cannot wedge the caller.
+const EINTR_RETRIES: u32 = 16;
+
+/// Re-issue `f` while it fails with `EINTR`, up to `EINTR_RETRIES` times.
+/// Any other error, or the last `EINTR`, is returned to the caller.
+fn retry_eintr<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut attempts = 0;
+    loop {
+        match f() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted && attempts < EINTR_RETRIES => attempts += 1,
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_eintr_recovers() {
+        let mut calls = 0;
+        let res = retry_eintr(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from_raw_os_error(libc::EINTR))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(res.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_retry_eintr_is_bounded() {
+        let mut calls = 0;
+        let res: io::Result<()> = retry_eintr(|| {
+            calls += 1;
+            Err(io::Error::from_raw_os_error(libc::EINTR))
+        });
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(calls, EINTR_RETRIES + 1);
+    }
+
+    #[test]
+    fn test_retry_eintr_passes_other_errors() {
+        let mut calls = 0;
+        let res: io::Result<()> = retry_eintr(|| {
+            calls += 1;
+            Err(io::Error::from_raw_os_error(libc::EBADF))
+        });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_wait_rx_reports_closed_fd() {
+        let mut p = [0; 2];
+        assert_eq!(unsafe { libc::pipe(p.as_mut_ptr()) }, 0);
+        let mut rx = RxPoll::new(p[0]);
+
+        assert_eq!(rx.wait(0).unwrap(), PollStatus::Timeout);
+        assert_eq!(unsafe { libc::write(p[1], b"x".as_ptr() as *const c_void, 1) }, 1);
+        assert_eq!(rx.wait(0).unwrap(), PollStatus::Readable);
+
+        // Hung up with data left: still readable. Closed outright: an error.
+        unsafe { libc::close(p[1]) };
+        assert_eq!(rx.wait(0).unwrap(), PollStatus::Readable);
+        unsafe { libc::close(p[0]) };
+        assert_eq!(rx.wait(0).unwrap(), PollStatus::Error(POLLNVAL));
+    }
+
+    #[test]
+    fn test_socket_buffer_sizes() {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let raw = unsafe { socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+        assert!(raw >= 0, "socket: {}", io::Error::last_os_error());
+        let sock = unsafe { OwnedFd::from_raw_fd(raw) };
+        let fd = sock.as_raw_fd();
+
+        // Well under the default rmem_max / wmem_max; the kernel reports double
+        set_rcvbuf(fd, 32 * 1024).unwrap();
+        set_sndbuf(fd, 16 * 1024).unwrap();
+        assert_eq!(get_rcvbuf(fd).unwrap(), 64 * 1024);
+        assert_eq!(get_sndbuf(fd).unwrap(), 32 * 1024);
+
+        assert_eq!(set_rcvbuf(fd, usize::MAX).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        // A descriptor that was never opened, so no other test can own it
+        assert!(get_rcvbuf(-1).is_err());
+    }
+
+    #[test]
+    fn test_wait_rx_any() {
+        let mut a = [0; 2];
+        let mut b = [0; 2];
+        assert_eq!(unsafe { libc::pipe(a.as_mut_ptr()) }, 0);
+        assert_eq!(unsafe { libc::pipe(b.as_mut_ptr()) }, 0);
+        let fds = [a[0], b[0]];
+
+        let mut set = RxPollSet::new(&fds);
+        assert!(!wait_rx_any(&fds, 0).unwrap());
+        assert!(!set.wait(0).unwrap());
+        assert_eq!(unsafe { libc::write(b[1], b"x".as_ptr() as *const c_void, 1) }, 1);
+        assert!(wait_rx_any(&fds, 0).unwrap());
+        // The same set sees the change on its next wait
+        assert!(set.wait(0).unwrap());
+        assert!(set.wait(0).unwrap());
+
+        for fd in a.into_iter().chain(b) {
+            unsafe { libc::close(fd) };
+        }
     }
-    Ok(ret > 0)
 }
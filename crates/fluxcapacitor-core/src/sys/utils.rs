@@ -1,5 +1,7 @@
 use std::ffi::CString;
 use std::io;
+use std::mem;
+use std::os::fd::{FromRawFd, OwnedFd, AsRawFd};
 
 pub fn if_nametoindex(name: &str) -> io::Result<u32> {
     let name_cstr = CString::new(name).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid interface name"))?;
@@ -9,3 +11,222 @@ pub fn if_nametoindex(name: &str) -> io::Result<u32> {
     }
     Ok(idx)
 }
+
+/// Basic link information for a network interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IfaceInfo {
+    pub ifindex: u32,
+    pub mtu: u32,
+    /// Administratively up (`IFF_UP`).
+    pub up: bool,
+    /// Operationally up, i.e. carrier present (`IFF_RUNNING`).
+    pub running: bool,
+}
+
+/// Current `CLOCK_TAI` time in nanoseconds, the clock NICs use for launch times
+/// (`XskTxMetadata::launch_time`).
+pub fn clock_tai_ns() -> u64 {
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_TAI, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Query ifindex, MTU and link flags via `SIOCGIFMTU` / `SIOCGIFFLAGS`.
+pub fn interface_info(name: &str) -> io::Result<IfaceInfo> {
+    let ifindex = if_nametoindex(name)?;
+    let mut req = ifreq(name)?;
+    let sock = ioctl_socket()?;
+
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFMTU as _, &mut req) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mtu = unsafe { req.ifr_ifru.ifru_mtu } as u32;
+
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut req) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let flags = unsafe { req.ifr_ifru.ifru_flags } as libc::c_int;
+
+    Ok(IfaceInfo {
+        ifindex,
+        mtu,
+        up: flags & libc::IFF_UP != 0,
+        running: flags & libc::IFF_RUNNING != 0,
+    })
+}
+
+/// Whether the interface receives all traffic on the link (`IFF_PROMISC`).
+pub fn promiscuous(ifindex: u32) -> io::Result<bool> {
+    let mut req = ifreq(&if_indextoname(ifindex)?)?;
+    let sock = ioctl_socket()?;
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut req) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { req.ifr_ifru.ifru_flags } as libc::c_int & libc::IFF_PROMISC != 0)
+}
+
+/// Turn promiscuous mode on or off via `SIOCGIFFLAGS`/`SIOCSIFFLAGS`, leaving the
+/// other flags alone. Needs `CAP_NET_ADMIN`.
+///
+/// This is the interface-wide flag (as `ip link set promisc`), not a per-socket
+/// reference: turning it off also disables it for anyone else relying on it.
+pub fn set_promiscuous(ifindex: u32, enable: bool) -> io::Result<()> {
+    let mut req = ifreq(&if_indextoname(ifindex)?)?;
+    let sock = ioctl_socket()?;
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut req) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let flags = unsafe { req.ifr_ifru.ifru_flags } as libc::c_int;
+    let flags = if enable { flags | libc::IFF_PROMISC } else { flags & !libc::IFF_PROMISC };
+    req.ifr_ifru.ifru_flags = flags as libc::c_short;
+    if unsafe { libc::ioctl(sock.as_raw_fd(), libc::SIOCSIFFLAGS as _, &req) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn if_indextoname(ifindex: u32) -> io::Result<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    if unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+    Ok(name.to_string_lossy().into_owned())
+}
+
+/// An interface request naming `name`, for the `SIOC*IF*` ioctls.
+fn ifreq(name: &str) -> io::Result<libc::ifreq> {
+    let mut req: libc::ifreq = unsafe { mem::zeroed() };
+    if name.len() >= req.ifr_name.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Interface name too long"));
+    }
+    for (dst, src) in req.ifr_name.iter_mut().zip(name.as_bytes()) {
+        *dst = *src as libc::c_char;
+    }
+    Ok(req)
+}
+
+// Any socket will do for interface ioctls.
+fn ioctl_socket() -> io::Result<OwnedFd> {
+    let raw = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if raw < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(raw) })
+}
+
+// The calling thread's namespace; `setns` only affects the calling thread.
+const THREAD_NETNS: &str = "/proc/thread-self/ns/net";
+
+/// Moves the calling thread into another network namespace, and back on `exit`
+/// or drop.
+///
+/// Sockets created meanwhile stay in the namespace they were created in, so an
+/// XSK socket can be bound to an interface of another namespace (e.g. a
+/// container's). Needs `CAP_SYS_ADMIN`.
+pub struct NetnsGuard {
+    // Taken by `exit`, so drop has nothing left to restore.
+    original: Option<std::fs::File>,
+}
+
+impl NetnsGuard {
+    /// Enter the namespace at `path`, e.g. `/var/run/netns/blue` or `/proc/<pid>/ns/net`.
+    pub fn enter(path: &str) -> io::Result<Self> {
+        let original = std::fs::File::open(THREAD_NETNS)?;
+        let target = std::fs::File::open(path)?;
+        setns(&target)?;
+        Ok(Self { original: Some(original) })
+    }
+
+    /// Return to the namespace `enter` was called from, reporting failure
+    /// instead of leaving it to drop.
+    pub fn exit(mut self) -> io::Result<()> {
+        match self.original.take() {
+            Some(original) => setns(&original),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for NetnsGuard {
+    fn drop(&mut self) {
+        // Staying in the wrong namespace would silently misdirect every later
+        // socket of this thread. Panicking here could abort mid-unwind anyway,
+        // so say why and abort outright.
+        if let Some(original) = self.original.take() {
+            if let Err(e) = setns(&original) {
+                eprintln!("fluxcapacitor: failed to restore network namespace: {}", e);
+                std::process::abort();
+            }
+        }
+    }
+}
+
+fn setns(ns: &std::fs::File) -> io::Result<()> {
+    if unsafe { libc::setns(ns.as_raw_fd(), libc::CLONE_NEWNET) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interface_info_loopback() {
+        let info = interface_info("lo").expect("Failed to query lo");
+        assert_eq!(info.ifindex, if_nametoindex("lo").unwrap());
+        assert!(info.mtu > 0);
+        assert!(info.up);
+    }
+
+    #[test]
+    fn test_netns_guard_roundtrip() {
+        use std::os::unix::fs::MetadataExt;
+        let current = || std::fs::metadata(THREAD_NETNS).expect("Failed to stat netns").ino();
+        let before = current();
+
+        // Entering our own namespace exercises open/setns/restore without side effects
+        let guard = match NetnsGuard::enter("/proc/self/ns/net") {
+            Ok(guard) => guard,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return, // Needs CAP_SYS_ADMIN
+            Err(e) => panic!("setns failed: {}", e),
+        };
+        assert_eq!(current(), before);
+        assert_eq!(if_nametoindex("lo").unwrap(), 1);
+        guard.exit().expect("Failed to restore netns");
+        assert_eq!(current(), before);
+
+        // Dropping without exit restores too
+        let guard = NetnsGuard::enter("/proc/self/ns/net").expect("setns failed");
+        drop(guard);
+        assert_eq!(current(), before);
+
+        assert!(NetnsGuard::enter("/nonexistent/netns").is_err());
+    }
+
+    #[test]
+    fn test_promiscuous_toggle_loopback() {
+        let lo = if_nametoindex("lo").unwrap();
+        let original = promiscuous(lo).expect("Failed to query lo");
+
+        match set_promiscuous(lo, !original) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return, // Needs CAP_NET_ADMIN
+            Err(e) => panic!("SIOCSIFFLAGS failed: {}", e),
+        }
+        assert_eq!(promiscuous(lo).unwrap(), !original);
+        set_promiscuous(lo, original).expect("Failed to restore lo");
+        assert_eq!(promiscuous(lo).unwrap(), original);
+        // The rest of the flags are untouched
+        assert!(interface_info("lo").unwrap().up);
+
+        assert!(promiscuous(0).is_err());
+    }
+
+    #[test]
+    fn test_interface_info_unknown() {
+        assert!(interface_info("nonexistent0").is_err());
+    }
+}
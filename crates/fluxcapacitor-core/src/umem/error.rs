@@ -0,0 +1,31 @@
+/// Why a checked UMEM access was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UmemError {
+    /// The access extends past the end of the region.
+    OutOfBounds { addr: u64, len: usize, size: usize },
+    /// The access starts in one frame (chunk) and would spill into the next.
+    CrossesFrame { addr: u64, len: usize, chunk_size: u32 },
+}
+
+impl std::fmt::Display for UmemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UmemError::OutOfBounds { addr, len, size } => write!(f, "{} bytes at {:#x} exceed UMEM of {} bytes", len, addr, size),
+            UmemError::CrossesFrame { addr, len, chunk_size } => write!(f, "{} bytes at {:#x} cross a {}-byte frame boundary", len, addr, chunk_size),
+        }
+    }
+}
+
+impl std::error::Error for UmemError {}
+
+/// Check that `len` bytes at `addr` stay inside one `chunk_size` frame of a `size`-byte region.
+pub(crate) fn check_frame_access(addr: u64, len: usize, size: usize, chunk_size: u32) -> Result<(), UmemError> {
+    match addr.checked_add(len as u64) {
+        Some(end) if end <= size as u64 => {}
+        _ => return Err(UmemError::OutOfBounds { addr, len, size }),
+    }
+    if addr % chunk_size as u64 + len as u64 > chunk_size as u64 {
+        return Err(UmemError::CrossesFrame { addr, len, chunk_size });
+    }
+    Ok(())
+}
@@ -2,8 +2,15 @@ use std::num::NonZeroU64;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UmemLayout {
+    /// Usable bytes per frame.
     pub frame_size: u32,
     pub frame_count: u32,
+    /// Stride between frames, as registered with the kernel (`XDP_UMEM_REG` chunk size).
+    /// At least `frame_size`; the slack can hold headroom or metadata.
+    pub chunk_size: u32,
+    /// Bytes reserved at the start of every chunk (`XDP_UMEM_REG` headroom). The
+    /// kernel places packet data after it.
+    pub headroom: u32,
 }
 
 impl UmemLayout {
@@ -15,11 +22,35 @@ impl UmemLayout {
         Self {
             frame_size,
             frame_count,
+            chunk_size: frame_size,
+            headroom: 0,
         }
     }
 
+    /// Layout whose frames are `chunk_size` apart but only `frame_size` bytes usable.
+    pub fn with_chunk_size(frame_size: u32, frame_count: u32, chunk_size: u32) -> Self {
+        // The kernel requires a power-of-2 chunk in aligned mode, between 2048 and a page.
+        assert!(chunk_size.is_power_of_two(), "Chunk size must be power of 2");
+        assert!(chunk_size >= 2048, "Chunk size must be at least 2048");
+        assert!(frame_size > 0 && frame_size <= chunk_size, "Frame size must fit in a chunk");
+
+        Self {
+            frame_size,
+            frame_count,
+            chunk_size,
+            headroom: 0,
+        }
+    }
+
+    /// Reserve `headroom` bytes in front of each frame, within the chunk.
+    pub fn with_headroom(mut self, headroom: u32) -> Self {
+        assert!(self.frame_size as u64 + headroom as u64 <= self.chunk_size as u64, "Headroom must fit in a chunk next to the frame");
+        self.headroom = headroom;
+        self
+    }
+
     pub fn size(&self) -> usize {
-        (self.frame_size as usize) * (self.frame_count as usize)
+        (self.chunk_size as usize) * (self.frame_count as usize)
     }
 
     #[inline]
@@ -27,7 +58,7 @@ impl UmemLayout {
         if addr >= (self.size() as u64) {
             return None;
         }
-        Some((addr / self.frame_size as u64) as u32)
+        Some((addr / self.chunk_size as u64) as u32)
     }
 
     #[inline]
@@ -35,6 +66,55 @@ impl UmemLayout {
         if idx >= self.frame_count {
             return None;
         }
-        Some((idx as u64) * (self.frame_size as u64))
+        Some((idx as u64) * (self.chunk_size as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_chunk_is_frame() {
+        let layout = UmemLayout::new(2048, 4);
+        assert_eq!(layout.chunk_size, 2048);
+        assert_eq!(layout.size(), 8192);
+        assert_eq!(layout.idx_to_addr(3), Some(6144));
+        assert_eq!(layout.addr_to_idx(6144 + 100), Some(3));
+    }
+
+    #[test]
+    fn test_chunk_larger_than_frame() {
+        // 2048 usable bytes plus 256 bytes of headroom, in 4096-byte chunks
+        let headroom = 256;
+        let layout = UmemLayout::with_chunk_size(2048, 4, 4096);
+        assert!(layout.frame_size + headroom <= layout.chunk_size);
+
+        assert_eq!(layout.size(), 4 * 4096);
+        assert_eq!(layout.idx_to_addr(1), Some(4096));
+        assert_eq!(layout.idx_to_addr(4), None);
+        // A descriptor address past the headroom still maps to its chunk
+        assert_eq!(layout.addr_to_idx(4096 + headroom as u64 + 100), Some(1));
+        assert_eq!(layout.addr_to_idx(4 * 4096), None);
+    }
+
+    #[test]
+    fn test_headroom() {
+        let layout = UmemLayout::with_chunk_size(2048, 4, 4096).with_headroom(256);
+        assert_eq!(layout.headroom, 256);
+        assert_eq!(layout.size(), 4 * 4096);
+        assert_eq!(UmemLayout::new(2048, 4).headroom, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Headroom must fit in a chunk")]
+    fn test_headroom_larger_than_slack() {
+        UmemLayout::new(2048, 4).with_headroom(256);
+    }
+
+    #[test]
+    #[should_panic(expected = "Frame size must fit in a chunk")]
+    fn test_frame_larger_than_chunk() {
+        UmemLayout::with_chunk_size(4096, 4, 2048);
     }
 }
@@ -1,3 +1,4 @@
+use crate::umem::error::{check_frame_access, UmemError};
 use crate::umem::layout::UmemLayout;
 use memmap2::{MmapMut, MmapOptions};
 use std::io;
@@ -26,4 +27,119 @@ impl UmemRegion {
     pub fn layout(&self) -> UmemLayout {
         self.layout
     }
+
+    /// The `frame_size` bytes of frame `idx`, starting at `layout().idx_to_addr(idx)`.
+    /// `None` if `idx` is not below `frame_count`.
+    pub fn frame(&self, idx: u32) -> Option<&[u8]> {
+        let addr = self.layout.idx_to_addr(idx)?;
+        Some(&self.mmap[addr as usize..addr as usize + self.layout.frame_size as usize])
+    }
+
+    /// Mutable `frame`.
+    pub fn frame_mut(&mut self, idx: u32) -> Option<&mut [u8]> {
+        let addr = self.layout.idx_to_addr(idx)?;
+        Some(&mut self.mmap[addr as usize..addr as usize + self.layout.frame_size as usize])
+    }
+
+    /// Copy `data` into UMEM at `addr`, e.g. to stage a frame for TX.
+    ///
+    /// Fails without writing if the range leaves the region or crosses from one
+    /// frame (`layout().chunk_size` stride) into the next.
+    pub fn write_frame(&self, addr: u64, data: &[u8]) -> Result<(), UmemError> {
+        check_frame_access(addr, data.len(), self.len(), self.layout.chunk_size)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.as_ptr().add(addr as usize), data.len());
+        }
+        Ok(())
+    }
+
+    /// Fault in every page of the region up front.
+    ///
+    /// Anonymous mappings are populated lazily, so without this the first packets
+    /// to land in each page pay for a page fault. Call it before any frame is handed
+    /// to the kernel: it writes each page's first byte back to itself.
+    pub fn prefault(&self) {
+        let _ = self.mmap.advise(memmap2::Advice::WillNeed);
+
+        let base = self.as_ptr();
+        for offset in (0..self.len()).step_by(PREFAULT_STRIDE) {
+            // A read alone may map the shared zero page; the write forces a private page.
+            unsafe {
+                let p = base.add(offset);
+                std::ptr::write_volatile(p, std::ptr::read_volatile(p));
+            }
+        }
+    }
+}
+
+// Smallest page size on supported platforms; touching more often than needed is harmless.
+const PREFAULT_STRIDE: usize = 4096;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefault_preserves_contents() {
+        let region = UmemRegion::new(UmemLayout::new(2048, 16)).unwrap();
+        unsafe { *region.as_ptr().add(4096) = 0xAB };
+
+        region.prefault();
+
+        assert_eq!(unsafe { *region.as_ptr().add(4096) }, 0xAB);
+        assert_eq!(unsafe { *region.as_ptr() }, 0);
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let region = UmemRegion::new(UmemLayout::new(2048, 4)).unwrap();
+
+        // In bounds, including a write that ends exactly on the frame boundary
+        region.write_frame(2048 + 10, b"hello").unwrap();
+        region.write_frame(4096 - 4, b"tail").unwrap();
+        let frame = unsafe { std::slice::from_raw_parts(region.as_ptr().add(2048 + 10), 5) };
+        assert_eq!(frame, b"hello");
+
+        // Spilling into the next frame
+        assert_eq!(
+            region.write_frame(2048 - 2, b"span"),
+            Err(UmemError::CrossesFrame { addr: 2046, len: 4, chunk_size: 2048 })
+        );
+        assert_eq!(unsafe { *region.as_ptr().add(2046) }, 0, "rejected write must not land");
+
+        // Past the end of the region, and an address that overflows
+        assert_eq!(
+            region.write_frame(8192, b"x"),
+            Err(UmemError::OutOfBounds { addr: 8192, len: 1, size: 8192 })
+        );
+        assert!(matches!(region.write_frame(u64::MAX, b"x"), Err(UmemError::OutOfBounds { .. })));
+    }
+
+    #[test]
+    fn test_frame_by_index() {
+        use crate::umem::allocator::UmemAllocator;
+
+        let layout = UmemLayout::with_chunk_size(2048, 4, 4096);
+        let mut region = UmemRegion::new(layout).unwrap();
+
+        // A frame is frame_size bytes, and the last index is in range
+        region.frame_mut(3).unwrap()[..4].copy_from_slice(b"last");
+        assert_eq!(region.frame(3).unwrap().len(), 2048);
+        assert_eq!(&region.frame(3).unwrap()[..4], b"last");
+
+        // Out of range
+        assert!(region.frame(4).is_none());
+        assert!(region.frame_mut(u32::MAX).is_none());
+
+        // Frames sit at the addresses the layout and the allocator hand out
+        let mut allocator = UmemAllocator::new(layout);
+        while let Some(addr) = allocator.allocate() {
+            let idx = layout.addr_to_idx(addr).unwrap();
+            assert_eq!(layout.idx_to_addr(idx), Some(addr));
+            let frame = region.frame(idx).unwrap();
+            assert_eq!(frame.as_ptr(), unsafe { region.as_ptr().add(addr as usize) } as *const u8);
+        }
+        region.write_frame(4096 + 8, b"mid").unwrap();
+        assert_eq!(&region.frame(1).unwrap()[8..11], b"mid");
+    }
 }
@@ -1,3 +1,4 @@
 pub mod layout;
 pub mod mmap;
 pub mod allocator;
+pub mod error;
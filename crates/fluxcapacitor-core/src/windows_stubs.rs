@@ -2,7 +2,9 @@
 
 use lazy_static::lazy_static;
 use std::sync::Mutex;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
 
 // --- GLOBAL SIMULATOR STATE ---
@@ -11,6 +13,10 @@ lazy_static! {
     pub static ref NEXT_FD: Mutex<usize> = Mutex::new(1000);
 }
 
+/// Number of delayed packets (across all sockets) not yet published to an RX ring.
+/// Lets `ConsumerRing::peek` skip the global lock when no latency model is active.
+pub static PENDING_RX: AtomicUsize = AtomicUsize::new(0);
+
 pub struct MockSocketState {
     // Ring Buffers (Actual memory backing the "mmap")
     pub rx_ring: Box<[u8]>,
@@ -20,10 +26,38 @@ pub struct MockSocketState {
     
     // UMEM Buffer
     pub umem: Vec<u8>,
+    // As registered with `set_umem_reg`. Like the kernel in aligned mode, RX places
+    // data `headroom` bytes into the chunk of the Fill address.
+    pub chunk_size: u32,
+    pub headroom: u32,
+    // Bytes of TX metadata in front of each TX frame (0: none).
+    pub tx_metadata_len: u32,
+
+    // Ring sizes (entries) as set via `set_ring_size`. Masks derive from these.
+    pub rx_size: u32,
+    pub tx_size: u32,
+    pub fill_size: u32,
+    pub comp_size: u32,
+    // Entries the ring buffers above have room for.
+    pub ring_capacity: u32,
 
     // Binding info
     pub if_index: u32,
     pub queue_id: u32,
+
+    // Fault injection: fraction of TX descriptors the "NIC" fails to send.
+    pub tx_failure_rate: f32,
+    // Accumulates `tx_failure_rate` per TX so failures are spread deterministically.
+    pub tx_failure_acc: f32,
+    // TX descriptors completed without being sent, reported as `tx_invalid_descs`.
+    pub tx_failures: u64,
+
+    // Latency model: injected packets wait in `rx_pending` until their `visible_at`.
+    pub rx_latency: Duration,
+    pub rx_jitter: Duration,
+    pub rx_pending: VecDeque<(Instant, Vec<u8>)>,
+    // xorshift state, so jitter is reproducible run to run.
+    jitter_state: u64,
 }
 
 impl MockSocketState {
@@ -38,8 +72,133 @@ impl MockSocketState {
             fill_ring: vec![0u8; ring_bytes].into_boxed_slice(),
             comp_ring: vec![0u8; ring_bytes].into_boxed_slice(),
             umem: Vec::new(), 
+            chunk_size: 0,
+            headroom: 0,
+            tx_metadata_len: 0,
+            rx_size: size as u32,
+            tx_size: size as u32,
+            fill_size: size as u32,
+            comp_size: size as u32,
+            ring_capacity: size as u32,
             if_index: 0,
             queue_id: 0,
+            tx_failure_rate: 0.0,
+            tx_failure_acc: 0.0,
+            tx_failures: 0,
+            rx_latency: Duration::ZERO,
+            rx_jitter: Duration::ZERO,
+            rx_pending: VecDeque::new(),
+            jitter_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Copy `data` into a frame taken from the Fill Ring and publish it on the RX Ring.
+    /// This is what the "NIC" does when a packet arrives.
+    pub fn deliver_rx(&mut self, data: &[u8]) -> Result<(), String> {
+        // 1. Get a frame from UMEM (Simulated mechanism)
+        // In reality, the user must have put frames in the FILL RING.
+        // We need to check the FILL RING to see if user gave us buffers.
+        
+        // pointers for fill ring
+        // Layout: Prod(0), Cons(4)
+        let fill_prod_ptr = self.fill_ring.as_ptr() as *const u32;
+        let fill_cons_ptr = unsafe { self.fill_ring.as_ptr().add(4) } as *mut u32;
+        let fill_desc_ptr = unsafe { self.fill_ring.as_ptr().add(8) } as *const u64; // Fill ring contains u64 addrs
+        
+        unsafe {
+            let fill_prod = *fill_prod_ptr;
+            let fill_cons = *fill_cons_ptr;
+            
+            if fill_cons == fill_prod {
+                return Err("RX Dropped: No buffers in Fill Ring".to_string());
+            }
+            
+            // Consume one buffer from Fill Ring
+            let idx = fill_cons & (self.fill_size - 1);
+            let addr = *fill_desc_ptr.add(idx as usize);
+            
+            // Update Fill Consumer
+            *fill_cons_ptr = fill_cons + 1;
+
+            let addr = match self.chunk_size {
+                0 => addr,
+                chunk => addr - addr % chunk as u64 + self.headroom as u64,
+            };
+            
+            // 2. Write data to UMEM
+            if (addr as usize) + data.len() > self.umem.len() {
+               // Resize UMEM if needed (simple mock behavior)
+               // In reality, UMEM is fixed. Mock allows dynamic for ease.
+               if (addr as usize) + data.len() > self.umem.len() {
+                   self.umem.resize((addr as usize) + data.len() + 4096, 0);
+               }
+            }
+            
+            // Copy data
+            let dest = self.umem.as_mut_ptr().add(addr as usize);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), dest, data.len());
+            
+            // 3. Publish to RX Ring
+            // Layout: Prod(0), Cons(4), Desc(8)
+            let rx_prod_ptr = self.rx_ring.as_mut_ptr() as *mut u32;
+            let rx_desc_ptr = self.rx_ring.as_mut_ptr().add(8) as *mut ring::XDPDesc;
+            
+            let rx_prod = *rx_prod_ptr;
+            let rx_idx = rx_prod & (self.rx_size - 1);
+            
+            let desc = ring::XDPDesc {
+                addr,
+                len: data.len() as u32,
+                options: 0,
+            };
+            
+            *rx_desc_ptr.add(rx_idx as usize) = desc;
+            
+            // Update RX Producer
+            *rx_prod_ptr = rx_prod + 1;
+        }
+        
+        Ok(())
+    }
+
+    /// When a packet injected at `now` should become visible on the RX Ring.
+    pub fn rx_visible_at(&mut self, now: Instant) -> Instant {
+        let jitter_ns = self.rx_jitter.as_nanos() as u64;
+        let offset = if jitter_ns == 0 {
+            0
+        } else {
+            self.jitter_state ^= self.jitter_state << 13;
+            self.jitter_state ^= self.jitter_state >> 7;
+            self.jitter_state ^= self.jitter_state << 17;
+            self.jitter_state % (jitter_ns + 1)
+        };
+        now + self.rx_latency + Duration::from_nanos(offset)
+    }
+
+    /// Publish every delayed packet that is due, in arrival order.
+    /// A packet that finds the Fill Ring empty is dropped, as on a real NIC.
+    pub fn flush_pending_rx(&mut self, now: Instant) {
+        while let Some((visible_at, _)) = self.rx_pending.front() {
+            if *visible_at > now {
+                break;
+            }
+            if let Some((_, data)) = self.rx_pending.pop_front() {
+                PENDING_RX.fetch_sub(1, Ordering::Relaxed);
+                let _ = self.deliver_rx(&data);
+            }
+        }
+    }
+}
+
+/// Publish delayed packets whose latency has elapsed on every mocked socket.
+pub fn flush_pending_rx() {
+    if PENDING_RX.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if let Ok(mut sockets) = SOCKETS.lock() {
+        let now = Instant::now();
+        for sock in sockets.values_mut() {
+            sock.flush_pending_rx(now);
         }
     }
 }
@@ -52,6 +211,32 @@ pub mod sys {
         use crate::windows_stubs::{SOCKETS, NEXT_FD, MockSocketState};
         
         pub type RawFd = RawHandle;
+
+        /// Outcome of waiting for RX readiness.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum PollStatus {
+            Readable,
+            Timeout,
+            Error(i16),
+        }
+
+        /// The simulator cannot signal readiness: `wait` sleeps out the timeout.
+        pub struct RxPollSet;
+
+        impl RxPollSet {
+            pub fn new(_fds: &[RawFd]) -> Self {
+                RxPollSet
+            }
+
+            pub fn wait(&mut self, timeout_ms: i32) -> io::Result<bool> {
+                std::thread::sleep(std::time::Duration::from_millis(timeout_ms.max(0) as u64));
+                Ok(false)
+            }
+        }
+
+        pub fn wait_rx_any(fds: &[RawFd], timeout_ms: i32) -> io::Result<bool> {
+            RxPollSet::new(fds).wait(timeout_ms)
+        }
         
         pub fn create_xsk_socket() -> io::Result<RawFd> {
             let mut fd_lock = NEXT_FD.lock().unwrap();
@@ -77,18 +262,38 @@ pub mod sys {
             }
         }
         
-        pub fn set_umem_reg(fd: RawFd, _umem_addr: u64, len: u64, _chunk_size: u32, _headroom: u32) -> io::Result<()> {
+        pub fn set_umem_reg(fd: RawFd, _umem_addr: u64, len: u64, chunk_size: u32, headroom: u32, tx_metadata_len: u32) -> io::Result<()> {
             let fd_idx = fd as usize;
             let mut sockets = SOCKETS.lock().unwrap();
             if let Some(sock) = sockets.get_mut(&fd_idx) {
                 sock.umem.resize(len as usize, 0);
+                sock.chunk_size = chunk_size;
+                sock.headroom = headroom;
+                sock.tx_metadata_len = tx_metadata_len;
                 Ok(())
             } else {
                 Err(io::Error::new(io::ErrorKind::NotFound, "socket not found"))
             }
         }
         
-        pub fn set_ring_size(_fd: RawFd, _ring_type: i32, _size: u32) -> io::Result<()> {
+        pub fn set_ring_size(fd: RawFd, ring_type: i32, size: u32) -> io::Result<()> {
+            use super::if_xdp::*;
+            
+            let fd_idx = fd as usize;
+            let mut sockets = SOCKETS.lock().unwrap();
+            let sock = sockets.get_mut(&fd_idx).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "socket not found"))?;
+            
+            // Same constraint as the kernel, plus what the mock buffers can hold
+            if !size.is_power_of_two() || size > sock.ring_capacity {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid ring size"));
+            }
+            match ring_type {
+                XDP_RX_RING => sock.rx_size = size,
+                XDP_TX_RING => sock.tx_size = size,
+                XDP_UMEM_FILL_RING => sock.fill_size = size,
+                XDP_UMEM_COMPLETION_RING => sock.comp_size = size,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown ring type")),
+            }
             Ok(())
         }
         
@@ -107,6 +312,35 @@ pub mod sys {
              })
         }
         
+        pub fn get_xdp_options(_fd: RawFd) -> io::Result<u32> {
+            // The simulator always copies frames
+            Ok(0)
+        }
+
+        // The simulator has no socket buffers to size.
+        pub fn set_rcvbuf(_fd: RawFd, _bytes: usize) -> io::Result<()> {
+            Ok(())
+        }
+
+        pub fn set_sndbuf(_fd: RawFd, _bytes: usize) -> io::Result<()> {
+            Ok(())
+        }
+
+        pub fn get_rcvbuf(_fd: RawFd) -> io::Result<usize> {
+            Ok(0)
+        }
+
+        pub fn get_sndbuf(_fd: RawFd) -> io::Result<usize> {
+            Ok(0)
+        }
+
+        pub fn get_xdp_statistics(fd: RawFd) -> io::Result<super::if_xdp::XdpStatistics> {
+            // The simulator never drops on the RX side
+            let sockets = SOCKETS.lock().unwrap();
+            let sock = sockets.get(&(fd as usize)).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "socket not found"))?;
+            Ok(super::if_xdp::XdpStatistics { tx_invalid_descs: sock.tx_failures, ..Default::default() })
+        }
+        
         pub unsafe fn mmap_range(fd: RawFd, _len: usize, offset: u64) -> io::Result<*mut u8> {
             let fd_idx = fd as usize;
             let mut sockets = SOCKETS.lock().unwrap();
@@ -146,12 +380,46 @@ pub mod sys {
             pub desc: u64,
             pub flags: u64,
         }
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct XdpStatistics {
+            pub rx_dropped: u64,
+            pub rx_invalid_descs: u64,
+            pub tx_invalid_descs: u64,
+            pub rx_ring_full: u64,
+            pub rx_fill_ring_empty_descs: u64,
+            pub tx_ring_empty_descs: u64,
+        }
+        
+        pub const XDP_COPY: u16 = 2;
+        pub const XDP_ZEROCOPY: u16 = 4;
+        pub const XDP_USE_NEED_WAKEUP: u16 = 8;
+        pub const XDP_USE_SG: u16 = 16;
         
         pub const XDP_RX_RING: i32 = 0;
         pub const XDP_TX_RING: i32 = 1;
         pub const XDP_UMEM_REG: i32 = 4;
         pub const XDP_UMEM_FILL_RING: i32 = 5;
         pub const XDP_UMEM_COMPLETION_RING: i32 = 6;
+        pub const XDP_STATISTICS: i32 = 7;
+        pub const XDP_OPTIONS: i32 = 8;
+        
+        pub const XDP_OPTIONS_ZEROCOPY: u32 = 1;
+
+        pub const XDP_RING_NEED_WAKEUP: u32 = 1;
+        pub const XDP_PKT_CONTD: u32 = 1 << 0;
+        pub const XDP_TX_METADATA: u32 = 1 << 1;
+
+        pub const XDP_UMEM_TX_METADATA_LEN: u32 = 1 << 2;
+        pub const XDP_TXMD_FLAGS_LAUNCH_TIME: u64 = 1 << 2;
+
+        #[repr(C)]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct XskTxMetadata {
+            pub flags: u64,
+            pub csum_start: u16,
+            pub csum_offset: u16,
+            pub launch_time: u64,
+        }
         
         pub const XDP_PGOFF_RX_RING: u64 = 0;
         pub const XDP_PGOFF_TX_RING: u64 = 100; // Mock offsets to distinguish
@@ -163,6 +431,46 @@ pub mod sys {
         pub fn if_nametoindex(_name: &str) -> std::io::Result<u32> {
             Ok(1)
         }
+
+        /// Stand-in for `CLOCK_TAI`: the simulator's "NIC" runs on wall-clock time.
+        pub fn clock_tai_ns() -> u64 {
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+        }
+
+        // The simulated link is never promiscuous, and setting it does nothing.
+        pub fn promiscuous(_ifindex: u32) -> std::io::Result<bool> {
+            Ok(false)
+        }
+
+        pub fn set_promiscuous(_ifindex: u32, _enable: bool) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        // The simulator has no namespaces.
+        pub struct NetnsGuard;
+
+        impl NetnsGuard {
+            pub fn enter(_path: &str) -> std::io::Result<Self> {
+                Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "network namespaces require Linux"))
+            }
+
+            pub fn exit(self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct IfaceInfo {
+            pub ifindex: u32,
+            pub mtu: u32,
+            pub up: bool,
+            pub running: bool,
+        }
+        
+        // A standard Ethernet link that is always up.
+        pub fn interface_info(name: &str) -> std::io::Result<IfaceInfo> {
+            Ok(IfaceInfo { ifindex: if_nametoindex(name)?, mtu: 1500, up: true, running: true })
+        }
     }
 
     pub mod mmap {
@@ -176,11 +484,9 @@ pub mod sys {
         unsafe impl Sync for MmapArea {}
 
         impl MmapArea {
-            pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> Self {
-                Self {
-                    ptr: NonNull::new(ptr).expect("mmap returned null"),
-                    len,
-                }
+            pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> std::io::Result<Self> {
+                let ptr = NonNull::new(ptr).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "mmap returned null"))?;
+                Ok(Self { ptr, len })
             }
             pub fn as_ptr(&self) -> *mut u8 { self.ptr.as_ptr() }
             pub fn len(&self) -> usize { self.len }
@@ -201,14 +507,23 @@ pub mod umem {
         pub struct UmemLayout {
             pub frame_size: u32,
             pub frame_count: u32,
+            pub chunk_size: u32,
+            pub headroom: u32,
         }
         impl UmemLayout {
-             pub fn new(frame_size: u32, frame_count: u32) -> Self { Self { frame_size, frame_count } }
-             pub fn size(&self) -> usize { (self.frame_size as usize) * (self.frame_count as usize) }
+             pub fn new(frame_size: u32, frame_count: u32) -> Self { Self { frame_size, frame_count, chunk_size: frame_size, headroom: 0 } }
+             pub fn with_chunk_size(frame_size: u32, frame_count: u32, chunk_size: u32) -> Self { Self { frame_size, frame_count, chunk_size, headroom: 0 } }
+             pub fn with_headroom(mut self, headroom: u32) -> Self { self.headroom = headroom; self }
+             pub fn size(&self) -> usize { (self.chunk_size as usize) * (self.frame_count as usize) }
         }
     }
     
+    pub mod error {
+        pub use crate::umem_error::*;
+    }
+
     pub mod mmap {
+        use super::error::{check_frame_access, UmemError};
         use super::layout::UmemLayout;
         use std::io;
         use crate::windows_stubs::SOCKETS;
@@ -250,6 +565,27 @@ pub mod umem {
             }
             pub fn len(&self) -> usize { self.layout.size() }
             pub fn layout(&self) -> UmemLayout { self.layout }
+            // Simulator memory is heap-backed (alloc_zeroed / Vec), nothing to fault in.
+            pub fn prefault(&self) {}
+            pub fn frame(&self, idx: u32) -> Option<&[u8]> {
+                if idx >= self.layout.frame_count {
+                    return None;
+                }
+                let addr = idx as usize * self.layout.chunk_size as usize;
+                Some(unsafe { std::slice::from_raw_parts(self.as_ptr().add(addr), self.layout.frame_size as usize) })
+            }
+            pub fn frame_mut(&mut self, idx: u32) -> Option<&mut [u8]> {
+                if idx >= self.layout.frame_count {
+                    return None;
+                }
+                let addr = idx as usize * self.layout.chunk_size as usize;
+                Some(unsafe { std::slice::from_raw_parts_mut(self.as_ptr().add(addr), self.layout.frame_size as usize) })
+            }
+            pub fn write_frame(&self, addr: u64, data: &[u8]) -> Result<(), UmemError> {
+                check_frame_access(addr, data.len(), self.len(), self.layout.chunk_size)?;
+                unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), self.as_ptr().add(addr as usize), data.len()) };
+                Ok(())
+            }
         }
     }
 
@@ -274,12 +610,11 @@ pub mod ring {
     
     pub struct ProducerRing<T> {
         producer: *mut u32,
-        #[allow(dead_code)]
         consumer: *mut u32,
         descriptors: *mut T,
-        #[allow(dead_code)]
         size: u32,
         mask: u32,
+        flags: *mut u32,
     }
     unsafe impl<T> Send for ProducerRing<T> {}
 
@@ -287,15 +622,26 @@ pub mod ring {
         pub unsafe fn new(producer: *mut u32, consumer: *mut u32, descriptors: *mut T, size: u32) -> Self {
             Self { 
                 producer, consumer, descriptors, 
-                size, mask: size - 1 
+                size, mask: size - 1, flags: std::ptr::null_mut()
             }
         }
-        pub fn reserve(&mut self, _cnt: u32) -> Option<u32> { 
-            // Mock: Always reserve successfully
-            // In real kernel, we'd check if (prod + cnt) - cons <= size
+        pub unsafe fn with_flags(mut self, flags: *mut u32) -> Self {
+            self.flags = flags;
+            self
+        }
+        pub fn needs_wakeup(&self) -> bool {
+            !self.flags.is_null() && unsafe { *self.flags } & super::sys::if_xdp::XDP_RING_NEED_WAKEUP != 0
+        }
+        pub fn reserve(&mut self, cnt: u32) -> Option<u32> { 
+            if self.available() < cnt {
+                return None;
+            }
             let prod_idx = unsafe { *self.producer };
             Some(prod_idx)
         }
+        pub fn reserve_up_to(&mut self, cnt: u32) -> (u32, u32) {
+            (unsafe { *self.producer }, self.available().min(cnt))
+        }
         pub unsafe fn write_at(&mut self, idx: u32, item: T) {
              let offset = idx & self.mask;
              std::ptr::write(self.descriptors.add(offset as usize), item);
@@ -303,12 +649,12 @@ pub mod ring {
         pub fn submit(&mut self, idx: u32) {
             unsafe { *self.producer = idx };
         }
-        pub fn available(&self) -> usize { 
+        pub fn available(&self) -> u32 { 
             let prod = unsafe { *self.producer };
             let cons = unsafe { *self.consumer };
-            (self.size - prod.wrapping_sub(cons)) as usize
+            self.size - prod.wrapping_sub(cons)
         }
-        pub fn len(&self) -> usize { self.size as usize }
+        pub fn len(&self) -> u32 { self.size }
     }
     
     pub struct ConsumerRing<T> {
@@ -330,13 +676,15 @@ pub mod ring {
                  size, mask: size - 1, _cached_prod: 0 
              }
         }
-        pub fn peek(&mut self, _cnt: u32) -> u32 { 
+        pub fn peek(&mut self, cnt: u32) -> usize { 
+            // Surface any delayed packets that are now due (latency model).
+            crate::windows_stubs::flush_pending_rx();
             let prod = unsafe { *self.producer };
             let cons = unsafe { *self.consumer };
             let avail = prod.wrapping_sub(cons);
             // If avail huge (wrap w/o packets), it's 0. 
             // In u32 wrapping logic, (3 - 2) = 1. (2 - 3) = MAX.
-            if avail > 0x80000000 { 0 } else { avail }
+            if avail > 0x80000000 { 0 } else { avail.min(cnt) as usize }
         }
         pub unsafe fn read_at(&self, idx: u32) -> T {
              let offset = idx & self.mask;
@@ -348,12 +696,12 @@ pub mod ring {
         pub fn consumer_idx(&self) -> u32 { 
              unsafe { *self.consumer }
         }
-        pub fn available(&self) -> usize {
+        pub fn available(&self) -> u32 {
             let prod = unsafe { *self.producer };
             let cons = unsafe { *self.consumer };
-            prod.wrapping_sub(cons) as usize
+            prod.wrapping_sub(cons)
         }
-        pub fn len(&self) -> usize { self.size as usize }
+        pub fn len(&self) -> u32 { self.size }
     }
 }
 
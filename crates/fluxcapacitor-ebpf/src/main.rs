@@ -5,12 +5,39 @@ use aya_ebpf::{
     bindings::xdp_action,
     macros::{xdp, map},
     programs::XdpContext,
-    maps::XskMap,
+    maps::{Array, HashMap, PerCpuArray, XskMap},
 };
+use core::mem;
 
 #[map]
 static XSK_MAP: XskMap = XskMap::with_max_entries(64, 0);
 
+// Destination ports (host byte order) to redirect when filtering is enabled.
+#[map]
+static REDIRECT_PORTS: HashMap<u16, u8> = HashMap::with_max_entries(1024, 0);
+
+// Index 0: non-zero enables port filtering. Zero (default) redirects everything.
+#[map]
+static FILTER_CONFIG: Array<u32> = Array::with_max_entries(1, 0);
+
+// Index 0: Toeplitz key for spreading flows over XSK_MAP (see RSS_CONFIG).
+#[map]
+static RSS_KEY: Array<[u8; 40]> = Array::with_max_entries(1, 0);
+
+// Index 0: number of XSK_MAP slots flows are expected to spread over by RSS_KEY
+// hash. Zero (default) disables the check.
+#[map]
+static RSS_CONFIG: Array<u32> = Array::with_max_entries(1, 0);
+
+// Index 0: packets whose RSS_KEY slot was not the queue they arrived on.
+#[map]
+static RSS_MISMATCH: PerCpuArray<u64> = PerCpuArray::with_max_entries(1, 0);
+
+const ETH_HDR_LEN: usize = 14;
+const ETH_P_IP: u16 = 0x0800;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
 #[xdp]
 pub fn fluxcapacitor(ctx: XdpContext) -> u32 {
     match try_fluxcapacitor(ctx) {
@@ -20,8 +47,20 @@ pub fn fluxcapacitor(ctx: XdpContext) -> u32 {
 }
 
 fn try_fluxcapacitor(ctx: XdpContext) -> Result<u32, u32> {
+    if filter_enabled() && !port_wanted(&ctx) {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
     let queue_id = unsafe { (*ctx.ctx).rx_queue_index };
-    
+
+    // The kernel drops a redirect to a socket bound to another queue, so the
+    // flow hash can only be checked against the NIC's choice, not override it.
+    if let Some(slot) = rss_slot(&ctx) {
+        if slot != queue_id {
+            count_rss_mismatch();
+        }
+    }
+
     // Redirect to XSK socket bound to this queue
     if XSK_MAP.redirect(queue_id, 0).is_ok() {
          return Ok(xdp_action::XDP_REDIRECT);
@@ -30,6 +69,129 @@ fn try_fluxcapacitor(ctx: XdpContext) -> Result<u32, u32> {
     Ok(xdp_action::XDP_PASS)
 }
 
+#[inline(always)]
+fn count_rss_mismatch() {
+    if let Some(count) = RSS_MISMATCH.get_ptr_mut(0) {
+        unsafe { *count += 1 };
+    }
+}
+
+#[inline(always)]
+fn filter_enabled() -> bool {
+    matches!(FILTER_CONFIG.get(0), Some(enabled) if *enabled != 0)
+}
+
+/// True if the frame is IPv4 TCP/UDP with a destination port in `REDIRECT_PORTS`.
+#[inline(always)]
+fn port_wanted(ctx: &XdpContext) -> bool {
+    let eth_type = match ptr_at::<u16>(ctx, 12) {
+        Some(p) => u16::from_be(unsafe { *p }),
+        None => return false,
+    };
+    if eth_type != ETH_P_IP {
+        return false;
+    }
+
+    let ver_ihl = match ptr_at::<u8>(ctx, ETH_HDR_LEN) {
+        Some(p) => unsafe { *p },
+        None => return false,
+    };
+    let proto = match ptr_at::<u8>(ctx, ETH_HDR_LEN + 9) {
+        Some(p) => unsafe { *p },
+        None => return false,
+    };
+    if proto != IPPROTO_TCP && proto != IPPROTO_UDP {
+        return false;
+    }
+    // Too short an IP header, or a non-first fragment: no ports to look at.
+    if ver_ihl & 0x0F < 5 {
+        return false;
+    }
+    let frag = match ptr_at::<u16>(ctx, ETH_HDR_LEN + 6) {
+        Some(p) => u16::from_be(unsafe { *p }),
+        None => return false,
+    };
+    if frag & 0x1FFF != 0 {
+        return false;
+    }
+
+    // TCP and UDP both carry the destination port at offset 2.
+    let l4_off = ETH_HDR_LEN + ((ver_ihl & 0x0F) as usize) * 4;
+    let dst_port = match ptr_at::<u16>(ctx, l4_off + 2) {
+        Some(p) => u16::from_be(unsafe { *p }),
+        None => return false,
+    };
+
+    unsafe { REDIRECT_PORTS.get(&dst_port) }.is_some()
+}
+
+/// XSK_MAP slot for the frame's IPv4 TCP/UDP 4-tuple when RSS is configured.
+#[inline(always)]
+fn rss_slot(ctx: &XdpContext) -> Option<u32> {
+    let slots = *RSS_CONFIG.get(0)?;
+    if slots == 0 {
+        return None;
+    }
+    let key = RSS_KEY.get(0)?;
+
+    if u16::from_be(unsafe { *ptr_at::<u16>(ctx, 12)? }) != ETH_P_IP {
+        return None;
+    }
+    let ver_ihl = unsafe { *ptr_at::<u8>(ctx, ETH_HDR_LEN)? };
+    let proto = unsafe { *ptr_at::<u8>(ctx, ETH_HDR_LEN + 9)? };
+    if proto != IPPROTO_TCP && proto != IPPROTO_UDP || ver_ihl & 0x0F < 5 {
+        return None;
+    }
+    // Non-first fragments carry no ports.
+    let frag = u16::from_be(unsafe { *ptr_at::<u16>(ctx, ETH_HDR_LEN + 6)? });
+    if frag & 0x1FFF != 0 {
+        return None;
+    }
+
+    // Source and destination address, then source and destination port.
+    let mut input = [0u8; 12];
+    let addrs = unsafe { *ptr_at::<[u8; 8]>(ctx, ETH_HDR_LEN + 12)? };
+    let l4_off = ETH_HDR_LEN + ((ver_ihl & 0x0F) as usize) * 4;
+    let ports = unsafe { *ptr_at::<[u8; 4]>(ctx, l4_off)? };
+    input[..8].copy_from_slice(&addrs);
+    input[8..].copy_from_slice(&ports);
+
+    Some(toeplitz_hash(key, &input) % slots)
+}
+
+/// Same hash as `fluxcapacitor::rss::toeplitz_hash`, for a fixed-size input.
+#[inline(always)]
+fn toeplitz_hash(key: &[u8; 40], input: &[u8; 12]) -> u32 {
+    let mut hash = 0u32;
+    let mut window = u32::from_be_bytes([key[0], key[1], key[2], key[3]]);
+    let mut i = 0;
+    while i < 12 {
+        let byte = input[i];
+        let next = key[i + 4];
+        let mut bit = 0;
+        while bit < 8 {
+            if byte & (0x80 >> bit) != 0 {
+                hash ^= window;
+            }
+            window = (window << 1) | ((next >> (7 - bit)) & 1) as u32;
+            bit += 1;
+        }
+        i += 1;
+    }
+    hash
+}
+
+/// Bounds-checked pointer into the packet, as the verifier requires.
+#[inline(always)]
+fn ptr_at<T>(ctx: &XdpContext, offset: usize) -> Option<*const T> {
+    let start = ctx.data();
+    let end = ctx.data_end();
+    if start + offset + mem::size_of::<T>() > end {
+        return None;
+    }
+    Some((start + offset) as *const T)
+}
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
@@ -0,0 +1,177 @@
+//! Single-pass walk over a frame's headers, reported to a visitor.
+
+use crate::ethernet::{ETH_P_IP, ETH_P_IPV6};
+use crate::ipv6::Ipv6Upper;
+use crate::transport::{dispatch, Transport};
+use crate::{EthHeader, IcmpHeader, Ipv4Header, Ipv6Header, TcpHeader, UdpHeader, VlanTag};
+
+// QinQ is two tags; anything deeper is not worth following.
+const MAX_VLAN_TAGS: usize = 2;
+
+/// Callbacks for `dissect`, one per header, outermost first.
+///
+/// Every method has an empty default, so a visitor only overrides the layers it
+/// cares about. Transport callbacks also get the data after the header.
+pub trait Dissector {
+    fn on_ethernet(&mut self, _eth: &EthHeader) {}
+    fn on_vlan(&mut self, _tag: &VlanTag) {}
+    fn on_ipv4(&mut self, _ip: &Ipv4Header) {}
+    /// Called with the fixed header; extension headers are skipped.
+    fn on_ipv6(&mut self, _ip: &Ipv6Header) {}
+    fn on_tcp(&mut self, _tcp: &TcpHeader, _payload: &[u8]) {}
+    fn on_udp(&mut self, _udp: &UdpHeader, _payload: &[u8]) {}
+    /// ICMP for IPv4, ICMPv6 for IPv6.
+    fn on_icmp(&mut self, _icmp: &IcmpHeader, _payload: &[u8]) {}
+}
+
+/// Parse `frame` from Ethernet down to the transport header, calling `visitor`
+/// for each header found.
+///
+/// Stops quietly at the first layer it does not understand or that is truncated,
+/// so the callbacks seen describe how far the frame could be parsed. Non-first
+/// fragments end after the IP layer.
+pub fn dissect<D: Dissector + ?Sized>(frame: &[u8], visitor: &mut D) {
+    let Some((eth, mut payload)) = crate::parse_eth(frame) else {
+        return;
+    };
+    visitor.on_ethernet(eth);
+
+    let mut eth_type = eth.eth_type();
+    for _ in 0..MAX_VLAN_TAGS {
+        if !crate::vlan::is_vlan(eth_type) {
+            break;
+        }
+        let Some((tag, rest)) = crate::parse_vlan(payload) else {
+            return;
+        };
+        visitor.on_vlan(tag);
+        eth_type = tag.eth_type();
+        payload = rest;
+    }
+
+    let transport = match eth_type {
+        ETH_P_IP => {
+            let Some((ip, l4)) = crate::parse_ipv4(payload) else {
+                return;
+            };
+            visitor.on_ipv4(ip);
+            if u16::from_be(ip.frag_off) & 0x1FFF != 0 {
+                return;
+            }
+            dispatch(ip.proto, l4)
+        }
+        ETH_P_IPV6 => {
+            let Some((ip, rest)) = crate::parse_ipv6(payload) else {
+                return;
+            };
+            visitor.on_ipv6(ip);
+            match crate::ipv6::skip_extension_headers(ip.next_header, rest) {
+                Some(Ipv6Upper::Proto(proto, l4)) => dispatch(proto, l4),
+                _ => return,
+            }
+        }
+        _ => return,
+    };
+
+    match transport {
+        Transport::Tcp(tcp, payload) => visitor.on_tcp(tcp, payload),
+        Transport::Udp(udp, payload) => visitor.on_udp(udp, payload),
+        Transport::Icmp(icmp, payload) => visitor.on_icmp(icmp, payload),
+        Transport::Fragmented | Transport::Other(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vlan::ETH_P_8021Q;
+
+    #[derive(Default)]
+    struct Stack(Vec<String>);
+
+    impl Dissector for Stack {
+        fn on_ethernet(&mut self, _eth: &EthHeader) {
+            self.0.push("eth".into());
+        }
+        fn on_vlan(&mut self, tag: &VlanTag) {
+            self.0.push(format!("vlan{}", tag.vid()));
+        }
+        fn on_ipv4(&mut self, _ip: &Ipv4Header) {
+            self.0.push("ipv4".into());
+        }
+        fn on_udp(&mut self, udp: &UdpHeader, payload: &[u8]) {
+            self.0.push(format!("udp:{} ({} bytes)", udp.dst_port(), payload.len()));
+        }
+    }
+
+    // Eth + 802.1Q (VID 42) + IPv4 + UDP to port 53 with 4 payload bytes.
+    fn tagged_udp() -> Vec<u8> {
+        let mut frame = vec![0u8; 14 + 4 + 20 + 8 + 4];
+        frame[12..14].copy_from_slice(&ETH_P_8021Q.to_be_bytes());
+        frame[14..16].copy_from_slice(&42u16.to_be_bytes());
+        frame[16..18].copy_from_slice(&ETH_P_IP.to_be_bytes());
+        frame[18] = 0x45;
+        frame[18 + 9] = 17;
+        frame[38 + 2..38 + 4].copy_from_slice(&53u16.to_be_bytes());
+        frame[38 + 4..38 + 6].copy_from_slice(&12u16.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_dissect_tagged_udp() {
+        let mut stack = Stack::default();
+        dissect(&tagged_udp(), &mut stack);
+        assert_eq!(stack.0, ["eth", "vlan42", "ipv4", "udp:53 (4 bytes)"]);
+    }
+
+    #[test]
+    fn test_dissect_stops_at_truncation() {
+        let frame = tagged_udp();
+        let mut stack = Stack::default();
+        dissect(&frame[..30], &mut stack);
+        assert_eq!(stack.0, ["eth", "vlan42"]);
+
+        // A visitor with no overrides is fine too
+        struct Nothing;
+        impl Dissector for Nothing {}
+        dissect(&frame, &mut Nothing);
+    }
+
+    #[test]
+    fn test_dissect_random_frames_bounded() {
+        #[derive(Default)]
+        struct Count(usize);
+        impl Dissector for Count {
+            fn on_ethernet(&mut self, _: &EthHeader) { self.0 += 1 }
+            fn on_vlan(&mut self, _: &VlanTag) { self.0 += 1 }
+            fn on_ipv4(&mut self, _: &Ipv4Header) { self.0 += 1 }
+            fn on_ipv6(&mut self, _: &Ipv6Header) { self.0 += 1 }
+            fn on_tcp(&mut self, _: &TcpHeader, _: &[u8]) { self.0 += 1 }
+            fn on_udp(&mut self, _: &UdpHeader, _: &[u8]) { self.0 += 1 }
+            fn on_icmp(&mut self, _: &IcmpHeader, _: &[u8]) { self.0 += 1 }
+        }
+
+        // Endless VLAN tags stop at MAX_VLAN_TAGS
+        let mut qinq = vec![0u8; 14 + 4 * 32];
+        for off in (12..qinq.len() - 2).step_by(4) {
+            qinq[off..off + 2].copy_from_slice(&ETH_P_8021Q.to_be_bytes());
+        }
+        let mut count = Count::default();
+        dissect(&qinq, &mut count);
+        assert_eq!(count.0, 1 + MAX_VLAN_TAGS);
+
+        let mut next = crate::xorshift(0x9E37_79B9_7F4A_7C15);
+        let types = [ETH_P_8021Q, ETH_P_IP, ETH_P_IPV6];
+        for _ in 0..10_000 {
+            let mut frame = vec![0u8; (next() % 128) as usize];
+            frame.iter_mut().for_each(|b| *b = next() as u8);
+            if frame.len() >= 14 {
+                let eth_type = types[(next() % types.len() as u64) as usize];
+                frame[12..14].copy_from_slice(&eth_type.to_be_bytes());
+            }
+            let mut count = Count::default();
+            dissect(&frame, &mut count);
+            assert!(count.0 <= 1 + MAX_VLAN_TAGS + 2);
+        }
+    }
+}
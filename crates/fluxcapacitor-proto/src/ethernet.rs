@@ -1,4 +1,4 @@
-
+use crate::ParseError;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
@@ -19,15 +19,21 @@ impl EthHeader {
 }
 
 pub fn parse_eth(data: &[u8]) -> Option<(&EthHeader, &[u8])> {
-    if data.len() < std::mem::size_of::<EthHeader>() {
-        return None;
+    try_parse_eth(data).ok().flatten()
+}
+
+/// Like `parse_eth`, but reports truncation as an error.
+pub fn try_parse_eth(data: &[u8]) -> Result<Option<(&EthHeader, &[u8])>, ParseError> {
+    let needed = std::mem::size_of::<EthHeader>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
     }
     
     let ptr = data.as_ptr() as *const EthHeader;
     let header = unsafe { &*ptr };
-    let payload = &data[std::mem::size_of::<EthHeader>()..];
+    let payload = &data[needed..];
     
-    Some((header, payload))
+    Ok(Some((header, payload)))
 }
 
 #[cfg(test)]
@@ -53,5 +59,6 @@ mod tests {
     fn test_eth_too_short() {
         let data = [0u8; 13];
         assert!(parse_eth(&data).is_none());
+        assert_eq!(try_parse_eth(&data).unwrap_err(), ParseError::Truncated { needed: 14, got: 13 });
     }
 }
@@ -1,3 +1,5 @@
+use crate::{Ipv4Header, ParseError};
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
 pub struct IcmpHeader {
@@ -10,18 +12,99 @@ impl IcmpHeader {
     pub fn checksum(&self) -> u16 {
         u16::from_be(self.check)
     }
+
+    /// Same as `Checksum::verify` with the data after the header.
+    pub fn verify_checksum(&self, payload: &[u8]) -> bool {
+        crate::Checksum::verify(self, payload)
+    }
+
+    /// Whether this is an error message quoting the packet that caused it:
+    /// Destination Unreachable, Source Quench, Redirect, Time Exceeded or
+    /// Parameter Problem.
+    pub fn is_error(&self) -> bool {
+        matches!(self.kind, 3 | 4 | 5 | 11 | 12)
+    }
+
+    /// The quoted IP header and the (usually 8) bytes following it, for an error
+    /// message with ICMP body `payload` (as returned by `parse_icmp`).
+    pub fn inner_packet<'a>(&self, payload: &'a [u8]) -> Option<(&'a Ipv4Header, &'a [u8])> {
+        if !self.is_error() {
+            return None;
+        }
+        // The 4 bytes after the checksum are unused or type-specific (MTU, gateway).
+        crate::parse_ipv4(payload.get(4..)?)
+    }
+}
+
+/// An ICMP error message and the offending packet it quotes (RFC 792).
+#[derive(Debug, Clone, Copy)]
+pub struct IcmpError<'a> {
+    pub header: &'a IcmpHeader,
+    /// Type-specific word after the checksum, e.g. the next-hop MTU.
+    pub rest: [u8; 4],
+    pub inner: &'a Ipv4Header,
+    /// Start of the inner transport header; RFC 792 only guarantees 8 bytes.
+    pub inner_payload: &'a [u8],
+}
+
+impl<'a> IcmpError<'a> {
+    /// Parse the error view from `parse_icmp` output. `None` for non-error types
+    /// or a truncated quote.
+    pub fn parse(header: &'a IcmpHeader, payload: &'a [u8]) -> Option<Self> {
+        let (inner, inner_payload) = header.inner_packet(payload)?;
+        Some(Self { header, rest: payload[..4].try_into().ok()?, inner, inner_payload })
+    }
+
+    /// Source and destination port of the quoted TCP or UDP header, as sent by
+    /// the original sender.
+    pub fn inner_ports(&self) -> Option<(u16, u16)> {
+        if !matches!(self.inner.proto, 6 | 17) {
+            return None;
+        }
+        let ports = self.inner_payload.get(..4)?;
+        Some((u16::from_be_bytes([ports[0], ports[1]]), u16::from_be_bytes([ports[2], ports[3]])))
+    }
+
+    /// Next-hop MTU of a Fragmentation Needed message (type 3 code 4, RFC 1191).
+    pub fn next_hop_mtu(&self) -> Option<u16> {
+        if self.header.kind != 3 || self.header.code != 4 {
+            return None;
+        }
+        Some(u16::from_be_bytes([self.rest[2], self.rest[3]]))
+    }
+}
+
+/// ICMPv4 checksum over the header and `payload` (the rest of the message).
+/// ICMPv6 also covers an IPv6 pseudo-header, which this does not.
+impl crate::Checksum for IcmpHeader {
+    type Context<'a> = &'a [u8];
+
+    fn compute(&self, payload: &[u8]) -> u16 {
+        let sum = u16::from_be_bytes([self.kind, self.code]) as u64;
+        crate::fold_sum(crate::sum_words(sum, payload))
+    }
+
+    fn verify(&self, payload: &[u8]) -> bool {
+        self.compute(payload) == self.checksum()
+    }
 }
 
 pub fn parse_icmp(data: &[u8]) -> Option<(&IcmpHeader, &[u8])> {
-    if data.len() < std::mem::size_of::<IcmpHeader>() {
-        return None;
+    try_parse_icmp(data).ok().flatten()
+}
+
+/// Like `parse_icmp`, but reports truncation as an error.
+pub fn try_parse_icmp(data: &[u8]) -> Result<Option<(&IcmpHeader, &[u8])>, ParseError> {
+    let needed = std::mem::size_of::<IcmpHeader>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
     }
     
     let ptr = data.as_ptr() as *const IcmpHeader;
     let header = unsafe { &*ptr };
-    let payload = &data[std::mem::size_of::<IcmpHeader>()..];
+    let payload = &data[needed..];
     
-    Some((header, payload))
+    Ok(Some((header, payload)))
 }
 
 #[cfg(test)]
@@ -40,5 +123,56 @@ mod tests {
         assert_eq!(header.kind, 8);
         assert_eq!(header.code, 0);
         assert_eq!(payload, &[0x11, 0x22, 0x33, 0x44]);
+        assert!(header.inner_packet(payload).is_none());
+    }
+
+    #[test]
+    fn test_time_exceeded_inner_udp() {
+        // ICMP Time Exceeded quoting an IPv4/UDP traceroute probe
+        let mut data = [0u8; 8 + 20 + 8];
+        data[0] = 11; // Time Exceeded
+        data[1] = 0; // TTL expired in transit
+
+        let ip = &mut data[8..28];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&60u16.to_be_bytes());
+        ip[8] = 1;
+        ip[9] = 17; // UDP
+        ip[12..16].copy_from_slice(&[192, 168, 1, 10]);
+        ip[16..20].copy_from_slice(&[8, 8, 8, 8]);
+
+        let udp = &mut data[28..];
+        udp[0..2].copy_from_slice(&51000u16.to_be_bytes());
+        udp[2..4].copy_from_slice(&33434u16.to_be_bytes());
+        udp[4..6].copy_from_slice(&40u16.to_be_bytes());
+
+        let (header, payload) = parse_icmp(&data).expect("Should parse icmp");
+        assert!(header.is_error());
+        let (inner, l4) = header.inner_packet(payload).expect("Should parse inner packet");
+        assert_eq!(inner.dst(), u32::from_be_bytes([8, 8, 8, 8]));
+        assert_eq!(l4.len(), 8);
+
+        let error = IcmpError::parse(header, payload).expect("Should parse icmp error");
+        assert_eq!(error.inner.src(), u32::from_be_bytes([192, 168, 1, 10]));
+        assert_eq!(error.inner_ports(), Some((51000, 33434)));
+        assert_eq!(error.next_hop_mtu(), None);
+
+        // Quote cut short inside the IP header
+        assert!(header.inner_packet(&payload[..16]).is_none());
+    }
+
+    #[test]
+    fn test_fragmentation_needed_mtu() {
+        let mut data = [0u8; 8 + 20 + 8];
+        data[0] = 3; // Destination Unreachable
+        data[1] = 4; // Fragmentation Needed
+        data[6..8].copy_from_slice(&1400u16.to_be_bytes());
+        data[8] = 0x45;
+        data[8 + 9] = 6; // TCP
+
+        let (header, payload) = parse_icmp(&data).expect("Should parse icmp");
+        let error = IcmpError::parse(header, payload).expect("Should parse icmp error");
+        assert_eq!(error.next_hop_mtu(), Some(1400));
+        assert_eq!(error.inner_ports(), Some((0, 0)));
     }
 }
@@ -1,3 +1,46 @@
+use crate::ParseError;
+use std::net::Ipv4Addr;
+
+/// IP protocol number, as in `Ipv4Header::proto` or the IPv6 next header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpProto {
+    Icmp,
+    Tcp,
+    Udp,
+    Gre,
+    Icmpv6,
+    Sctp,
+    Other(u8),
+}
+
+impl From<u8> for IpProto {
+    fn from(proto: u8) -> Self {
+        match proto {
+            1 => IpProto::Icmp,
+            6 => IpProto::Tcp,
+            17 => IpProto::Udp,
+            47 => IpProto::Gre,
+            58 => IpProto::Icmpv6,
+            132 => IpProto::Sctp,
+            other => IpProto::Other(other),
+        }
+    }
+}
+
+impl From<IpProto> for u8 {
+    fn from(proto: IpProto) -> Self {
+        match proto {
+            IpProto::Icmp => 1,
+            IpProto::Tcp => 6,
+            IpProto::Udp => 17,
+            IpProto::Gre => 47,
+            IpProto::Icmpv6 => 58,
+            IpProto::Sctp => 132,
+            IpProto::Other(other) => other,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
 pub struct Ipv4Header {
@@ -21,7 +64,39 @@ impl Ipv4Header {
     pub fn ihl(&self) -> u8 {
         self.ver_ihl & 0x0F
     }
+
+    pub fn protocol(&self) -> IpProto {
+        IpProto::from(self.proto)
+    }
     
+    /// Differentiated Services Code Point, the top 6 bits of the TOS byte.
+    pub fn dscp(&self) -> u8 {
+        self.tos >> 2
+    }
+
+    /// Explicit Congestion Notification, the low 2 bits of the TOS byte.
+    pub fn ecn(&self) -> u8 {
+        self.tos & 0x03
+    }
+
+    /// Set the DSCP (6 bits), keeping ECN and patching the checksum.
+    pub fn set_dscp(&mut self, dscp: u8) {
+        self.set_tos(((dscp & 0x3F) << 2) | self.ecn());
+    }
+
+    /// Set the ECN codepoint (2 bits), keeping DSCP and patching the checksum.
+    pub fn set_ecn(&mut self, ecn: u8) {
+        self.set_tos((self.tos & !0x03) | (ecn & 0x03));
+    }
+
+    fn set_tos(&mut self, tos: u8) {
+        // TOS shares a 16-bit word with version/IHL.
+        let old_word = u16::from_be_bytes([self.ver_ihl, self.tos]);
+        self.tos = tos;
+        let new_word = u16::from_be_bytes([self.ver_ihl, self.tos]);
+        self.check = crate::checksum_adjust(self.checksum(), old_word, new_word).to_be();
+    }
+
     pub fn total_len(&self) -> u16 {
         u16::from_be(self.total_len)
     }
@@ -34,10 +109,40 @@ impl Ipv4Header {
         u32::from_be(self.dst)
     }
 
+    pub fn src_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.src())
+    }
+
+    pub fn dst_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.dst())
+    }
+
     pub fn header_len(&self) -> usize {
         (self.ihl() as usize) * 4
     }
 
+    pub fn checksum(&self) -> u16 {
+        u16::from_be(self.check)
+    }
+
+    /// Decrement the TTL for forwarding, patching the checksum incrementally (RFC 1624).
+    ///
+    /// Returns `false` when the TTL reaches (or already was) zero: the packet must not be
+    /// forwarded, and the caller should drop it or answer with ICMP Time Exceeded.
+    pub fn decrement_ttl(&mut self) -> bool {
+        if self.ttl == 0 {
+            return false;
+        }
+
+        // TTL shares a 16-bit word with the protocol field.
+        let old_word = u16::from_be_bytes([self.ttl, self.proto]);
+        self.ttl -= 1;
+        let new_word = u16::from_be_bytes([self.ttl, self.proto]);
+        self.check = crate::checksum_adjust(self.checksum(), old_word, new_word).to_be();
+
+        self.ttl != 0
+    }
+
     pub fn is_valid(&self) -> bool {
          let len = self.header_len();
          let ptr = self as *const Ipv4Header as *const u8;
@@ -46,31 +151,121 @@ impl Ipv4Header {
     }
 }
 
-pub fn parse_ipv4(data: &[u8]) -> Option<(&Ipv4Header, &[u8])> {
-    if data.len() < std::mem::size_of::<Ipv4Header>() {
+/// Header checksum, options included. Like `is_valid`, this reads `header_len`
+/// bytes, so the header must come from a parser.
+impl crate::Checksum for Ipv4Header {
+    type Context<'a> = ();
+
+    fn compute(&self, _ctx: ()) -> u16 {
+        let ptr = self as *const Ipv4Header as *const u8;
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, self.header_len()) };
+        // Skip the checksum field (bytes 10..12)
+        crate::fold_sum(crate::sum_words(crate::sum_words(0, &bytes[..10]), &bytes[12..]))
+    }
+
+    fn verify(&self, _ctx: ()) -> bool {
+        self.is_valid()
+    }
+}
+
+/// `(src, dst, protocol)`, e.g. as a routing or flow table key.
+impl From<&Ipv4Header> for (Ipv4Addr, Ipv4Addr, u8) {
+    fn from(header: &Ipv4Header) -> Self {
+        (header.src_addr(), header.dst_addr(), header.proto)
+    }
+}
+
+/// Remove the options of the IPv4 packet at the start of `packet`, in place: the
+/// payload (and anything after it) moves up to follow the 20-byte header, IHL
+/// becomes 5, and `total_len` and the checksum are updated. Transport checksums
+/// do not cover IP options and stay valid.
+///
+/// Returns how many bytes were removed (0 without options); the packet is that
+/// much shorter and the caller trims its buffer. `None`, leaving the bytes alone,
+/// if `packet` is not a well-formed IPv4 packet. Which options may be dropped
+/// is up to the caller (RFC 7126 discusses the common ones).
+pub fn strip_options(packet: &mut [u8]) -> Option<usize> {
+    let (header, _) = parse_ipv4(packet)?;
+    let header_len = header.header_len();
+    let total_len = header.total_len() as usize;
+    if total_len < header_len || total_len > packet.len() {
         return None;
     }
+    let removed = header_len - 20;
+    if removed == 0 {
+        return Some(0);
+    }
+
+    packet.copy_within(header_len.., 20);
+    packet[0] = 0x45;
+    packet[2..4].copy_from_slice(&((total_len - removed) as u16).to_be_bytes());
+    packet[10..12].fill(0);
+    let check = crate::checksum(&packet[..20]);
+    packet[10..12].copy_from_slice(&check.to_be_bytes());
+    Some(removed)
+}
+
+pub fn parse_ipv4(data: &[u8]) -> Option<(&Ipv4Header, &[u8])> {
+    try_parse_ipv4(data).ok().flatten()
+}
+
+/// Like `parse_ipv4`, but distinguishes "not IPv4" (`Ok(None)`) from a truncated
+/// or malformed header (`Err`).
+pub fn try_parse_ipv4(data: &[u8]) -> Result<Option<(&Ipv4Header, &[u8])>, ParseError> {
+    // The version nibble is all we need to tell whether this is IPv4 at all.
+    match data.first() {
+        Some(b) if b >> 4 != 4 => return Ok(None),
+        _ => {}
+    }
+
+    let needed = std::mem::size_of::<Ipv4Header>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
+    }
     
     let ptr = data.as_ptr() as *const Ipv4Header;
     let header = unsafe { &*ptr };
-    
-    if header.version() != 4 {
-        return None;
-    }
 
     let header_len = header.header_len();
+    if header_len < needed {
+        return Err(ParseError::Malformed);
+    }
     if data.len() < header_len {
-        return None;
+        return Err(ParseError::Truncated { needed: header_len, got: data.len() });
     }
 
     let payload = &data[header_len..];
-    Some((header, payload))
+    Ok(Some((header, payload)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ip_proto_numbers() {
+        let known = [
+            (1, IpProto::Icmp),
+            (6, IpProto::Tcp),
+            (17, IpProto::Udp),
+            (47, IpProto::Gre),
+            (58, IpProto::Icmpv6),
+            (132, IpProto::Sctp),
+        ];
+        for (number, proto) in known {
+            assert_eq!(IpProto::from(number), proto);
+            assert_eq!(u8::from(proto), number);
+        }
+        assert_eq!(IpProto::from(89), IpProto::Other(89));
+        assert_eq!(u8::from(IpProto::Other(89)), 89);
+
+        let mut data = [0u8; 20];
+        data[0] = 0x45;
+        data[9] = 17;
+        let (header, _) = parse_ipv4(&data).expect("Should parse ipv4");
+        assert_eq!(header.protocol(), IpProto::Udp);
+    }
+
     #[test]
     fn test_ipv4_parsing() {
         let mut data = [0u8; 24];
@@ -92,9 +287,93 @@ mod tests {
         assert_eq!(header.header_len(), 20);
         assert_eq!(header.proto, 17);
         assert!(header.is_valid());
+        let key: (Ipv4Addr, Ipv4Addr, u8) = header.into();
+        assert_eq!(key, (Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 100), 17));
         assert_eq!(payload, &[0x11, 0x22, 0x33, 0x44]);
     }
 
+    #[test]
+    fn test_decrement_ttl_incremental_checksum() {
+        let mut data = [0u8; 20];
+        data[0] = 0x45;
+        data[2..4].copy_from_slice(&20u16.to_be_bytes());
+        data[8] = 2; // TTL
+        data[9] = 6; // TCP
+        data[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        data[16..20].copy_from_slice(&[10, 0, 0, 2]);
+        let csum = crate::checksum(&data);
+        data[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let header = unsafe { &mut *(data.as_mut_ptr() as *mut Ipv4Header) };
+
+        // 2 -> 1: still forwardable
+        assert!(header.decrement_ttl());
+        assert_eq!(header.ttl, 1);
+        let incremental = header.checksum();
+        assert!(header.is_valid());
+
+        // Full recompute over the same header must agree
+        let mut copy = data;
+        copy[10..12].copy_from_slice(&[0, 0]);
+        assert_eq!(incremental, crate::checksum(&copy));
+
+        // 1 -> 0: expired
+        let header = unsafe { &mut *(data.as_mut_ptr() as *mut Ipv4Header) };
+        assert!(!header.decrement_ttl());
+        assert_eq!(header.ttl, 0);
+        assert!(header.is_valid());
+
+        // Already 0: left untouched
+        assert!(!header.decrement_ttl());
+        assert_eq!(header.ttl, 0);
+    }
+
+    #[test]
+    fn test_dscp_ecn() {
+        let mut data = [0u8; 20];
+        data[0] = 0x45;
+        data[1] = 0x02; // DSCP 0, ECN ECT(0)
+        data[8] = 64;
+        data[9] = 17;
+        let csum = crate::checksum(&data);
+        data[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let header = unsafe { &mut *(data.as_mut_ptr() as *mut Ipv4Header) };
+        assert_eq!(header.dscp(), 0);
+        assert_eq!(header.ecn(), 0b10);
+
+        // Expedited Forwarding
+        header.set_dscp(46);
+        assert_eq!(header.dscp(), 46);
+        assert_eq!(header.ecn(), 0b10);
+        assert_eq!(header.tos, 0xBA);
+        assert!(header.is_valid());
+
+        // Congestion Experienced, DSCP untouched
+        header.set_ecn(0b11);
+        assert_eq!(header.dscp(), 46);
+        assert_eq!(header.ecn(), 0b11);
+        assert!(header.is_valid());
+    }
+
+    #[test]
+    fn test_ipv4_wrong_protocol_vs_truncated() {
+        // IPv6 version nibble: not our protocol, not an error
+        let v6 = [0x60u8; 40];
+        assert!(matches!(try_parse_ipv4(&v6), Ok(None)));
+
+        // IPv4 cut short
+        let mut data = [0u8; 12];
+        data[0] = 0x45;
+        assert_eq!(try_parse_ipv4(&data).unwrap_err(), ParseError::Truncated { needed: 20, got: 12 });
+
+        // IHL below the minimum
+        let mut data = [0u8; 20];
+        data[0] = 0x44;
+        assert_eq!(try_parse_ipv4(&data).unwrap_err(), ParseError::Malformed);
+        assert!(parse_ipv4(&data).is_none());
+    }
+
     #[test]
     fn test_ipv4_with_options() {
         let mut data = [0u8; 28];
@@ -105,4 +384,32 @@ mod tests {
         assert_eq!(header.header_len(), 28);
         assert_eq!(payload.len(), 0);
     }
+
+    #[test]
+    fn test_strip_options() {
+        // IHL 6 with a Router Alert option, 4 payload bytes, 2 bytes of padding
+        let mut data = [0u8; 30];
+        data[0] = 0x46;
+        data[2..4].copy_from_slice(&28u16.to_be_bytes());
+        data[8] = 1;
+        data[9] = 2; // IGMP
+        data[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        data[16..20].copy_from_slice(&[224, 0, 0, 22]);
+        data[20..24].copy_from_slice(&[0x94, 0x04, 0x00, 0x00]);
+        data[24..28].copy_from_slice(b"igmp");
+        let check = crate::checksum(&data[..24]);
+        data[10..12].copy_from_slice(&check.to_be_bytes());
+
+        assert_eq!(strip_options(&mut data), Some(4));
+        let (header, payload) = parse_ipv4(&data[..26]).expect("Should parse ipv4");
+        assert_eq!((header.ihl(), header.total_len()), (5, 24));
+        assert!(header.is_valid());
+        assert_eq!(header.dst_addr(), Ipv4Addr::new(224, 0, 0, 22));
+        assert_eq!(&payload[..4], b"igmp");
+
+        // Nothing left to strip; a total length past the buffer is refused
+        assert_eq!(strip_options(&mut data[..26]), Some(0));
+        data[2..4].copy_from_slice(&100u16.to_be_bytes());
+        assert_eq!(strip_options(&mut data), None);
+    }
 }
@@ -0,0 +1,236 @@
+use crate::ParseError;
+use std::net::Ipv6Addr;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct Ipv6Header {
+    pub ver_tc_flow: u32,
+    pub payload_len: u16,
+    pub next_header: u8,
+    pub hop_limit: u8,
+    pub src: [u8; 16],
+    pub dst: [u8; 16],
+}
+
+impl Ipv6Header {
+    pub fn version(&self) -> u8 {
+        (u32::from_be(self.ver_tc_flow) >> 28) as u8
+    }
+
+    pub fn payload_len(&self) -> u16 {
+        u16::from_be(self.payload_len)
+    }
+
+    pub fn src_addr(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.src)
+    }
+
+    pub fn dst_addr(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.dst)
+    }
+
+    /// Rewrite the source address. IPv6 has no header checksum, but TCP/UDP/ICMPv6
+    /// checksums cover it through the pseudo-header and must be fixed separately.
+    pub fn set_src_addr(&mut self, addr: Ipv6Addr) {
+        self.src = addr.octets();
+    }
+
+    /// Rewrite the destination address; see `set_src_addr`.
+    pub fn set_dst_addr(&mut self, addr: Ipv6Addr) {
+        self.dst = addr.octets();
+    }
+
+    /// Whether the destination is a multicast group (`ff00::/8`).
+    pub fn is_multicast(&self) -> bool {
+        self.dst_addr().is_multicast()
+    }
+
+    /// Whether either address is link-local unicast (`fe80::/10`). Such packets
+    /// must not be forwarded off the link.
+    pub fn is_link_local(&self) -> bool {
+        self.src_addr().is_unicast_link_local() || self.dst_addr().is_unicast_link_local()
+    }
+}
+
+// Extension headers that can precede the transport header (RFC 8200, section 4).
+const NEXTHDR_HOP: u8 = 0;
+const NEXTHDR_ROUTING: u8 = 43;
+const NEXTHDR_FRAGMENT: u8 = 44;
+const NEXTHDR_AUTH: u8 = 51;
+const NEXTHDR_DEST: u8 = 60;
+
+// Bound on the extension header chain, so a crafted packet cannot make us loop.
+const MAX_EXT_HEADERS: usize = 8;
+
+/// Upper-layer protocol found after walking the extension header chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ipv6Upper<'a> {
+    /// Transport protocol number and the data following all extension headers.
+    Proto(u8, &'a [u8]),
+    /// A Fragment header that is not an atomic fragment: the transport header is
+    /// either in another fragment or must not be trusted before reassembly.
+    Fragmented,
+}
+
+/// Skip extension headers starting at `next_header` (the fixed header's field)
+/// over `payload` (the data after the fixed header).
+///
+/// Hop-by-Hop, Routing, Destination Options and Authentication headers are
+/// skipped. Returns `None` if the chain is truncated or longer than we follow.
+pub fn skip_extension_headers(mut next_header: u8, mut payload: &[u8]) -> Option<Ipv6Upper<'_>> {
+    for _ in 0..MAX_EXT_HEADERS {
+        let len = match next_header {
+            NEXTHDR_HOP | NEXTHDR_ROUTING | NEXTHDR_DEST => (*payload.get(1)? as usize + 1) * 8,
+            NEXTHDR_AUTH => (*payload.get(1)? as usize + 2) * 4,
+            NEXTHDR_FRAGMENT => {
+                let frag = payload.get(..8)?;
+                // Offset (13 bits) and M flag; both zero means an atomic fragment (RFC 6946).
+                if u16::from_be_bytes([frag[2], frag[3]]) & 0xFFF9 != 0 {
+                    return Some(Ipv6Upper::Fragmented);
+                }
+                8
+            }
+            proto => return Some(Ipv6Upper::Proto(proto, payload)),
+        };
+        if payload.len() < len {
+            return None;
+        }
+        next_header = payload[0];
+        payload = &payload[len..];
+    }
+    None
+}
+
+pub fn parse_ipv6(data: &[u8]) -> Option<(&Ipv6Header, &[u8])> {
+    try_parse_ipv6(data).ok().flatten()
+}
+
+/// Like `parse_ipv6`, but distinguishes "not IPv6" (`Ok(None)`) from a truncated
+/// header (`Err`).
+pub fn try_parse_ipv6(data: &[u8]) -> Result<Option<(&Ipv6Header, &[u8])>, ParseError> {
+    match data.first() {
+        Some(b) if b >> 4 != 6 => return Ok(None),
+        _ => {}
+    }
+
+    let needed = std::mem::size_of::<Ipv6Header>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
+    }
+
+    let ptr = data.as_ptr() as *const Ipv6Header;
+    let header = unsafe { &*ptr };
+    let payload = &data[needed..];
+
+    Ok(Some((header, payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipv6_parsing() {
+        let mut data = [0u8; 44];
+        data[0] = 0x60;
+        data[4..6].copy_from_slice(&4u16.to_be_bytes());
+        data[6] = 17; // UDP
+        data[7] = 64;
+        data[8..24].copy_from_slice(&[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data[40..44].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+
+        let (header, payload) = parse_ipv6(&data).expect("Should parse ipv6");
+        assert_eq!(header.version(), 6);
+        assert_eq!(header.payload_len(), 4);
+        assert_eq!(header.next_header, 17);
+        assert_eq!(header.src[15], 1);
+        assert_eq!(header.src_addr(), "fe80::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(payload, &[0x11, 0x22, 0x33, 0x44]);
+
+        // IPv4 is not an error, a cut-short IPv6 header is
+        assert!(matches!(try_parse_ipv6(&[0x45; 20]), Ok(None)));
+        assert_eq!(try_parse_ipv6(&data[..20]).unwrap_err(), ParseError::Truncated { needed: 40, got: 20 });
+    }
+
+    #[test]
+    fn test_ipv6_addresses() {
+        // 2001:db8::8a2e:370:7334 -> ff02::1
+        let mut data = [0u8; 40];
+        data[0] = 0x60;
+        data[8..24].copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34]);
+        data[24] = 0xFF;
+        data[25] = 0x02;
+        data[39] = 1;
+
+        let header = unsafe { &mut *(data.as_mut_ptr() as *mut Ipv6Header) };
+        assert_eq!(header.src_addr(), "2001:db8::8a2e:370:7334".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(header.dst_addr(), Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1));
+        assert!(header.is_multicast());
+        assert!(!header.is_link_local());
+
+        header.set_src_addr("fe80::1".parse().unwrap());
+        header.set_dst_addr("2001:db8::1".parse().unwrap());
+        assert!(header.is_link_local());
+        assert!(!header.is_multicast());
+        assert_eq!(&data[8..10], &[0xFE, 0x80]);
+        assert_eq!(data[39], 1);
+    }
+
+    #[test]
+    fn test_skip_extension_headers() {
+        // Hop-by-Hop (8 bytes, PadN) -> Destination Options (16 bytes) -> UDP
+        let mut chain = vec![0u8; 24 + 8];
+        chain[0] = NEXTHDR_DEST;
+        chain[2] = 1; // PadN
+        chain[3] = 4;
+        chain[8] = 17;
+        chain[9] = 1; // (1 + 1) * 8 bytes
+        chain[24..].copy_from_slice(&[0xAA; 8]);
+
+        assert_eq!(skip_extension_headers(NEXTHDR_HOP, &chain), Some(Ipv6Upper::Proto(17, &[0xAA; 8][..])));
+        // No extension headers at all
+        assert_eq!(skip_extension_headers(6, &chain[24..]), Some(Ipv6Upper::Proto(6, &[0xAA; 8][..])));
+        // Truncated chain
+        assert_eq!(skip_extension_headers(NEXTHDR_HOP, &chain[..12]), None);
+    }
+
+    #[test]
+    fn test_fragment_header() {
+        let mut frag = [0u8; 16];
+        frag[0] = 17;
+
+        // Atomic fragment: offset 0, M clear. The UDP header follows.
+        assert_eq!(skip_extension_headers(NEXTHDR_FRAGMENT, &frag), Some(Ipv6Upper::Proto(17, &[0u8; 8][..])));
+
+        // First fragment (M set) and a later fragment (offset 185 * 8)
+        frag[3] = 0x01;
+        assert_eq!(skip_extension_headers(NEXTHDR_FRAGMENT, &frag), Some(Ipv6Upper::Fragmented));
+        frag[2..4].copy_from_slice(&(185u16 << 3).to_be_bytes());
+        assert_eq!(skip_extension_headers(NEXTHDR_FRAGMENT, &frag), Some(Ipv6Upper::Fragmented));
+    }
+
+    #[test]
+    fn test_extension_chain_bounded() {
+        // A chain longer than we follow gives up instead of walking it all
+        let mut chain = vec![0u8; 8 * 64];
+        chain.chunks_mut(8).for_each(|hdr| hdr[0] = NEXTHDR_HOP);
+        assert_eq!(skip_extension_headers(NEXTHDR_HOP, &chain), None);
+
+        // Random chains: never panics, and the upper layer lies within the input
+        let mut next = crate::xorshift(0x2545_F491_4F6C_DD1D);
+        let kinds = [NEXTHDR_HOP, NEXTHDR_ROUTING, NEXTHDR_DEST, NEXTHDR_AUTH, NEXTHDR_FRAGMENT, 17];
+        for _ in 0..10_000 {
+            let mut data = vec![0u8; (next() % 256) as usize];
+            data.iter_mut().for_each(|b| *b = next() as u8);
+            // Mostly extension headers, so the walk goes deep
+            for hdr in data.chunks_mut(8) {
+                hdr[0] = kinds[(next() % kinds.len() as u64) as usize];
+            }
+            let first = kinds[(next() % kinds.len() as u64) as usize];
+            if let Some(Ipv6Upper::Proto(_, upper)) = skip_extension_headers(first, &data) {
+                let end = data.as_ptr_range().end;
+                assert!(upper.as_ptr() >= data.as_ptr() && upper.as_ptr_range().end == end);
+            }
+        }
+    }
+}
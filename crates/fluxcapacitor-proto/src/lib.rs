@@ -1,29 +1,67 @@
 pub mod ethernet;
 pub mod ipv4;
+pub mod ipv6;
 pub mod udp;
 pub mod tcp;
 pub mod icmp;
+pub mod nat;
+pub mod transport;
+pub mod vlan;
+pub mod dissect;
 
-pub use ethernet::{EthHeader, parse_eth};
-pub use ipv4::{Ipv4Header, parse_ipv4};
-pub use udp::{UdpHeader, parse_udp};
-pub use tcp::{TcpHeader, parse_tcp};
-pub use icmp::{IcmpHeader, parse_icmp};
+pub use ethernet::{EthHeader, parse_eth, try_parse_eth};
+pub use ipv4::{IpProto, Ipv4Header, parse_ipv4, try_parse_ipv4};
+pub use ipv6::{Ipv6Header, parse_ipv6, try_parse_ipv6};
+pub use udp::{UdpHeader, parse_udp, try_parse_udp};
+pub use tcp::{TcpHeader, parse_tcp, try_parse_tcp};
+pub use icmp::{IcmpHeader, IcmpError, parse_icmp, try_parse_icmp};
+pub use transport::{Transport, parse_transport};
+pub use vlan::{VlanTag, parse_vlan, try_parse_vlan};
+pub use dissect::{Dissector, dissect};
+
+/// Why a `try_parse_*` function rejected a buffer.
+///
+/// A buffer that simply holds a different protocol is not an error: the parsers
+/// return `Ok(None)` for that. `ParseError` means the data claims to be the protocol
+/// but is truncated or internally inconsistent, which is worth flagging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The buffer ends before the header does.
+    Truncated { needed: usize, got: usize },
+    /// A length field is below the protocol minimum (e.g. IPv4 IHL < 5).
+    Malformed,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Truncated { needed, got } => write!(f, "truncated header: needed {} bytes, got {}", needed, got),
+            ParseError::Malformed => write!(f, "malformed header"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 pub trait PacketView {
     fn len(&self) -> usize;
 }
 
+/// Internet checksum (RFC 1071): the one's complement of the one's complement sum
+/// of `data` taken as big-endian 16-bit words. Host order result.
+///
+/// An odd trailing byte is padded with a zero byte on the right, as if `data` had
+/// one more byte (`[a, b, c]` sums like `[a, b, c, 0]`). An empty slice sums to 0 and
+/// so yields `0xFFFF`. Summing a buffer that includes a correct checksum field
+/// yields 0, which is how `verify_checksum` and `Ipv4Header::is_valid` check it.
 pub fn checksum(data: &[u8]) -> u16 {
     let mut sum: u32 = 0;
-    let mut i = 0;
-    while i + 1 < data.len() {
-        let word = u16::from_be_bytes([data[i], data[i+1]]);
-        sum += word as u32;
-        i += 2;
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u32;
     }
-    if i < data.len() {
-        sum += (data[i] as u32) << 8;
+    if let [last] = words.remainder() {
+        sum += u16::from_be_bytes([*last, 0]) as u32;
     }
     
     while (sum >> 16) != 0 {
@@ -32,3 +70,193 @@ pub fn checksum(data: &[u8]) -> u16 {
     
     !sum as u16
 }
+
+/// Checksum carried by a protocol header, so every layer of a packet can be
+/// validated by the same generic code.
+///
+/// `Context` is whatever else the checksum covers: nothing for IPv4, the data after
+/// the header for ICMP, and the IPv4 header (for the pseudo-header) plus the data
+/// after the header for UDP and TCP.
+pub trait Checksum {
+    type Context<'a>;
+
+    /// The value the checksum field should hold, host order. The field's current
+    /// value is ignored.
+    fn compute(&self, ctx: Self::Context<'_>) -> u16;
+
+    /// Whether the checksum field holds the right value.
+    fn verify(&self, ctx: Self::Context<'_>) -> bool;
+}
+
+// Running one's complement sum of big-endian words, folded by `fold_sum`. Every
+// slice but the last must have an even length.
+pub(crate) fn sum_words(mut sum: u64, data: &[u8]) -> u64 {
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u64;
+    }
+    if let [last] = words.remainder() {
+        sum += u16::from_be_bytes([*last, 0]) as u64;
+    }
+    sum
+}
+
+pub(crate) fn fold_sum(mut sum: u64) -> u16 {
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !sum as u16
+}
+
+// Sum of the IPv4 pseudo-header for an L4 segment of `l4_len` bytes.
+pub(crate) fn pseudo_header_sum(ip: &Ipv4Header, l4_len: usize) -> u64 {
+    let mut sum = sum_words(0, &ip.src_addr().octets());
+    sum = sum_words(sum, &ip.dst_addr().octets());
+    sum + ip.proto as u64 + l4_len as u64
+}
+
+/// Incrementally update a checksum after one 16-bit word changed (RFC 1624, eqn. 3).
+///
+/// `check` is the current checksum; `old` and `new` are the word before and after
+/// the change. All values are host order.
+pub fn checksum_adjust(check: u16, old: u16, new: u16) -> u16 {
+    let mut sum = (!check as u32) + (!old as u32) + (new as u32);
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// Reproducible xorshift64 stream for the randomized parser tests. `seed` must
+// not be 0.
+#[cfg(test)]
+pub(crate) fn xorshift(mut seed: u64) -> impl FnMut() -> u64 {
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_short_inputs() {
+        assert_eq!(checksum(&[]), 0xFFFF);
+        assert_eq!(checksum(&[0xAB]), !0xAB00);
+        assert_eq!(checksum(&[0x12, 0x34]), !0x1234);
+        assert_eq!(checksum(&[0x12, 0x34, 0x56]), !(0x1234 + 0x5600));
+
+        // Odd lengths are padded with a zero byte
+        assert_eq!(checksum(&[0x12, 0x34, 0x56]), checksum(&[0x12, 0x34, 0x56, 0x00]));
+    }
+
+    #[test]
+    fn test_checksum_carry_and_verify() {
+        // 0xFFFF + 0x0001 wraps around to 0x0001
+        assert_eq!(checksum(&[0xFF, 0xFF, 0x00, 0x01]), !0x0001);
+
+        // Data followed by its own checksum sums to zero
+        for data in [&[][..], &[0xAB, 0x00], &[0x12, 0x34], &[0xFF, 0xFF, 0x00, 0x01]] {
+            let mut buf = data.to_vec();
+            buf.extend_from_slice(&checksum(data).to_be_bytes());
+            assert_eq!(checksum(&buf), 0, "{:02x?}", data);
+        }
+    }
+
+    #[test]
+    fn test_checksum_adjust_matches_full() {
+        let mut data = [0x45, 0x00, 0x00, 0x1C, 0x12, 0x34];
+        let check = checksum(&data);
+        data[2..4].copy_from_slice(&0x0040u16.to_be_bytes());
+        assert_eq!(checksum_adjust(check, 0x001C, 0x0040), checksum(&data));
+    }
+
+    #[test]
+    fn test_checksum_trait_generic() {
+        fn all_valid<H: Checksum>(layers: &[(&H, H::Context<'_>)]) -> bool
+        where
+            for<'a> H::Context<'a>: Copy,
+        {
+            layers.iter().all(|(header, ctx)| header.verify(*ctx))
+        }
+
+        // IPv4 (src 10.0.0.1, dst 10.0.0.2) + UDP 1234 -> 53 + 3 byte payload + 1 byte padding
+        let mut ip = [0u8; 20];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&31u16.to_be_bytes());
+        ip[8] = 64;
+        ip[9] = 17;
+        ip[12..16].copy_from_slice(&[10, 0, 0, 1]);
+        ip[16..20].copy_from_slice(&[10, 0, 0, 2]);
+        let mut udp = [0u8; 12];
+        udp[0..2].copy_from_slice(&1234u16.to_be_bytes());
+        udp[2..4].copy_from_slice(&53u16.to_be_bytes());
+        udp[4..6].copy_from_slice(&11u16.to_be_bytes());
+        udp[8..11].copy_from_slice(b"abc");
+
+        let ip_check = parse_ipv4(&ip).unwrap().0.compute(());
+        ip[10..12].copy_from_slice(&ip_check.to_be_bytes());
+        let (ip_header, _) = parse_ipv4(&ip).unwrap();
+        assert!(all_valid(&[(ip_header, ())]));
+
+        let (udp_header, payload) = parse_udp(&udp).unwrap();
+        let udp_check = udp_header.compute((ip_header, payload));
+        // Matches a plain checksum over pseudo-header + segment (padding excluded)
+        let mut pseudo = vec![10, 0, 0, 1, 10, 0, 0, 2, 0, 17, 0, 11];
+        pseudo.extend_from_slice(&udp[..11]);
+        assert_eq!(udp_check, checksum(&pseudo));
+
+        udp[6..8].copy_from_slice(&udp_check.to_be_bytes());
+        let (udp_header, payload) = parse_udp(&udp).unwrap();
+        assert!(all_valid(&[(udp_header, (ip_header, payload))]));
+        assert!(udp_header.verify_checksum(ip_header, payload));
+
+        // A corrupted payload byte is caught
+        udp[9] ^= 0xFF;
+        let (udp_header, payload) = parse_udp(&udp).unwrap();
+        assert!(!all_valid(&[(udp_header, (ip_header, payload))]));
+
+        // ICMP echo request, through the same generic helper
+        let mut icmp = [8, 0, 0, 0, 0, 1, 0, 1, b'p', b'i', b'n', b'g'];
+        let (header, payload) = parse_icmp(&icmp).unwrap();
+        let check = header.compute(payload);
+        icmp[2..4].copy_from_slice(&check.to_be_bytes());
+        assert_eq!(checksum(&icmp), 0);
+        let (header, payload) = parse_icmp(&icmp).unwrap();
+        assert!(all_valid(&[(header, payload)]));
+    }
+
+    #[test]
+    fn test_tcp_checksum_trait() {
+        // IPv4 + TCP with 4 bytes of options and a 2 byte payload
+        let mut frame = [0u8; 20 + 24 + 2];
+        frame[0] = 0x45;
+        frame[2..4].copy_from_slice(&46u16.to_be_bytes());
+        frame[9] = 6;
+        frame[12..16].copy_from_slice(&[192, 168, 0, 1]);
+        frame[16..20].copy_from_slice(&[192, 168, 0, 2]);
+        let tcp = &mut frame[20..];
+        tcp[12] = 0x60;
+        tcp[13] = 0x02;
+        tcp[20..24].copy_from_slice(&[2, 4, 0x05, 0xB4]); // MSS 1460
+        tcp[24..26].copy_from_slice(b"hi");
+
+        let (ip, l4) = parse_ipv4(&frame).unwrap();
+        let (tcp, payload) = parse_tcp(l4).unwrap();
+        let check = tcp.compute((ip, payload));
+
+        let mut pseudo = vec![192, 168, 0, 1, 192, 168, 0, 2, 0, 6, 0, 26];
+        pseudo.extend_from_slice(&frame[20..]);
+        assert_eq!(check, checksum(&pseudo));
+
+        frame[20 + 16..20 + 18].copy_from_slice(&check.to_be_bytes());
+        let (ip, l4) = parse_ipv4(&frame).unwrap();
+        let (tcp, payload) = parse_tcp(l4).unwrap();
+        assert!(tcp.verify((ip, payload)));
+        assert!(tcp.verify_checksum(ip, payload));
+    }
+}
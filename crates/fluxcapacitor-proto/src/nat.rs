@@ -0,0 +1,197 @@
+//! In-place NAT rewrites for IPv4 TCP/UDP frames.
+//!
+//! Addresses and ports are patched directly inside the Ethernet frame, and both the
+//! IPv4 header checksum and the L4 checksum are fixed up incrementally (RFC 1624 /
+//! RFC 3022). The L4 checksum changes even for an address-only rewrite, because the
+//! addresses are part of its pseudo-header.
+
+use crate::ethernet::ETH_P_IP;
+
+const ETH_HLEN: usize = 14;
+
+/// Rewrite the source address and port of an IPv4 frame.
+///
+/// `new_ip` is host order. Pass the current value to leave a field unchanged.
+/// Ports are only rewritten for TCP/UDP (first fragment). Returns `false`, leaving the
+/// frame untouched, if it is not IPv4 or is too short to hold the headers.
+pub fn rewrite_src(frame: &mut [u8], new_ip: u32, new_port: u16) -> bool {
+    rewrite(frame, 12, 0, new_ip, new_port)
+}
+
+/// Rewrite the destination address and port of an IPv4 frame.
+///
+/// Same rules as `rewrite_src`.
+pub fn rewrite_dst(frame: &mut [u8], new_ip: u32, new_port: u16) -> bool {
+    rewrite(frame, 16, 2, new_ip, new_port)
+}
+
+fn rewrite(frame: &mut [u8], ip_off: usize, port_off: usize, new_ip: u32, new_port: u16) -> bool {
+    if frame.len() < ETH_HLEN + 20 || read_u16(frame, 12) != ETH_P_IP {
+        return false;
+    }
+
+    let ip = &mut frame[ETH_HLEN..];
+    let ihl = ((ip[0] & 0x0F) as usize) * 4;
+    if ip[0] >> 4 != 4 || ihl < 20 || ip.len() < ihl {
+        return false;
+    }
+
+    // Only the first fragment carries the L4 header.
+    let proto = ip[9];
+    let first_fragment = read_u16(ip, 6) & 0x1FFF == 0;
+    let l4_check_off = match proto {
+        6 if first_fragment => Some(16), // TCP
+        17 if first_fragment => Some(6), // UDP
+        _ => None,
+    };
+    if let Some(off) = l4_check_off {
+        if ip.len() < ihl + off + 2 {
+            return false;
+        }
+    }
+
+    // 1. Address + IP header checksum
+    let old_ip = read_u32(ip, ip_off);
+    write_u32(ip, ip_off, new_ip);
+    let ip_check = adjust_u32(read_u16(ip, 10), old_ip, new_ip);
+    write_u16(ip, 10, ip_check);
+
+    // 2. Port + L4 checksum (pseudo-header includes the address)
+    if let Some(check_off) = l4_check_off {
+        let l4 = &mut ip[ihl..];
+        let old_port = read_u16(l4, port_off);
+        write_u16(l4, port_off, new_port);
+
+        let check = read_u16(l4, check_off);
+        if proto == 17 && check == 0 {
+            return true; // UDP checksum not in use
+        }
+
+        let mut check = adjust_u32(check, old_ip, new_ip);
+        check = crate::checksum_adjust(check, old_port, new_port);
+        if proto == 17 && check == 0 {
+            check = 0xFFFF; // 0 is reserved for "no checksum" in UDP
+        }
+        write_u16(l4, check_off, check);
+    }
+
+    true
+}
+
+fn adjust_u32(check: u16, old: u32, new: u32) -> u16 {
+    let check = crate::checksum_adjust(check, (old >> 16) as u16, (new >> 16) as u16);
+    crate::checksum_adjust(check, old as u16, new as u16)
+}
+
+fn read_u16(buf: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes([buf[off], buf[off + 1]])
+}
+
+fn write_u16(buf: &mut [u8], off: usize, val: u16) {
+    buf[off..off + 2].copy_from_slice(&val.to_be_bytes());
+}
+
+fn read_u32(buf: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+fn write_u32(buf: &mut [u8], off: usize, val: u32) {
+    buf[off..off + 4].copy_from_slice(&val.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Eth + IPv4 + L4 header (+ 4 bytes payload) with valid checksums.
+    fn build_frame(proto: u8, l4_len: usize, check_off: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; ETH_HLEN + 20 + l4_len];
+        frame[12..14].copy_from_slice(&ETH_P_IP.to_be_bytes());
+
+        let ip = &mut frame[ETH_HLEN..ETH_HLEN + 20];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&((20 + l4_len) as u16).to_be_bytes());
+        ip[8] = 64;
+        ip[9] = proto;
+        ip[12..16].copy_from_slice(&[192, 168, 1, 10]);
+        ip[16..20].copy_from_slice(&[8, 8, 8, 8]);
+        let csum = crate::checksum(ip);
+        ip[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let l4 = &mut frame[ETH_HLEN + 20..];
+        l4[0..2].copy_from_slice(&40000u16.to_be_bytes());
+        l4[2..4].copy_from_slice(&53u16.to_be_bytes());
+        if proto == 17 {
+            l4[4..6].copy_from_slice(&(l4_len as u16).to_be_bytes());
+        } else {
+            l4[12] = 0x50; // Data offset 5
+        }
+        let n = l4.len();
+        l4[n - 4..].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let csum = full_l4_checksum(&frame, check_off);
+        frame[ETH_HLEN + 20 + check_off..ETH_HLEN + 20 + check_off + 2].copy_from_slice(&csum.to_be_bytes());
+        frame
+    }
+
+    // Pseudo-header + segment, computed from scratch with the checksum field zeroed.
+    fn full_l4_checksum(frame: &[u8], check_off: usize) -> u16 {
+        let ip = &frame[ETH_HLEN..ETH_HLEN + 20];
+        let mut l4 = frame[ETH_HLEN + 20..].to_vec();
+        l4[check_off] = 0;
+        l4[check_off + 1] = 0;
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&ip[12..20]);
+        buf.extend_from_slice(&[0, ip[9]]);
+        buf.extend_from_slice(&(l4.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&l4);
+        crate::checksum(&buf)
+    }
+
+    fn assert_checksums_valid(frame: &[u8], check_off: usize) {
+        assert_eq!(crate::checksum(&frame[ETH_HLEN..ETH_HLEN + 20]), 0, "IP checksum broken");
+        let l4_check = read_u16(&frame[ETH_HLEN + 20..], check_off);
+        assert_eq!(l4_check, full_l4_checksum(frame, check_off), "L4 checksum broken");
+    }
+
+    #[test]
+    fn test_rewrite_src_tcp() {
+        let mut frame = build_frame(6, 24, 16);
+        assert!(rewrite_src(&mut frame, 0xCB00_7105, 61000)); // 203.0.113.5
+
+        assert_eq!(&frame[ETH_HLEN + 12..ETH_HLEN + 16], &[203, 0, 113, 5]);
+        assert_eq!(read_u16(&frame[ETH_HLEN + 20..], 0), 61000);
+        assert_checksums_valid(&frame, 16);
+    }
+
+    #[test]
+    fn test_rewrite_dst_udp() {
+        let mut frame = build_frame(17, 12, 6);
+        assert!(rewrite_dst(&mut frame, 0x0A00_0002, 5353)); // 10.0.0.2
+
+        assert_eq!(&frame[ETH_HLEN + 16..ETH_HLEN + 20], &[10, 0, 0, 2]);
+        assert_eq!(read_u16(&frame[ETH_HLEN + 20..], 2), 5353);
+        assert_checksums_valid(&frame, 6);
+    }
+
+    #[test]
+    fn test_rewrite_udp_without_checksum() {
+        let mut frame = build_frame(17, 12, 6);
+        frame[ETH_HLEN + 26..ETH_HLEN + 28].copy_from_slice(&[0, 0]);
+
+        assert!(rewrite_src(&mut frame, 0x0A00_0001, 1234));
+        assert_eq!(read_u16(&frame[ETH_HLEN + 20..], 6), 0);
+        assert_eq!(crate::checksum(&frame[ETH_HLEN..ETH_HLEN + 20]), 0);
+    }
+
+    #[test]
+    fn test_rewrite_rejects_non_ipv4() {
+        let mut frame = build_frame(6, 24, 16);
+        frame[12..14].copy_from_slice(&0x86DDu16.to_be_bytes());
+        let before = frame.clone();
+
+        assert!(!rewrite_src(&mut frame, 0x0A00_0001, 1234));
+        assert_eq!(frame, before);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ipv4::Ipv4Header;
+use crate::ParseError;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
@@ -44,54 +45,50 @@ impl TcpHeader {
         u16::from_be(self.data_off_res_flags) & 0x01FF
     }
 
-    pub fn verify_checksum(&self, ip: &Ipv4Header, _payload: &[u8]) -> bool {
-        // TCP Length = IP Total Len - IP Header Len
-        let ip_len = u16::from_be(ip.total_len) as usize;
-        let ip_hdr_len = ip.header_len();
-        if ip_len < ip_hdr_len { return false; }
-        
-        let tcp_seg_len = ip_len - ip_hdr_len;
-        
-        let mut sum: u32 = 0;
-        
-        // Pseudo Header
-        let src = ip.src.to_be_bytes();
-        sum += u16::from_be_bytes([src[0], src[1]]) as u32;
-        sum += u16::from_be_bytes([src[2], src[3]]) as u32;
-        
-        let dst = ip.dst.to_be_bytes();
-        sum += u16::from_be_bytes([dst[0], dst[1]]) as u32;
-        sum += u16::from_be_bytes([dst[2], dst[3]]) as u32;
-        
-        sum += ip.proto as u32; 
-        sum += tcp_seg_len as u32;
-        
-        // TCP Header + Payload
+    pub fn checksum(&self) -> u16 {
+        u16::from_be(self.check)
+    }
+
+    /// Same as `Checksum::verify` with `(ip, payload)`.
+    pub fn verify_checksum(&self, ip: &Ipv4Header, payload: &[u8]) -> bool {
+        crate::Checksum::verify(self, (ip, payload))
+    }
+}
+
+/// Checksum over the IPv4 pseudo-header, the TCP header with its options and
+/// `payload` (the data after the header, as returned by `parse_tcp`). The segment
+/// ends where the IPv4 total length says, so Ethernet padding is ignored.
+impl crate::Checksum for TcpHeader {
+    type Context<'a> = (&'a Ipv4Header, &'a [u8]);
+
+    fn compute(&self, (ip, payload): (&Ipv4Header, &[u8])) -> u16 {
+        let header_len = self.header_len();
+        let segment_len = (ip.total_len() as usize).saturating_sub(ip.header_len());
+        let payload = &payload[..payload.len().min(segment_len.saturating_sub(header_len))];
+
         let ptr = self as *const TcpHeader as *const u8;
-        // Total bytes
-        let tcp_bytes = unsafe { std::slice::from_raw_parts(ptr, tcp_seg_len) };
-        
-        let mut i = 0;
-        while i + 1 < tcp_bytes.len() {
-            let word = u16::from_be_bytes([tcp_bytes[i], tcp_bytes[i+1]]);
-            sum += word as u32;
-            i += 2;
-        }
-        if i < tcp_bytes.len() {
-            sum += (tcp_bytes[i] as u32) << 8;
-        }
-        
-        while (sum >> 16) != 0 {
-            sum = (sum & 0xFFFF) + (sum >> 16);
-        }
-        
-        !sum as u16 == 0
+        let header = unsafe { std::slice::from_raw_parts(ptr, header_len) };
+        let mut sum = crate::pseudo_header_sum(ip, header_len + payload.len());
+        // Skip the checksum field (bytes 16..18)
+        sum = crate::sum_words(sum, &header[..16]);
+        sum = crate::sum_words(sum, &header[18..]);
+        crate::fold_sum(crate::sum_words(sum, payload))
+    }
+
+    fn verify(&self, ctx: (&Ipv4Header, &[u8])) -> bool {
+        self.compute(ctx) == self.checksum()
     }
 }
 
 pub fn parse_tcp(data: &[u8]) -> Option<(&TcpHeader, &[u8])> {
-    if data.len() < std::mem::size_of::<TcpHeader>() {
-        return None;
+    try_parse_tcp(data).ok().flatten()
+}
+
+/// Like `parse_tcp`, but reports truncation and a bad data offset as errors.
+pub fn try_parse_tcp(data: &[u8]) -> Result<Option<(&TcpHeader, &[u8])>, ParseError> {
+    let needed = std::mem::size_of::<TcpHeader>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
     }
     
     let ptr = data.as_ptr() as *const TcpHeader;
@@ -99,12 +96,15 @@ pub fn parse_tcp(data: &[u8]) -> Option<(&TcpHeader, &[u8])> {
     
     let header_len = header.header_len();
     // Safety check: header_len must be at least 20 bytes (5 words)
-    if header_len < 20 || data.len() < header_len {
-        return None;
+    if header_len < 20 {
+        return Err(ParseError::Malformed);
+    }
+    if data.len() < header_len {
+        return Err(ParseError::Truncated { needed: header_len, got: data.len() });
     }
 
     let payload = &data[header_len..];
-    Some((header, payload))
+    Ok(Some((header, payload)))
 }
 
 #[cfg(test)]
@@ -127,4 +127,15 @@ mod tests {
         assert_eq!(header.flags(), 0x002); // SYN
         assert_eq!(payload.len(), 0);
     }
+
+    #[test]
+    fn test_tcp_malformed_and_truncated() {
+        let mut data = [0u8; 24];
+        data[12] = 0x40; // Offset 4 (< minimum of 5)
+        assert_eq!(try_parse_tcp(&data).unwrap_err(), ParseError::Malformed);
+
+        data[12] = 0x80; // Offset 8 (32 bytes), but only 24 present
+        assert_eq!(try_parse_tcp(&data).unwrap_err(), ParseError::Truncated { needed: 32, got: 24 });
+        assert!(parse_tcp(&data).is_none());
+    }
 }
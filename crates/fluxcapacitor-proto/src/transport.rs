@@ -0,0 +1,132 @@
+//! Layer 4 dispatch shared by IPv4 and IPv6.
+
+use crate::ethernet::{ETH_P_IP, ETH_P_IPV6};
+use crate::ipv6::Ipv6Upper;
+use crate::{IcmpHeader, TcpHeader, UdpHeader};
+
+/// Transport header of an IP packet.
+#[derive(Debug, Clone, Copy)]
+pub enum Transport<'a> {
+    Tcp(&'a TcpHeader, &'a [u8]),
+    Udp(&'a UdpHeader, &'a [u8]),
+    /// ICMP for IPv4, ICMPv6 for IPv6 (both start with type/code/checksum).
+    Icmp(&'a IcmpHeader, &'a [u8]),
+    /// A fragment; the transport header is not (reliably) in this packet.
+    Fragmented,
+    /// Any other protocol number, or a truncated transport header.
+    Other(u8),
+}
+
+/// Find the transport header of an Ethernet frame carrying IPv4 or IPv6.
+///
+/// IPv6 extension headers are skipped (see `ipv6::skip_extension_headers`).
+/// Returns `None` for non-IP frames and malformed IP headers.
+pub fn parse_transport(frame: &[u8]) -> Option<Transport<'_>> {
+    let (eth, payload) = crate::parse_eth(frame)?;
+    match eth.eth_type() {
+        ETH_P_IP => {
+            let (ip, l4) = crate::parse_ipv4(payload)?;
+            // Only the first fragment carries the L4 header.
+            if u16::from_be(ip.frag_off) & 0x1FFF != 0 {
+                return Some(Transport::Fragmented);
+            }
+            Some(dispatch(ip.proto, l4))
+        }
+        ETH_P_IPV6 => {
+            let (ip, rest) = crate::parse_ipv6(payload)?;
+            match crate::ipv6::skip_extension_headers(ip.next_header, rest)? {
+                Ipv6Upper::Proto(proto, l4) => Some(dispatch(proto, l4)),
+                Ipv6Upper::Fragmented => Some(Transport::Fragmented),
+            }
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn dispatch(proto: u8, l4: &[u8]) -> Transport<'_> {
+    let parsed = match proto {
+        6 => crate::parse_tcp(l4).map(|(h, p)| Transport::Tcp(h, p)), // TCP
+        17 => crate::parse_udp(l4).map(|(h, p)| Transport::Udp(h, p)), // UDP
+        1 | 58 => crate::parse_icmp(l4).map(|(h, p)| Transport::Icmp(h, p)), // ICMP, ICMPv6
+        _ => None,
+    };
+    parsed.unwrap_or(Transport::Other(proto))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Eth + IPv6 + Hop-by-Hop (Router Alert) + UDP to port 5353 with 4 payload bytes.
+    fn ipv6_udp_with_hop_by_hop() -> Vec<u8> {
+        let mut frame = vec![0u8; 14 + 40 + 8 + 8 + 4];
+        frame[12..14].copy_from_slice(&ETH_P_IPV6.to_be_bytes());
+
+        let ip = &mut frame[14..54];
+        ip[0] = 0x60;
+        ip[4..6].copy_from_slice(&20u16.to_be_bytes());
+        ip[6] = 0; // Hop-by-Hop
+        ip[7] = 1;
+
+        let hbh = &mut frame[54..62];
+        hbh[0] = 17; // UDP
+        hbh[1] = 0; // 8 bytes
+        hbh[2..6].copy_from_slice(&[0x05, 0x02, 0x00, 0x00]); // Router Alert
+        hbh[6] = 0x01; // PadN, 0 bytes
+
+        let udp = &mut frame[62..];
+        udp[0..2].copy_from_slice(&40000u16.to_be_bytes());
+        udp[2..4].copy_from_slice(&5353u16.to_be_bytes());
+        udp[4..6].copy_from_slice(&12u16.to_be_bytes());
+        udp[8..12].copy_from_slice(b"mdns");
+        frame
+    }
+
+    #[test]
+    fn test_ipv6_udp_with_hop_by_hop() {
+        let frame = ipv6_udp_with_hop_by_hop();
+        match parse_transport(&frame) {
+            Some(Transport::Udp(udp, payload)) => {
+                assert_eq!(udp.src_port(), 40000);
+                assert_eq!(udp.dst_port(), 5353);
+                assert_eq!(payload, b"mdns");
+            }
+            other => panic!("expected UDP, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ipv6_fragment_is_not_parsed_as_tcp() {
+        let mut frame = vec![0u8; 14 + 40 + 8 + 20];
+        frame[12..14].copy_from_slice(&ETH_P_IPV6.to_be_bytes());
+        frame[14] = 0x60;
+        frame[14 + 6] = 44; // Fragment
+        frame[54] = 6; // TCP
+        frame[54 + 2..54 + 4].copy_from_slice(&((100u16 << 3) | 1).to_be_bytes());
+
+        assert!(matches!(parse_transport(&frame), Some(Transport::Fragmented)));
+    }
+
+    #[test]
+    fn test_ipv4_dispatch() {
+        let mut frame = vec![0u8; 14 + 20 + 8];
+        frame[12..14].copy_from_slice(&ETH_P_IP.to_be_bytes());
+        frame[14] = 0x45;
+        frame[14 + 9] = 17;
+        frame[34 + 2..34 + 4].copy_from_slice(&53u16.to_be_bytes());
+        assert!(matches!(parse_transport(&frame), Some(Transport::Udp(h, _)) if h.dst_port() == 53));
+
+        // Later fragment
+        frame[14 + 6..14 + 8].copy_from_slice(&64u16.to_be_bytes());
+        assert!(matches!(parse_transport(&frame), Some(Transport::Fragmented)));
+
+        // GRE
+        frame[14 + 6..14 + 8].copy_from_slice(&[0, 0]);
+        frame[14 + 9] = 47;
+        assert!(matches!(parse_transport(&frame), Some(Transport::Other(47))));
+
+        // ARP
+        frame[12..14].copy_from_slice(&0x0806u16.to_be_bytes());
+        assert!(parse_transport(&frame).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ipv4::Ipv4Header;
+use crate::ParseError;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
@@ -22,79 +23,56 @@ impl UdpHeader {
         u16::from_be(self.len)
     }
 
-    pub fn verify_checksum(&self, ip: &Ipv4Header, _payload: &[u8]) -> bool {
-        if self.check == 0 {
-            return true; // Optional in IPv4
-        }
-        
-        let udp_len = self.length();
-        // Check if payload matches length
-        // Note: payload.len() might be larger if padding exists?
-        // But udp_len includes header.
-        
-        let mut sum: u32 = 0;
-        
-        // Pseudo Header
-        // Src IP
-        let src = ip.src.to_be_bytes();
-        sum += u16::from_be_bytes([src[0], src[1]]) as u32;
-        sum += u16::from_be_bytes([src[2], src[3]]) as u32;
-        
-        // Dst IP
-        let dst = ip.dst.to_be_bytes();
-        sum += u16::from_be_bytes([dst[0], dst[1]]) as u32;
-        sum += u16::from_be_bytes([dst[2], dst[3]]) as u32;
-        
-        // Zero + Proto
-        sum += ip.proto as u32; // padded to u16: 0x00_Proto
-        
-        // Length
-        sum += udp_len as u32;
-        
-        // UDP Header + Payload
-        // We can reconstruct the slice
-        let ptr = self as *const UdpHeader as *const u8;
-        // Total UDP bytes
-        let total_len = udp_len as usize;
-        
-        // Safety: We assume the caller provided valid pointers/lengths.
-        // We can just sum the bytes starting at `ptr`.
-        let udp_bytes = unsafe { std::slice::from_raw_parts(ptr, total_len) };
-        
-        // We need to use a checksum helper that accumulates into existing sum or handles folding.
-        // Our crate::checksum returns u16.
-        // We can reuse the logic.
-        
-        // Let's perform the sum manually or expose a `checksum_continue`.
-        
-        let mut i = 0;
-        while i + 1 < udp_bytes.len() {
-            let word = u16::from_be_bytes([udp_bytes[i], udp_bytes[i+1]]);
-            sum += word as u32;
-            i += 2;
-        }
-        if i < udp_bytes.len() {
-            sum += (udp_bytes[i] as u32) << 8;
+    pub fn checksum(&self) -> u16 {
+        u16::from_be(self.check)
+    }
+
+    /// Same as `Checksum::verify` with `(ip, payload)`.
+    pub fn verify_checksum(&self, ip: &Ipv4Header, payload: &[u8]) -> bool {
+        crate::Checksum::verify(self, (ip, payload))
+    }
+}
+
+/// Checksum over the IPv4 pseudo-header, the UDP header and `payload` (the data
+/// after the header, trimmed to the length field so Ethernet padding is ignored).
+impl crate::Checksum for UdpHeader {
+    type Context<'a> = (&'a Ipv4Header, &'a [u8]);
+
+    fn compute(&self, (ip, payload): (&Ipv4Header, &[u8])) -> u16 {
+        let payload = &payload[..payload.len().min((self.length() as usize).saturating_sub(8))];
+        let ports = [self.src_port, self.dst_port, self.len];
+        let mut sum = crate::pseudo_header_sum(ip, 8 + payload.len());
+        for word in ports {
+            sum += u16::from_be(word) as u64;
         }
-        
-        while (sum >> 16) != 0 {
-            sum = (sum & 0xFFFF) + (sum >> 16);
+        match crate::fold_sum(crate::sum_words(sum, payload)) {
+            0 => 0xFFFF, // 0 means "no checksum" in UDP
+            check => check,
         }
-        
-        !sum as u16 == 0
+    }
+
+    fn verify(&self, ctx: (&Ipv4Header, &[u8])) -> bool {
+        // Optional in IPv4
+        self.check == 0 || self.compute(ctx) == self.checksum()
     }
 }
 
 pub fn parse_udp(data: &[u8]) -> Option<(&UdpHeader, &[u8])> {
-    if data.len() < std::mem::size_of::<UdpHeader>() {
-        return None;
+    try_parse_udp(data).ok().flatten()
+}
+
+/// Like `parse_udp`, but reports truncation as an error.
+pub fn try_parse_udp(data: &[u8]) -> Result<Option<(&UdpHeader, &[u8])>, ParseError> {
+    let needed = std::mem::size_of::<UdpHeader>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
     }
     
     let ptr = data.as_ptr() as *const UdpHeader;
     let header = unsafe { &*ptr };
-    let payload = &data[std::mem::size_of::<UdpHeader>()..];
+    let payload = &data[needed..];
     
-    Some((header, payload))
+    Ok(Some((header, payload)))
 }
 
 #[cfg(test)]
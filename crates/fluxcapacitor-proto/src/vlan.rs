@@ -0,0 +1,93 @@
+use crate::ParseError;
+
+/// 802.1Q customer tag.
+pub const ETH_P_8021Q: u16 = 0x8100;
+/// 802.1ad service tag (outer tag of a QinQ frame).
+pub const ETH_P_8021AD: u16 = 0x88A8;
+
+/// An 802.1Q tag as it follows the Ethernet addresses: the TCI and the EtherType
+/// of what comes next. The TPID is the preceding header's `eth_type`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct VlanTag {
+    pub tci: u16,
+    pub eth_type: u16,
+}
+
+impl VlanTag {
+    /// VLAN identifier (12 bits).
+    pub fn vid(&self) -> u16 {
+        u16::from_be(self.tci) & 0x0FFF
+    }
+
+    /// Priority code point (3 bits).
+    pub fn pcp(&self) -> u8 {
+        (u16::from_be(self.tci) >> 13) as u8
+    }
+
+    /// Set the priority code point, keeping the VID and DEI. Only the low 3 bits
+    /// of `pcp` are used.
+    pub fn set_pcp(&mut self, pcp: u8) {
+        let tci = (u16::from_be(self.tci) & 0x1FFF) | (((pcp & 0x07) as u16) << 13);
+        self.tci = tci.to_be();
+    }
+
+    /// Drop eligible indicator.
+    pub fn dei(&self) -> bool {
+        u16::from_be(self.tci) & 0x1000 != 0
+    }
+
+    pub fn eth_type(&self) -> u16 {
+        u16::from_be(self.eth_type)
+    }
+}
+
+/// Whether `eth_type` announces a VLAN tag.
+pub fn is_vlan(eth_type: u16) -> bool {
+    matches!(eth_type, ETH_P_8021Q | ETH_P_8021AD)
+}
+
+/// Parse the tag after an Ethernet header (or outer tag) whose type `is_vlan`.
+pub fn parse_vlan(data: &[u8]) -> Option<(&VlanTag, &[u8])> {
+    try_parse_vlan(data).ok().flatten()
+}
+
+/// Like `parse_vlan`, but reports truncation as an error.
+pub fn try_parse_vlan(data: &[u8]) -> Result<Option<(&VlanTag, &[u8])>, ParseError> {
+    let needed = std::mem::size_of::<VlanTag>();
+    if data.len() < needed {
+        return Err(ParseError::Truncated { needed, got: data.len() });
+    }
+
+    let ptr = data.as_ptr() as *const VlanTag;
+    let tag = unsafe { &*ptr };
+    let payload = &data[needed..];
+
+    Ok(Some((tag, payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vlan_parsing() {
+        // PCP 5, DEI clear, VID 100, carrying IPv4
+        let data = [0xA0, 0x64, 0x08, 0x00, 0x45];
+        let (tag, payload) = parse_vlan(&data).expect("Should parse vlan");
+        assert_eq!(tag.vid(), 100);
+        assert_eq!(tag.pcp(), 5);
+        assert!(!tag.dei());
+        assert_eq!(tag.eth_type(), crate::ethernet::ETH_P_IP);
+        assert_eq!(payload, &[0x45]);
+
+        let mut tag = *tag;
+        tag.set_pcp(2);
+        assert_eq!((tag.pcp(), tag.vid(), tag.dei()), (2, 100, false));
+        tag.set_pcp(0xFF);
+        assert_eq!((tag.pcp(), tag.vid()), (7, 100));
+
+        assert!(is_vlan(ETH_P_8021Q) && is_vlan(ETH_P_8021AD));
+        assert_eq!(try_parse_vlan(&data[..3]).unwrap_err(), ParseError::Truncated { needed: 4, got: 3 });
+    }
+}
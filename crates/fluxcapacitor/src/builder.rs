@@ -1,37 +1,178 @@
 use crate::raw::FluxRaw;
-use crate::config::Poller;
+use crate::raw::socket::PromiscGuard;
+use crate::config::{Backend, Clock, CommitOrder, Poller, DEFAULT_POLL_TIMEOUT};
 use crate::engine::FluxEngine;
+use crate::error::FluxError;
+use crate::observer::FluxObserver;
+use crate::rss::RSS_KEY_LEN;
 use fluxcapacitor_core::umem::layout::UmemLayout;
 use fluxcapacitor_core::umem::mmap::UmemRegion;
-use fluxcapacitor_core::sys::socket::{create_xsk_socket, bind_socket, set_umem_reg, set_ring_size, get_mmap_offsets, mmap_range};
-use fluxcapacitor_core::sys::if_xdp::{XDP_UMEM_FILL_RING, XDP_UMEM_COMPLETION_RING, XDP_RX_RING, XDP_TX_RING, XDP_UMEM_PGOFF_FILL_RING, XDP_UMEM_PGOFF_COMPLETION_RING, XDP_PGOFF_RX_RING, XDP_PGOFF_TX_RING};
+use fluxcapacitor_core::sys::socket::{create_xsk_socket, bind_socket, set_umem_reg, set_ring_size, set_rcvbuf, set_sndbuf, get_mmap_offsets, get_xdp_options, mmap_range};
+use fluxcapacitor_core::sys::if_xdp::{XdpRingOffset, XDP_UMEM_FILL_RING, XDP_UMEM_COMPLETION_RING, XDP_RX_RING, XDP_TX_RING, XDP_UMEM_PGOFF_FILL_RING, XDP_UMEM_PGOFF_COMPLETION_RING, XDP_PGOFF_RX_RING, XDP_PGOFF_TX_RING, XDP_ZEROCOPY, XDP_OPTIONS_ZEROCOPY, XDP_USE_SG, XDP_USE_NEED_WAKEUP, XskTxMetadata};
 use fluxcapacitor_core::ring::{ProducerRing, ConsumerRing, XDPDesc};
+use fluxcapacitor_core::sys::utils::NetnsGuard;
+use fluxcapacitor_core::sys::socket::RawFd;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+// An XSK map owned by someone else, for `xsk_map_fd` / `xsk_map_pin`.
+#[derive(Clone)]
+enum XskMapSource {
+    Fd(RawFd),
+    Pinned(PathBuf),
+}
+
+/// Link problems that do not stop a build but likely lose packets.
+/// See `FluxBuilder::check_link` and `FluxRaw::link_warnings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkWarning {
+    /// The interface is administratively down or has no carrier.
+    Down,
+    /// Frames cannot hold an MTU-sized packet plus its Ethernet header.
+    FrameTooSmall { frame_size: u32, mtu: u32 },
+}
+
+impl std::fmt::Display for LinkWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkWarning::Down => write!(f, "interface is down"),
+            LinkWarning::FrameTooSmall { frame_size, mtu } => {
+                write!(f, "frame size {} is smaller than MTU {} + Ethernet header", frame_size, mtu)
+            }
+        }
+    }
+}
 
 pub struct FluxBuilder {
     interface: String,
+    netns: Option<String>,
     queue_id: u32,
     frame_count: u32,
     frame_size: u32,
+    chunk_size: Option<u32>,
+    headroom: u32,
     poller: Poller,
     batch_size: usize,
+    comp_batch: Option<usize>,
+    poll_timeout: Duration,
+    clock: Option<Arc<dyn Clock>>,
+    drain_rx: bool,
+    commit_order: CommitOrder,
+    observer: Option<Box<dyn FluxObserver>>,
     bind_flags: u16,
     load_xdp: bool,
+    xsk_map: Option<(XskMapSource, u32)>,
+    redirect_ports: Vec<u16>,
+    rss_key: Option<[u8; RSS_KEY_LEN]>,
+    prefault_umem: bool,
+    rx_metadata: bool,
+    auto_fill: bool,
+    tx_reserve_frames: u32,
+    fill_batch: u32,
+    tx_launch_time: bool,
+    promiscuous: bool,
+    rcvbuf: Option<usize>,
+    sndbuf: Option<usize>,
+    ring_size: Option<u32>,
+    backend: Backend,
+}
+
+/// Copies every setting except the `observer`, which belongs to one engine: set a
+/// fresh one on each clone. Handy for one engine per queue:
+///
+/// ```ignore
+/// let base = FluxBuilder::new("eth0").umem_pages(4096).batch_size(64);
+/// let engines = (0..4).map(|q| base.clone().queue_id(q).build_engine()).collect::<Result<Vec<_>, _>>()?;
+/// ```
+///
+/// `load_xdp(true)` and `xsk_map_*` are copied too: load the program once (or
+/// point each clone at its own map slot) rather than on every clone.
+impl Clone for FluxBuilder {
+    fn clone(&self) -> Self {
+        Self {
+            interface: self.interface.clone(),
+            netns: self.netns.clone(),
+            queue_id: self.queue_id,
+            frame_count: self.frame_count,
+            frame_size: self.frame_size,
+            chunk_size: self.chunk_size,
+            headroom: self.headroom,
+            poller: self.poller,
+            batch_size: self.batch_size,
+            comp_batch: self.comp_batch,
+            poll_timeout: self.poll_timeout,
+            clock: self.clock.clone(),
+            drain_rx: self.drain_rx,
+            commit_order: self.commit_order,
+            observer: None,
+            bind_flags: self.bind_flags,
+            load_xdp: self.load_xdp,
+            xsk_map: self.xsk_map.clone(),
+            redirect_ports: self.redirect_ports.clone(),
+            rss_key: self.rss_key,
+            prefault_umem: self.prefault_umem,
+            rx_metadata: self.rx_metadata,
+            auto_fill: self.auto_fill,
+            tx_reserve_frames: self.tx_reserve_frames,
+            fill_batch: self.fill_batch,
+            tx_launch_time: self.tx_launch_time,
+            promiscuous: self.promiscuous,
+            rcvbuf: self.rcvbuf,
+            sndbuf: self.sndbuf,
+            ring_size: self.ring_size,
+            backend: self.backend,
+        }
+    }
 }
 
 impl FluxBuilder {
     pub fn new(interface: &str) -> Self {
         Self {
             interface: interface.to_string(),
+            netns: None,
             queue_id: 0,
             frame_count: 4096,
             frame_size: 2048,
+            chunk_size: None,
+            headroom: 0,
             poller: Poller::Adaptive,
             batch_size: 64,
+            comp_batch: None,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            clock: None,
+            drain_rx: false,
+            commit_order: CommitOrder::default(),
+            observer: None,
             bind_flags: 0,
             load_xdp: false,
+            xsk_map: None,
+            redirect_ports: Vec::new(),
+            rss_key: None,
+            prefault_umem: false,
+            rx_metadata: false,
+            auto_fill: true,
+            tx_reserve_frames: 0,
+            fill_batch: 32,
+            tx_launch_time: false,
+            promiscuous: false,
+            rcvbuf: None,
+            sndbuf: None,
+            ring_size: None,
+            backend: Backend::Xdp,
         }
     }
 
+    /// Look up the interface and create the socket in the network namespace at
+    /// `path` (e.g. `/var/run/netns/blue`), then return to the current one.
+    ///
+    /// Only the building thread switches namespaces, and only for the duration of the
+    /// build. Needs `CAP_SYS_ADMIN`. Linux only; building fails elsewhere.
+    pub fn netns(mut self, path: &str) -> Self {
+        self.netns = Some(path.to_string());
+        self
+    }
+
     pub fn queue_id(mut self, id: u32) -> Self {
         self.queue_id = id;
         self
@@ -47,6 +188,44 @@ impl FluxBuilder {
         self
     }
 
+    /// Stride between UMEM frames as registered with the kernel. Must be a power of
+    /// two, at least 2048 and at least the frame size (2048). Defaults to the frame size.
+    ///
+    /// A larger chunk leaves room in front of each frame, e.g. for headroom or RX
+    /// metadata, without changing how many bytes a packet may use.
+    pub fn chunk_size(mut self, size: u32) -> Self {
+        self.chunk_size = Some(size);
+        self
+    }
+
+    /// Bytes reserved at the start of each chunk, exposed per packet through
+    /// `PacketRef::metadata_mut`. Defaults to 0.
+    ///
+    /// Must fit in the chunk next to the frame, so raise `chunk_size` first
+    /// (e.g. a 4096-byte chunk leaves up to 2048 bytes).
+    pub fn headroom(mut self, headroom: u32) -> Self {
+        self.headroom = headroom;
+        self
+    }
+
+    /// Entries in each of the four rings. Must be a power of two.
+    /// Defaults to the UMEM frame count.
+    ///
+    /// A ring smaller than the frame count is fine: frames that do not fit on the
+    /// Fill Ring wait in the free pool (the engine's spares, or the shared pool after
+    /// `system::split`) and are handed over as the kernel consumes the ring. With
+    /// `drain_rx`, the ring must hold at least one batch (`FluxError::RingTooSmall`).
+    pub fn ring_size(mut self, size: u32) -> Self {
+        self.ring_size = Some(size);
+        self
+    }
+
+    /// Select the packet I/O backend. Defaults to `Backend::Xdp`.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
     pub fn poller(mut self, poller: Poller) -> Self {
         self.poller = poller;
         self
@@ -57,36 +236,417 @@ impl FluxBuilder {
         self
     }
 
+    /// Most RX descriptors the engine takes per pass (default 64). Another name
+    /// for `batch_size`, to pair with `comp_batch`; the two are tuned separately.
+    pub fn rx_batch(self, frames: usize) -> Self {
+        self.batch_size(frames)
+    }
+
+    /// See `FluxEngine::set_comp_batch`.
+    pub fn comp_batch(mut self, frames: usize) -> Self {
+        self.comp_batch = Some(frames);
+        self
+    }
+
+    /// See `FluxEngine::set_poll_timeout`.
+    pub fn poll_timeout(mut self, timeout: Duration) -> Self {
+        self.poll_timeout = timeout;
+        self
+    }
+
+    /// See `FluxEngine::set_clock`.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// See `FluxEngine::set_drain_rx`.
+    pub fn drain_rx(mut self, drain: bool) -> Self {
+        self.drain_rx = drain;
+        self
+    }
+
+    /// See `FluxEngine::set_commit_order`.
+    pub fn commit_order(mut self, order: CommitOrder) -> Self {
+        self.commit_order = order;
+        self
+    }
+
+    /// Register a `FluxObserver` that the engine notifies of batch and ring events.
+    pub fn observer<O: FluxObserver + 'static>(mut self, observer: O) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Fault in the whole UMEM at build time (see `UmemRegion::prefault`).
+    ///
+    /// Trades a slower, larger-footprint startup for no page-fault jitter on the first packets.
+    pub fn prefault_umem(mut self, prefault: bool) -> Self {
+        self.prefault_umem = prefault;
+        self
+    }
+
+    /// Expose the `XdpRxMeta` area in front of each frame through `PacketRef::rx_meta`.
+    ///
+    /// Requires an XDP program that fills it in (see `packet::meta`); the bundled
+    /// program does not, and without one the area holds stale headroom bytes.
+    pub fn rx_metadata(mut self, enable: bool) -> Self {
+        self.rx_metadata = enable;
+        self
+    }
+
+    /// Hand every UMEM frame to the Fill Ring on construction (the default).
+    ///
+    /// Disable for custom frame management, e.g. to keep some frames as TX templates.
+    /// The Fill Ring then starts empty and nothing is received until the user submits
+    /// frames (`FluxRx::fill_frames`, or `FluxEngine::socket.fill` directly).
+    pub fn auto_fill(mut self, enable: bool) -> Self {
+        self.auto_fill = enable;
+        self
+    }
+
+    /// Keep `n` frames out of the Fill Ring for `FluxTx::alloc_frame` (default 0).
+    ///
+    /// Without a reserve, a callback that wants to originate a packet can find every
+    /// frame on the Fill Ring or in flight. `system::split` hands the last `n` frames
+    /// to the TX half, which tops the reserve back up from completed transmits. Must
+    /// leave at least one frame for RX. `FluxEngine` does not allocate TX frames and
+    /// ignores it.
+    pub fn tx_reserve_frames(mut self, n: u32) -> Self {
+        self.tx_reserve_frames = n;
+        self
+    }
+
+    /// Frames `FluxRx::refill` hands to the Fill Ring per reservation (default 32,
+    /// at least 1). `refill` keeps going until the ring or the free list runs out,
+    /// so this only trades fewer ring index updates against handing frames to the
+    /// kernel sooner. `FluxEngine` refills in whole batches and ignores it.
+    pub fn fill_batch(mut self, frames: u32) -> Self {
+        self.fill_batch = frames.max(1);
+        self
+    }
+
+    /// Register the UMEM with room for TX metadata so `FluxTx::send_at` can hand
+    /// launch times to the NIC (default off).
+    ///
+    /// Needs Linux 6.8+ for TX metadata (6.14+ and a driver with launch-time
+    /// offload to honour it). If the kernel refuses the registration, the socket
+    /// is built without it and `send_at` falls back to sleeping; check
+    /// `FluxTx::hw_launch_time`. Ignored by `Backend::AfPacket`.
+    pub fn tx_launch_time(mut self, enabled: bool) -> Self {
+        self.tx_launch_time = enabled;
+        self
+    }
+
+    /// Put the interface in promiscuous mode on build (default off), so the socket
+    /// sees frames not addressed to it, e.g. for capture on a mirror port.
+    ///
+    /// If the build turned it on, dropping the socket turns it off again, unless it
+    /// was built in another namespace (`netns`). Promiscuous mode is one flag per
+    /// interface: the first socket to enable it also disables it for the others.
+    /// Needs `CAP_NET_ADMIN`. A no-op in the simulator.
+    pub fn promiscuous(mut self, enable: bool) -> Self {
+        self.promiscuous = enable;
+        self
+    }
+
+    /// Set the XDP socket's `SO_RCVBUF` before bind (default: the kernel's).
+    ///
+    /// AF_XDP moves packets through its rings, but some paths, such as copy mode
+    /// under bursty load, still account against the socket buffers. The kernel
+    /// doubles the value and caps it at `net.core.rmem_max`. Only applies to
+    /// `Backend::Xdp`; a no-op in the simulator.
+    pub fn rcvbuf(mut self, bytes: usize) -> Self {
+        self.rcvbuf = Some(bytes);
+        self
+    }
+
+    /// Set the XDP socket's `SO_SNDBUF` before bind, capped at
+    /// `net.core.wmem_max`. See `rcvbuf`.
+    pub fn sndbuf(mut self, bytes: usize) -> Self {
+        self.sndbuf = Some(bytes);
+        self
+    }
+
     pub fn load_xdp(mut self, load: bool) -> Self {
         self.load_xdp = load;
         self
     }
 
-    pub fn build_engine(self) -> Result<FluxEngine, std::io::Error> {
+    /// Register the socket at `index` of an existing XSK map instead of loading the
+    /// bundled program, for interfaces where something else owns XDP (e.g. an
+    /// xdp-dispatcher set up by libxdp/xdp-tools). That program decides what is
+    /// redirected; `index` is usually the queue id.
+    ///
+    /// `fd` stays owned by the caller and may be closed after the build. The kernel
+    /// drops the map entry when the socket closes. Conflicts with `load_xdp(true)`.
+    pub fn xsk_map_fd(mut self, fd: RawFd, index: u32) -> Self {
+        self.xsk_map = Some((XskMapSource::Fd(fd), index));
+        self
+    }
+
+    /// Like `xsk_map_fd`, for a map pinned in bpffs, e.g.
+    /// `/sys/fs/bpf/<prog>/xsks_map` as left by `xdp-loader load --pin-path`.
+    /// Opening a pinned map needs the same privileges as loading a program.
+    pub fn xsk_map_pin(mut self, path: &str, index: u32) -> Self {
+        self.xsk_map = Some((XskMapSource::Pinned(PathBuf::from(path)), index));
+        self
+    }
+
+    /// Only redirect IPv4 TCP/UDP packets with one of these destination ports to the socket.
+    ///
+    /// Everything else is passed to the kernel stack, so the socket can coexist with host
+    /// networking; that includes non-first IP fragments, which carry no ports. An empty list (the default) redirects all traffic on the queue.
+    /// Only takes effect together with `load_xdp(true)`.
+    pub fn redirect_ports(mut self, ports: &[u16]) -> Self {
+        self.redirect_ports = ports.to_vec();
+        self
+    }
+
+    /// Check IPv4 TCP/UDP flows against the Toeplitz hash under `key`, the same
+    /// hash `rss::flow_hash` computes.
+    ///
+    /// The program hashes over as many slots as the interface has RX queues.
+    /// The kernel only accepts a redirect to a socket bound to the receiving
+    /// queue, so the program always redirects there and counts packets whose
+    /// hashed slot differs (`FluxRaw::rss_mismatches`). The count stays at zero
+    /// when the NIC's hardware RSS uses the same key and an even spread
+    /// (`ethtool -X <if> hkey <key> equal <queues>`); then userspace, the program
+    /// and the NIC agree on where each flow lands. Only takes effect together
+    /// with `load_xdp(true)`.
+    pub fn rss_key(mut self, key: &[u8; RSS_KEY_LEN]) -> Self {
+        self.rss_key = Some(*key);
+        self
+    }
+
+    pub fn build_engine(mut self) -> Result<FluxEngine, std::io::Error> {
         let poller = self.poller;
         let batch_size = self.batch_size;
+        let poll_timeout = self.poll_timeout;
+        let clock = self.clock.take();
+        let drain_rx = self.drain_rx;
+        let commit_order = self.commit_order;
+        let comp_batch = self.comp_batch;
+        let observer = self.observer.take();
+        self.check_batch_fits().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
         let raw = self.build_raw()?;
-        Ok(FluxEngine::with_config(raw, batch_size, poller))
+        let mut engine = FluxEngine::with_config(raw, batch_size, poller);
+        engine.set_poll_timeout(poll_timeout);
+        if let Some(clock) = clock {
+            engine.set_clock(clock);
+        }
+        engine.set_drain_rx(drain_rx);
+        engine.set_commit_order(commit_order);
+        if let Some(frames) = comp_batch {
+            engine.set_comp_batch(frames);
+        }
+        if let Some(observer) = observer {
+            engine.set_observer(observer);
+        }
+        Ok(engine)
     }
 
     pub fn build_raw(self) -> Result<FluxRaw, std::io::Error> {
+        let netns = self.enter_netns()?;
+
+        // 0. Sanity-check the link. Only warn: bind reports the hard failures.
+        let link_warnings = self.link_warnings().unwrap_or_default();
+
+        let mut raw = match self.backend {
+            Backend::Xdp => self.build_xdp(),
+            Backend::AfPacket => self.build_af_packet(),
+            // Report the XDP failure: it is the primary backend.
+            Backend::Auto => self.build_xdp().or_else(|e| self.build_af_packet().map_err(|_| e)),
+        }?;
+        raw.link_warnings = link_warnings;
+
+        if self.promiscuous && !fluxcapacitor_core::sys::utils::promiscuous(raw.if_index)? {
+            fluxcapacitor_core::sys::utils::set_promiscuous(raw.if_index, true)?;
+            // Outside the build namespace the index may name another interface.
+            if self.netns.is_none() {
+                raw.promisc = Some(PromiscGuard(raw.if_index));
+            }
+        }
+        if let Some(netns) = netns {
+            netns.exit()?;
+        }
+        Ok(raw)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn build_af_packet(&self) -> Result<FluxRaw, std::io::Error> {
+        let layout = self.umem_layout()?;
+        let umem = UmemRegion::new(layout)?;
+        if self.prefault_umem {
+            umem.prefault();
+        }
+
+        let if_index = fluxcapacitor_core::sys::utils::if_nametoindex(&self.interface)?;
+        let ring_size = self.ring_entries()?;
+        let mut raw = crate::raw::af_packet::build(umem, if_index, ring_size)?;
+
+        raw.interface = self.interface.clone();
+        raw.if_index = if_index;
+        raw.queue_id = self.queue_id;
+        raw.rx_meta = self.rx_metadata;
+        raw.auto_fill = self.auto_fill;
+        raw.tx_reserve = self.tx_reserve_frames;
+        raw.fill_batch = self.fill_batch;
+        Ok(raw)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn build_af_packet(&self) -> Result<FluxRaw, std::io::Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "AF_PACKET backend requires Linux"))
+    }
+
+    /// Check the configuration without creating a socket or touching the interface.
+    ///
+    /// Catches everything `build_raw` would reject before the bind syscall: frame and
+    /// chunk sizes, ring sizes, the UMEM size and an unknown interface name. The bind
+    /// itself can still fail (privileges, driver support).
+    pub fn validate(&self) -> Result<(), FluxError> {
+        let invalid = |e: std::io::Error| FluxError::InvalidConfiguration(e.to_string());
+
+        if self.frame_count == 0 {
+            return Err(FluxError::InvalidConfiguration("UMEM needs at least one frame".to_string()));
+        }
+        let layout = self.umem_layout().map_err(invalid)?;
+        if (layout.frame_count as u64).checked_mul(layout.chunk_size as u64).is_none_or(|size| size > isize::MAX as u64) {
+            return Err(FluxError::InvalidConfiguration(format!(
+                "{} frames of {} bytes do not fit in the address space", layout.frame_count, layout.chunk_size
+            )));
+        }
+        self.ring_entries().map_err(invalid)?;
+        self.check_xdp_program().map_err(invalid)?;
+        self.check_batch_fits()?;
+
+        #[cfg(not(target_os = "linux"))]
+        if self.backend == Backend::AfPacket {
+            return Err(FluxError::InvalidConfiguration("AF_PACKET backend requires Linux".to_string()));
+        }
+
+        let _netns = self.enter_netns()?;
+        fluxcapacitor_core::sys::utils::if_nametoindex(&self.interface)
+            .map_err(|_| FluxError::InterfaceNotSupported)?;
+        Ok(())
+    }
+
+    /// Look for link problems a build would only warn about (interface down,
+    /// frames shorter than the MTU), without building anything. A built socket
+    /// reports the same through `FluxRaw::link_warnings`.
+    pub fn check_link(&self) -> Result<Vec<LinkWarning>, std::io::Error> {
+        let _netns = self.enter_netns()?;
+        self.link_warnings()
+    }
+
+    fn link_warnings(&self) -> Result<Vec<LinkWarning>, std::io::Error> {
+        let info = fluxcapacitor_core::sys::utils::interface_info(&self.interface)?;
+        let mut warnings = Vec::new();
+        if !info.up || !info.running {
+            warnings.push(LinkWarning::Down);
+        }
+        // Ethernet header on top of the MTU, with no UMEM headroom configured.
+        if info.mtu + 14 > self.frame_size {
+            warnings.push(LinkWarning::FrameTooSmall { frame_size: self.frame_size, mtu: info.mtu });
+        }
+        Ok(warnings)
+    }
+
+    fn enter_netns(&self) -> Result<Option<NetnsGuard>, std::io::Error> {
+        self.netns.as_deref().map(NetnsGuard::enter).transpose()
+    }
+
+    fn umem_layout(&self) -> Result<UmemLayout, std::io::Error> {
+        let chunk_size = self.chunk_size.unwrap_or(self.frame_size);
+        if !chunk_size.is_power_of_two() || chunk_size < 2048 || chunk_size < self.frame_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("chunk size {} must be a power of 2, at least 2048 and at least the frame size {}", chunk_size, self.frame_size),
+            ));
+        }
+        if self.frame_size as u64 + self.headroom as u64 > chunk_size as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("headroom {} does not fit in chunk size {} next to the frame size {}", self.headroom, chunk_size, self.frame_size),
+            ));
+        }
+        if self.tx_reserve_frames >= self.frame_count && self.frame_count > 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("TX reserve of {} frames leaves none of the {} for RX", self.tx_reserve_frames, self.frame_count),
+            ));
+        }
+        Ok(UmemLayout::with_chunk_size(self.frame_size, self.frame_count, chunk_size).with_headroom(self.headroom))
+    }
+
+    // Draining stops at the first short chunk, and a chunk can never be longer than
+    // the RX ring, so `drain_rx` with a bigger batch would never drain.
+    fn check_batch_fits(&self) -> Result<(), FluxError> {
+        match self.ring_entries() {
+            Ok(ring) if self.drain_rx && (ring as usize) < self.batch_size => {
+                Err(FluxError::RingTooSmall { ring, batch: self.batch_size })
+            }
+            // A bad ring size is reported by `ring_entries` itself.
+            _ => Ok(()),
+        }
+    }
+
+    fn check_xdp_program(&self) -> Result<(), std::io::Error> {
+        if self.load_xdp && self.xsk_map.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "load_xdp and an external XSK map are mutually exclusive",
+            ));
+        }
+        Ok(())
+    }
+
+    fn ring_entries(&self) -> Result<u32, std::io::Error> {
+        let size = self.ring_size.unwrap_or(self.frame_count);
+        if size == 0 || !size.is_power_of_two() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("ring size {} must be a power of 2", size),
+            ));
+        }
+        Ok(size)
+    }
+
+    fn build_xdp(&self) -> Result<FluxRaw, std::io::Error> {
+        self.check_xdp_program()?;
+
         // 1. Create UMEM
-        let layout = UmemLayout::new(self.frame_size, self.frame_count);
+        let layout = self.umem_layout()?;
         let mut umem = UmemRegion::new(layout)?;
         
         // 2. Create Socket
         let fd = create_xsk_socket()?;
+        if let Some(bytes) = self.rcvbuf {
+            set_rcvbuf(fd, bytes)?;
+        }
+        if let Some(bytes) = self.sndbuf {
+            set_sndbuf(fd, bytes)?;
+        }
 
         // simulator: link umem to fd so they share same memory
         #[cfg(not(target_os = "linux"))]
         umem.set_fd(fd);
+
+        if self.prefault_umem {
+            umem.prefault();
+        }
         
         // 3. Register UMEM
-        let headroom = 0;
-        set_umem_reg(fd, umem.as_ptr() as u64, umem.len() as u64, self.frame_size, headroom)?;
+        let tx_metadata_len = std::mem::size_of::<XskTxMetadata>() as u32;
+        let tx_launch_time = self.tx_launch_time
+            && set_umem_reg(fd, umem.as_ptr() as u64, umem.len() as u64, layout.chunk_size, layout.headroom, tx_metadata_len).is_ok();
+        if !tx_launch_time {
+            set_umem_reg(fd, umem.as_ptr() as u64, umem.len() as u64, layout.chunk_size, layout.headroom, 0)?;
+        }
         
         // 4. Set Ring Sizes
-        let ring_size = self.frame_count;
+        let ring_size = self.ring_entries()?;
         set_ring_size(fd, XDP_UMEM_FILL_RING as i32, ring_size)?;
         set_ring_size(fd, XDP_UMEM_COMPLETION_RING as i32, ring_size)?;
         set_ring_size(fd, XDP_RX_RING as i32, ring_size)?;
@@ -96,20 +656,25 @@ impl FluxBuilder {
         let off = get_mmap_offsets(fd)?;
         
         // Fill Ring
-        let fill_len = (off.fr.desc + (ring_size as u64) * 8) as usize;
+        let fill_len = ring_mmap_len("fill", &off.fr, ring_size, 8)?;
         let fill_ptr = unsafe { mmap_range(fd, fill_len, XDP_UMEM_PGOFF_FILL_RING) }?;
-        let fill_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(fill_ptr, fill_len) };
+        let fill_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(fill_ptr, fill_len) }?;
         let fill = unsafe { ProducerRing::new(
             fill_ptr.add(off.fr.producer as usize) as *mut u32,
             fill_ptr.add(off.fr.consumer as usize) as *mut u32,
             fill_ptr.add(off.fr.desc as usize) as *mut u64,
             ring_size,
         )};
+        let fill = if off.fr.flags != 0 {
+            unsafe { fill.with_flags(fill_ptr.add(off.fr.flags as usize) as *mut u32) }
+        } else {
+            fill
+        };
         
         // Completion Ring
-        let comp_len = (off.cr.desc + (ring_size as u64) * 8) as usize;
+        let comp_len = ring_mmap_len("completion", &off.cr, ring_size, 8)?;
         let comp_ptr = unsafe { mmap_range(fd, comp_len, XDP_UMEM_PGOFF_COMPLETION_RING) }?;
-        let comp_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(comp_ptr, comp_len) };
+        let comp_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(comp_ptr, comp_len) }?;
         let comp = unsafe { ConsumerRing::new(
             comp_ptr.add(off.cr.producer as usize) as *mut u32,
             comp_ptr.add(off.cr.consumer as usize) as *mut u32,
@@ -118,9 +683,9 @@ impl FluxBuilder {
         )};
         
         // RX Ring
-        let rx_len = (off.rx.desc + (ring_size as u64) * 16) as usize;
+        let rx_len = ring_mmap_len("rx", &off.rx, ring_size, 16)?;
         let rx_ptr = unsafe { mmap_range(fd, rx_len, XDP_PGOFF_RX_RING) }?;
-        let rx_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(rx_ptr, rx_len) };
+        let rx_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(rx_ptr, rx_len) }?;
         let rx = unsafe { ConsumerRing::new(
             rx_ptr.add(off.rx.producer as usize) as *mut u32,
             rx_ptr.add(off.rx.consumer as usize) as *mut u32,
@@ -129,26 +694,37 @@ impl FluxBuilder {
         )};
         
         // TX Ring
-        let tx_len = (off.tx.desc + (ring_size as u64) * 16) as usize;
+        let tx_len = ring_mmap_len("tx", &off.tx, ring_size, 16)?;
         let tx_ptr = unsafe { mmap_range(fd, tx_len, XDP_PGOFF_TX_RING) }?;
-        let tx_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(tx_ptr, tx_len) };
+        let tx_map = unsafe { fluxcapacitor_core::sys::mmap::MmapArea::from_raw(tx_ptr, tx_len) }?;
         let tx = unsafe { ProducerRing::new(
             tx_ptr.add(off.tx.producer as usize) as *mut u32,
             tx_ptr.add(off.tx.consumer as usize) as *mut u32,
             tx_ptr.add(off.tx.desc as usize) as *mut XDPDesc,
             ring_size,
         )};
+        let tx = if off.tx.flags != 0 {
+            unsafe { tx.with_flags(tx_ptr.add(off.tx.flags as usize) as *mut u32) }
+        } else {
+            tx
+        };
         
         // 6. Bind (if interface provided)
         let if_index = fluxcapacitor_core::sys::utils::if_nametoindex(&self.interface)?;
         bind_socket(fd, if_index, self.queue_id, self.bind_flags)?;
 
+        // Ask the kernel which mode it picked. Older kernels lack XDP_OPTIONS;
+        // a successful bind with XDP_ZEROCOPY still guarantees zero-copy there.
+        let zerocopy = get_xdp_options(fd)
+            .map(|opts| opts & XDP_OPTIONS_ZEROCOPY != 0)
+            .unwrap_or(self.bind_flags & XDP_ZEROCOPY != 0);
+
         #[cfg(target_os = "linux")]
         let mut bpf_handle = None;
 
         #[cfg(target_os = "linux")]
         if self.load_xdp {
-             use aya::Bpf;
+             use aya::Ebpf;
              use aya::programs::{Xdp, XdpFlags};
              use aya::maps::XskMap;
 
@@ -156,7 +732,29 @@ impl FluxBuilder {
                  std::io::Error::new(std::io::ErrorKind::NotFound, "eBPF object not found")
              })?;
 
-             let mut bpf = Bpf::load_file(bpf_path).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+             let mut bpf = Ebpf::load_file(bpf_path).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+             // Populate the port filter before attaching so no unwanted traffic is redirected.
+             if !self.redirect_ports.is_empty() {
+                 use aya::maps::{Array, HashMap};
+
+                 let mut ports: HashMap<_, u16, u8> = bpf.map_mut("REDIRECT_PORTS").ok_or_else(|| {
+                     std::io::Error::new(std::io::ErrorKind::NotFound, "REDIRECT_PORTS not found")
+                 })?.try_into().map_err(std::io::Error::other)?;
+                 for &port in &self.redirect_ports {
+                     ports.insert(port, 1, 0).map_err(std::io::Error::other)?;
+                 }
+
+                 let mut config: Array<_, u32> = bpf.map_mut("FILTER_CONFIG").ok_or_else(|| {
+                     std::io::Error::new(std::io::ErrorKind::NotFound, "FILTER_CONFIG not found")
+                 })?.try_into().map_err(std::io::Error::other)?;
+                 config.set(0, 1, 0).map_err(std::io::Error::other)?;
+             }
+
+             if let Some(key) = &self.rss_key {
+                 write_rss_config(&mut bpf, key, rx_queue_count(&self.interface))?;
+             }
+
              let program: &mut Xdp = bpf.program_mut("fluxcapacitor").ok_or_else(|| {
                  std::io::Error::new(std::io::ErrorKind::NotFound, "XDP program 'fluxcapacitor' not found")
              })?.try_into().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
@@ -172,6 +770,12 @@ impl FluxBuilder {
 
              bpf_handle = Some(bpf);
         }
+
+        // Someone else's program redirects to us: just take our slot in its map.
+        #[cfg(target_os = "linux")]
+        if let Some((source, index)) = &self.xsk_map {
+            register_in_xsk_map(source, *index, fd)?;
+        }
  
         let mut raw = FluxRaw::new(
             umem, 
@@ -182,6 +786,18 @@ impl FluxBuilder {
             fd
         );
 
+        raw.interface = self.interface.clone();
+        raw.if_index = if_index;
+        raw.queue_id = self.queue_id;
+        raw.zerocopy = zerocopy;
+        raw.multi_buffer = self.bind_flags & XDP_USE_SG != 0;
+        raw.need_wakeup = self.bind_flags & XDP_USE_NEED_WAKEUP != 0;
+        raw.rx_meta = self.rx_metadata;
+        raw.auto_fill = self.auto_fill;
+        raw.tx_reserve = self.tx_reserve_frames;
+        raw.fill_batch = self.fill_batch;
+        raw.tx_launch_time = tx_launch_time;
+
         #[cfg(target_os = "linux")]
         {
             raw.bpf = bpf_handle;
@@ -191,6 +807,81 @@ impl FluxBuilder {
     }
 }
 
+/// Length to mmap for a ring of `size` entries of `stride` bytes at offsets `off`.
+///
+/// Rejects offsets that would put the producer/consumer words inside the descriptor
+/// array, or a length that overflows, so a confused kernel (or simulator) cannot
+/// lead ring accesses outside the mapping.
+fn ring_mmap_len(ring: &str, off: &XdpRingOffset, size: u32, stride: u64) -> Result<usize, std::io::Error> {
+    let invalid = |what: &str| std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("inconsistent {} ring offsets ({}): {:?}, {} entries", ring, what, off, size),
+    );
+
+    if size == 0 || !size.is_power_of_two() {
+        return Err(invalid("size is not a power of two"));
+    }
+    // Counters (and the flags word, if the kernel reports one) precede the descriptors.
+    let words = [off.producer, off.consumer].into_iter().chain((off.flags != 0).then_some(off.flags));
+    for word in words {
+        if word.checked_add(4).is_none_or(|end| end > off.desc) {
+            return Err(invalid("control word overlaps descriptors"));
+        }
+    }
+    if off.producer.abs_diff(off.consumer) < 4 {
+        return Err(invalid("producer and consumer overlap"));
+    }
+
+    (size as u64).checked_mul(stride)
+        .and_then(|descs| descs.checked_add(off.desc))
+        .filter(|&len| len <= isize::MAX as u64)
+        .map(|len| len as usize)
+        .ok_or_else(|| invalid("length overflows"))
+}
+
+#[cfg(target_os = "linux")]
+fn register_in_xsk_map(source: &XskMapSource, index: u32, fd: RawFd) -> Result<(), std::io::Error> {
+    use aya::maps::{Map, MapData, XskMap};
+    use std::os::fd::BorrowedFd;
+
+    let data = match source {
+        XskMapSource::Fd(map_fd) => {
+            // Duplicate it: the caller keeps its descriptor.
+            let owned = unsafe { BorrowedFd::borrow_raw(*map_fd) }.try_clone_to_owned()?;
+            MapData::from_fd(owned)
+        }
+        XskMapSource::Pinned(path) => MapData::from_pin(path),
+    }.map_err(std::io::Error::other)?;
+
+    let mut xsk_map: XskMap<_> = Map::XskMap(data).try_into().map_err(std::io::Error::other)?;
+    xsk_map.set(index, fd, 0).map_err(std::io::Error::other)
+}
+
+/// Store `key` in `RSS_KEY` and spread flows over `slots` XSK map entries.
+#[cfg(target_os = "linux")]
+fn write_rss_config(bpf: &mut aya::Ebpf, key: &[u8; RSS_KEY_LEN], slots: u32) -> Result<(), std::io::Error> {
+    use aya::maps::Array;
+
+    let mut keys: Array<_, [u8; RSS_KEY_LEN]> = bpf.map_mut("RSS_KEY").ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "RSS_KEY not found")
+    })?.try_into().map_err(std::io::Error::other)?;
+    keys.set(0, *key, 0).map_err(std::io::Error::other)?;
+
+    let mut config: Array<_, u32> = bpf.map_mut("RSS_CONFIG").ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "RSS_CONFIG not found")
+    })?.try_into().map_err(std::io::Error::other)?;
+    config.set(0, slots, 0).map_err(std::io::Error::other)
+}
+
+/// RX queues of `interface` as listed in sysfs, at least 1.
+#[cfg(target_os = "linux")]
+fn rx_queue_count(interface: &str) -> u32 {
+    std::fs::read_dir(format!("/sys/class/net/{}/queues", interface))
+        .map(|dir| dir.filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().starts_with("rx-")).count() as u32)
+        .unwrap_or(0)
+        .max(1)
+}
+
 #[cfg(target_os = "linux")]
 fn find_bpf_program_internal() -> Option<std::path::PathBuf> {
     let target_dir = std::path::Path::new("target");
@@ -202,4 +893,124 @@ fn find_bpf_program_internal() -> Option<std::path::PathBuf> {
         }
     }
     None
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_check_link_on_loopback() {
+        // lo is up with a 64 KiB MTU, far more than a 2048-byte frame holds
+        let mtu = fluxcapacitor_core::sys::utils::interface_info("lo").expect("lo missing").mtu;
+        assert_eq!(
+            FluxBuilder::new("lo").check_link().unwrap(),
+            [LinkWarning::FrameTooSmall { frame_size: 2048, mtu }]
+        );
+        assert!(FluxBuilder::new("no-such-if0").check_link().is_err());
+    }
+
+    // The simulator's layout: producer, consumer, then descriptors.
+    fn sim_offsets() -> XdpRingOffset {
+        XdpRingOffset { producer: 0, consumer: 4, desc: 8, flags: 0 }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_sizes() {
+        let invalid = |builder: FluxBuilder| matches!(builder.validate(), Err(FluxError::InvalidConfiguration(_)));
+
+        assert!(invalid(FluxBuilder::new("lo").umem_pages(0)));
+        assert!(invalid(FluxBuilder::new("lo").chunk_size(3000)));
+        assert!(invalid(FluxBuilder::new("lo").chunk_size(1024)));
+        assert!(invalid(FluxBuilder::new("lo").ring_size(1000)));
+        assert!(invalid(FluxBuilder::new("lo").headroom(64)));
+        assert!(invalid(FluxBuilder::new("lo").umem_pages(64).tx_reserve_frames(64)));
+        assert!(invalid(FluxBuilder::new("lo").umem_pages(64).load_xdp(true).xsk_map_pin("/sys/fs/bpf/xsks_map", 0)));
+        assert!(FluxBuilder::new("lo").umem_pages(64).tx_reserve_frames(63).umem_layout().is_ok());
+        assert!(FluxBuilder::new("lo").chunk_size(4096).headroom(64).umem_layout().is_ok());
+        // Ring size defaults to the frame count
+        assert!(invalid(FluxBuilder::new("lo").umem_pages(100)));
+    }
+
+    #[test]
+    fn test_drain_rx_needs_a_batch_of_ring() {
+        let builder = FluxBuilder::new("lo").umem_pages(64).ring_size(16).batch_size(32).drain_rx(true);
+        assert!(matches!(builder.validate(), Err(FluxError::RingTooSmall { ring: 16, batch: 32 })));
+        let err = builder.build_engine().err().expect("drain_rx with a short ring must fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        // Without draining a small ring only means shorter batches
+        let builder = FluxBuilder::new("lo").umem_pages(64).ring_size(16).batch_size(32);
+        assert!(builder.check_batch_fits().is_ok());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_validate_interface() {
+        assert!(FluxBuilder::new("lo").umem_pages(64).validate().is_ok());
+        assert!(matches!(
+            FluxBuilder::new("nosuchif0").validate(),
+            Err(FluxError::InterfaceNotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_rx_and_comp_batch_are_independent() {
+        let builder = FluxBuilder::new("lo").rx_batch(8).comp_batch(2);
+        assert_eq!((builder.batch_size, builder.comp_batch), (8, Some(2)));
+        let builder = builder.rx_batch(16);
+        assert_eq!((builder.batch_size, builder.comp_batch), (16, Some(2)));
+        let builder = builder.comp_batch(128);
+        assert_eq!((builder.batch_size, builder.comp_batch), (16, Some(128)));
+    }
+
+    #[test]
+    fn test_clone_keeps_settings() {
+        let base = FluxBuilder::new("lo").umem_pages(64).ring_size(16).batch_size(32).drain_rx(true);
+        let clone = base.clone().queue_id(3);
+        assert_eq!(clone.queue_id, 3);
+        assert!(matches!(clone.validate(), Err(FluxError::RingTooSmall { ring: 16, batch: 32 })));
+        assert_eq!(base.queue_id, 0);
+    }
+
+    #[test]
+    fn test_ring_size_must_be_power_of_two() {
+        let builder = FluxBuilder::new("eth0").ring_size(6);
+        assert_eq!(builder.ring_entries().unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert!(FluxBuilder::new("eth0").ring_size(0).ring_entries().is_err());
+        assert_eq!(FluxBuilder::new("eth0").ring_size(512).ring_entries().unwrap(), 512);
+    }
+
+    #[test]
+    fn test_ring_mmap_len() {
+        assert_eq!(ring_mmap_len("fill", &sim_offsets(), 16, 8).unwrap(), 8 + 16 * 8);
+        assert_eq!(ring_mmap_len("rx", &sim_offsets(), 16, 16).unwrap(), 8 + 16 * 16);
+
+        // Kernel layout with a flags word
+        let off = XdpRingOffset { producer: 0, consumer: 64, desc: 192, flags: 128 };
+        assert_eq!(ring_mmap_len("tx", &off, 2048, 16).unwrap(), 192 + 2048 * 16);
+    }
+
+    #[test]
+    fn test_ring_mmap_len_rejects_inconsistent_offsets() {
+        let bad = |off: XdpRingOffset, size: u32| {
+            ring_mmap_len("rx", &off, size, 16).unwrap_err().kind()
+        };
+
+        // Consumer inside the descriptor array
+        let off = XdpRingOffset { consumer: 12, ..sim_offsets() };
+        assert_eq!(bad(off, 16), std::io::ErrorKind::InvalidData);
+        // Producer and consumer share a word
+        let off = XdpRingOffset { consumer: 2, ..sim_offsets() };
+        assert_eq!(bad(off, 16), std::io::ErrorKind::InvalidData);
+        // Flags word past the start of the descriptors
+        let off = XdpRingOffset { flags: 8, ..sim_offsets() };
+        assert_eq!(bad(off, 16), std::io::ErrorKind::InvalidData);
+        // Descriptor offset so large the length overflows
+        let off = XdpRingOffset { desc: u64::MAX - 8, ..sim_offsets() };
+        assert_eq!(bad(off, 16), std::io::ErrorKind::InvalidData);
+        // Size that is not a power of two
+        assert_eq!(bad(sim_offsets(), 24), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -1,13 +1,146 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Poller {
     /// Burns 100% CPU. Latency: <10us.
     Busy,
     /// Sleeps immediately. Latency: >50us. Saves Power.
+    ///
+    /// Blocks in `poll()` for at most the poll timeout, so a stop request is
+    /// noticed within that time even without traffic.
     Wait,
     /// Spins for a short duration, then sleeps. Best general-purpose balance.
     Adaptive,
 }
 
+/// A `Poller` that can be switched while an engine runs, e.g. from a control thread
+/// that moves to `Busy` under sustained load. See `FluxEngine::poller_control`.
+#[derive(Debug)]
+pub struct PollerControl(AtomicU8);
+
+impl PollerControl {
+    pub fn new(poller: Poller) -> Self {
+        Self(AtomicU8::new(poller as u8))
+    }
+
+    pub fn get(&self) -> Poller {
+        match self.0.load(Ordering::Relaxed) {
+            0 => Poller::Busy,
+            1 => Poller::Wait,
+            _ => Poller::Adaptive,
+        }
+    }
+
+    /// Takes effect on the engine's next loop iteration.
+    pub fn set(&self, poller: Poller) {
+        self.0.store(poller as u8, Ordering::Relaxed);
+    }
+}
+
+/// Where the engine reads the time for its idle strategy. See `FluxEngine::set_clock`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// `Instant::now()`. The default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when `advance`d, to test time-dependent behaviour
+/// such as `Poller::Adaptive` deterministically.
+#[derive(Debug)]
+pub struct MockClock(Mutex<Instant>);
+
+impl MockClock {
+    /// Starts at the current time.
+    pub fn new() -> Self {
+        Self(Mutex::new(Instant::now()))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How long `Poller::Adaptive` keeps spinning after the last packet before it
+/// starts sleeping.
+pub const ADAPTIVE_SPIN: Duration = Duration::from_micros(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IdleStep {
+    /// Yield the CPU and poll again right away.
+    Spin,
+    /// Nap for a millisecond.
+    Sleep,
+}
+
+// `Poller::Adaptive`: spin for `ADAPTIVE_SPIN` after the last packet, then sleep.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AdaptiveIdle {
+    last_packet: Instant,
+}
+
+impl AdaptiveIdle {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self { last_packet: now }
+    }
+
+    pub(crate) fn packet(&mut self, now: Instant) {
+        self.last_packet = now;
+    }
+
+    pub(crate) fn step(&self, now: Instant) -> IdleStep {
+        if now.saturating_duration_since(self.last_packet) > ADAPTIVE_SPIN {
+            IdleStep::Sleep
+        } else {
+            IdleStep::Spin
+        }
+    }
+
+    pub(crate) fn idle(&self, now: Instant) {
+        match self.step(now) {
+            IdleStep::Sleep => std::thread::sleep(Duration::from_millis(1)),
+            IdleStep::Spin => std::thread::yield_now(),
+        }
+    }
+}
+
+/// Default upper bound on a single blocking RX wait (`Poller::Wait`).
+pub const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Where the engine spends time on ring housekeeping within a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitOrder {
+    /// Reclaim completed TX frames into the Fill Ring before reading RX (the default).
+    /// Frees frames as early as possible, at the cost of delaying every batch.
+    #[default]
+    ReclaimFirst,
+    /// Submit and kick TX as soon as the callback returns, then recycle dropped
+    /// frames and reclaim completions. Shortest RX-to-TX path for cut-through
+    /// forwarding; completed frames wait one batch longer to be refilled.
+    TxFirst,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CongestionStrategy {
     /// Return an error immediately if ring is full.
@@ -15,3 +148,20 @@ pub enum CongestionStrategy {
     /// Block the thread until space is available.
     Block,
 }
+
+/// Packet I/O backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// AF_XDP. Needs Linux 5.x, a driver with XDP support and CAP_NET_ADMIN.
+    Xdp,
+    /// AF_PACKET with a TPACKET_V3 ring, for hosts without AF_XDP (CI, VMs).
+    ///
+    /// Exposes the same rings so `FluxRaw` and `FluxEngine` work unchanged, but every
+    /// packet is copied twice (kernel -> TPACKET ring -> UMEM), TX costs one syscall
+    /// per frame, and the socket sees every queue of the interface. Expect several
+    /// times lower packet rates than XDP; use it for development, not production.
+    /// `system::split` requires `Xdp`. Linux only.
+    AfPacket,
+    /// Try `Xdp`, fall back to `AfPacket` if it cannot be set up.
+    Auto,
+}
@@ -0,0 +1,464 @@
+//! Per-flow connection tracking for stateful filtering.
+//!
+//! Flows are keyed on the normalized IPv4 5-tuple, so both directions of a
+//! connection share one entry. TCP state follows the observed flags; UDP flows are
+//! "established" once the responder has replied. The table is a fixed-size slab
+//! allocated up front: when it is full, the least recently seen flow is evicted.
+//!
+//! ```ignore
+//! let mut ct = ConnTrack::new(65536);
+//! engine.run(&stop, |batch| {
+//!     for mut pkt in batch.iter_mut() {
+//!         match ct.update(&pkt) {
+//!             ConnState::Established | ConnState::Closing => pkt.send(),
+//!             _ => pkt.drop(),
+//!         }
+//!     }
+//! })?;
+//! ```
+
+use crate::packet::PacketRef;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const TCP_FIN: u16 = 0x01;
+const TCP_SYN: u16 = 0x02;
+const TCP_RST: u16 = 0x04;
+const TCP_ACK: u16 = 0x10;
+
+const NIL: u32 = u32::MAX;
+
+/// Tracking verdict for a packet, after the packet has been accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnState {
+    /// First packets of a flow: a TCP handshake in progress, or a UDP flow that
+    /// has not seen a reply yet.
+    New,
+    /// Traffic has been seen in both directions (TCP handshake completed).
+    Established,
+    /// A TCP FIN or RST was seen. The entry lingers for a short timeout.
+    Closing,
+    /// A TCP segment that neither opens a flow (bare SYN) nor belongs to a
+    /// tracked one. Not added to the table.
+    Invalid,
+    /// Not IPv4 TCP/UDP, or a non-first fragment. Not tracked.
+    Untracked,
+}
+
+/// Normalized 5-tuple: the lower (address, port) endpoint always comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FlowKey {
+    proto: u8,
+    lo: (u32, u16),
+    hi: (u32, u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    TcpSynSent,
+    TcpSynRecv,
+    TcpEstablished,
+    TcpClosing,
+    UdpUnreplied,
+    UdpReplied,
+}
+
+impl Phase {
+    fn state(self) -> ConnState {
+        match self {
+            Phase::TcpSynSent | Phase::TcpSynRecv | Phase::UdpUnreplied => ConnState::New,
+            Phase::TcpEstablished | Phase::UdpReplied => ConnState::Established,
+            Phase::TcpClosing => ConnState::Closing,
+        }
+    }
+}
+
+struct Entry {
+    key: FlowKey,
+    phase: Phase,
+    // Whether the `lo` endpoint opened the flow.
+    initiator_lo: bool,
+    expires: Instant,
+    prev: u32,
+    next: u32,
+}
+
+/// Bounded connection tracking table with LRU eviction.
+pub struct ConnTrack {
+    map: HashMap<FlowKey, u32>,
+    entries: Vec<Entry>,
+    free: Vec<u32>,
+    // Most recently seen at `head`, eviction candidate at `tail`.
+    head: u32,
+    tail: u32,
+    capacity: usize,
+    timeouts: Timeouts,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Timeouts {
+    tcp: Duration,
+    udp: Duration,
+    transient: Duration,
+}
+
+impl Timeouts {
+    fn for_phase(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::TcpEstablished => self.tcp,
+            Phase::TcpSynSent | Phase::TcpSynRecv | Phase::TcpClosing => self.transient,
+            Phase::UdpUnreplied | Phase::UdpReplied => self.udp,
+        }
+    }
+}
+
+impl ConnTrack {
+    /// Create a table holding at most `capacity` flows (at least 1).
+    ///
+    /// Defaults: 300s idle for established TCP, 30s for UDP, 10s for TCP flows
+    /// still in the handshake or closing.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            map: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            free: Vec::with_capacity(capacity),
+            head: NIL,
+            tail: NIL,
+            capacity,
+            timeouts: Timeouts {
+                tcp: Duration::from_secs(300),
+                udp: Duration::from_secs(30),
+                transient: Duration::from_secs(10),
+            },
+        }
+    }
+
+    /// Idle timeout for established TCP flows.
+    pub fn tcp_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.tcp = timeout;
+        self
+    }
+
+    /// Idle timeout for UDP flows.
+    pub fn udp_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.udp = timeout;
+        self
+    }
+
+    /// Idle timeout for TCP flows in the handshake or closing.
+    pub fn transient_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.transient = timeout;
+        self
+    }
+
+    /// Number of tracked flows.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Account for `packet` and return the state of its flow.
+    pub fn update(&mut self, packet: &PacketRef) -> ConnState {
+        self.update_at(packet, Instant::now())
+    }
+
+    /// Like `update`, with an explicit timestamp (e.g. one `Instant::now()` per batch).
+    pub fn update_at(&mut self, packet: &PacketRef, now: Instant) -> ConnState {
+        let Some(ip) = packet.ipv4() else {
+            return ConnState::Untracked;
+        };
+        // Only the first fragment carries the L4 header.
+        if u16::from_be(ip.frag_off) & 0x1FFF != 0 {
+            return ConnState::Untracked;
+        }
+        let (src, dst) = (ip.src(), ip.dst());
+
+        let (proto, src_port, dst_port, flags) = match ip.proto {
+            6 => match packet.tcp() { // TCP
+                Some(tcp) => (6, tcp.src_port(), tcp.dst_port(), Some(tcp.flags())),
+                None => return ConnState::Untracked,
+            },
+            17 => match packet.udp() { // UDP
+                Some(udp) => (17, udp.src_port(), udp.dst_port(), None),
+                None => return ConnState::Untracked,
+            },
+            _ => return ConnState::Untracked,
+        };
+
+        let a = (src, src_port);
+        let b = (dst, dst_port);
+        let from_lo = a <= b;
+        let key = if from_lo {
+            FlowKey { proto, lo: a, hi: b }
+        } else {
+            FlowKey { proto, lo: b, hi: a }
+        };
+
+        // A flow idle past its timeout is over, whether or not `evict` ran yet.
+        let existing = match self.map.get(&key).copied() {
+            Some(idx) if self.entries[idx as usize].expires <= now => {
+                self.remove(idx);
+                None
+            }
+            existing => existing,
+        };
+
+        match existing {
+            Some(idx) => {
+                let timeouts = self.timeouts;
+                let entry = &mut self.entries[idx as usize];
+                let from_initiator = from_lo == entry.initiator_lo;
+                entry.phase = match flags {
+                    Some(flags) => {
+                        // A fresh SYN on a closing flow is the port being reused.
+                        if entry.phase == Phase::TcpClosing && flags & (TCP_SYN | TCP_ACK) == TCP_SYN {
+                            entry.initiator_lo = from_lo;
+                            Phase::TcpSynSent
+                        } else {
+                            tcp_transition(entry.phase, flags, from_initiator)
+                        }
+                    }
+                    None if !from_initiator => Phase::UdpReplied,
+                    None => entry.phase,
+                };
+                entry.expires = now + timeouts.for_phase(entry.phase);
+                let state = entry.phase.state();
+                self.touch(idx);
+                state
+            }
+            None => {
+                let phase = match flags {
+                    Some(flags) if flags & (TCP_SYN | TCP_ACK | TCP_RST) == TCP_SYN => Phase::TcpSynSent,
+                    Some(_) => return ConnState::Invalid,
+                    None => Phase::UdpUnreplied,
+                };
+                self.insert(key, phase, from_lo, now + self.timeouts.for_phase(phase));
+                phase.state()
+            }
+        }
+    }
+
+    /// Remove every flow idle past its timeout. Returns the number removed.
+    pub fn evict(&mut self, now: Instant) -> usize {
+        let mut removed = 0;
+        let mut idx = self.tail;
+        while idx != NIL {
+            let prev = self.entries[idx as usize].prev;
+            if self.entries[idx as usize].expires <= now {
+                self.remove(idx);
+                removed += 1;
+            }
+            idx = prev;
+        }
+        removed
+    }
+
+    fn insert(&mut self, key: FlowKey, phase: Phase, initiator_lo: bool, expires: Instant) {
+        if self.map.len() >= self.capacity {
+            self.remove(self.tail);
+        }
+
+        let entry = Entry { key, phase, initiator_lo, expires, prev: NIL, next: NIL };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.entries[idx as usize] = entry;
+                idx
+            }
+            None => {
+                self.entries.push(entry);
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.map.insert(key, idx);
+        self.link_front(idx);
+    }
+
+    fn remove(&mut self, idx: u32) {
+        self.unlink(idx);
+        self.map.remove(&self.entries[idx as usize].key);
+        self.free.push(idx);
+    }
+
+    fn touch(&mut self, idx: u32) {
+        if self.head != idx {
+            self.unlink(idx);
+            self.link_front(idx);
+        }
+    }
+
+    fn link_front(&mut self, idx: u32) {
+        self.entries[idx as usize].prev = NIL;
+        self.entries[idx as usize].next = self.head;
+        if self.head != NIL {
+            self.entries[self.head as usize].prev = idx;
+        }
+        self.head = idx;
+        if self.tail == NIL {
+            self.tail = idx;
+        }
+    }
+
+    fn unlink(&mut self, idx: u32) {
+        let (prev, next) = (self.entries[idx as usize].prev, self.entries[idx as usize].next);
+        if prev != NIL {
+            self.entries[prev as usize].next = next;
+        } else {
+            self.head = next;
+        }
+        if next != NIL {
+            self.entries[next as usize].prev = prev;
+        } else {
+            self.tail = prev;
+        }
+    }
+}
+
+fn tcp_transition(phase: Phase, flags: u16, from_initiator: bool) -> Phase {
+    if flags & (TCP_RST | TCP_FIN) != 0 {
+        return Phase::TcpClosing;
+    }
+    match phase {
+        Phase::TcpSynSent if !from_initiator && flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK => Phase::TcpSynRecv,
+        Phase::TcpSynRecv if from_initiator && flags & (TCP_SYN | TCP_ACK) == TCP_ACK => Phase::TcpEstablished,
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Action;
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 2];
+    const SERVER: [u8; 4] = [10, 0, 0, 1];
+
+    // Eth + IPv4 + 20-byte TCP or 8-byte UDP header. Checksums are not needed.
+    fn frame(proto: u8, src: ([u8; 4], u16), dst: ([u8; 4], u16), flags: u16) -> Vec<u8> {
+        let l4_len = if proto == 6 { 20 } else { 8 };
+        let mut frame = vec![0u8; 14 + 20 + l4_len];
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+
+        let ip = &mut frame[14..34];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&((20 + l4_len) as u16).to_be_bytes());
+        ip[8] = 64;
+        ip[9] = proto;
+        ip[12..16].copy_from_slice(&src.0);
+        ip[16..20].copy_from_slice(&dst.0);
+
+        let l4 = &mut frame[34..];
+        l4[0..2].copy_from_slice(&src.1.to_be_bytes());
+        l4[2..4].copy_from_slice(&dst.1.to_be_bytes());
+        if proto == 6 {
+            l4[12..14].copy_from_slice(&((5u16 << 12) | flags).to_be_bytes());
+        } else {
+            l4[4..6].copy_from_slice(&8u16.to_be_bytes());
+        }
+        frame
+    }
+
+    fn track(ct: &mut ConnTrack, mut frame: Vec<u8>, now: Instant) -> ConnState {
+        let mut action = Action::Drop;
+        let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        ct.update_at(&packet, now)
+    }
+
+    #[test]
+    fn test_tcp_handshake_establishes() {
+        let mut ct = ConnTrack::new(16);
+        let now = Instant::now();
+        let c = (CLIENT, 40000);
+        let s = (SERVER, 80);
+
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_SYN), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(6, s, c, TCP_SYN | TCP_ACK), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_ACK), now), ConnState::Established);
+        assert_eq!(track(&mut ct, frame(6, s, c, TCP_ACK), now), ConnState::Established);
+        assert_eq!(ct.len(), 1);
+
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_FIN | TCP_ACK), now), ConnState::Closing);
+    }
+
+    #[test]
+    fn test_tcp_handshake_needs_responder_synack() {
+        let mut ct = ConnTrack::new(16);
+        let now = Instant::now();
+        let c = (CLIENT, 40000);
+        let s = (SERVER, 80);
+
+        // The initiator cannot complete the handshake on its own.
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_SYN), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_ACK), now), ConnState::New);
+    }
+
+    #[test]
+    fn test_tcp_midstream_is_invalid() {
+        let mut ct = ConnTrack::new(16);
+        let state = track(&mut ct, frame(6, (CLIENT, 40000), (SERVER, 80), TCP_ACK), Instant::now());
+        assert_eq!(state, ConnState::Invalid);
+        assert!(ct.is_empty());
+    }
+
+    #[test]
+    fn test_udp_reply_establishes_and_times_out() {
+        let mut ct = ConnTrack::new(16).udp_timeout(Duration::from_secs(5));
+        let now = Instant::now();
+        let c = (CLIENT, 5353);
+        let s = (SERVER, 53);
+
+        assert_eq!(track(&mut ct, frame(17, c, s, 0), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(17, c, s, 0), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(17, s, c, 0), now), ConnState::Established);
+
+        assert_eq!(ct.evict(now + Duration::from_secs(4)), 0);
+        assert_eq!(ct.evict(now + Duration::from_secs(5)), 1);
+        assert!(ct.is_empty());
+    }
+
+    #[test]
+    fn test_expired_flow_starts_over_without_evict() {
+        let mut ct = ConnTrack::new(16).udp_timeout(Duration::from_secs(5)).tcp_timeout(Duration::from_secs(60));
+        let now = Instant::now();
+        let c = (CLIENT, 5353);
+        let s = (SERVER, 53);
+
+        assert_eq!(track(&mut ct, frame(17, c, s, 0), now), ConnState::New);
+        assert_eq!(track(&mut ct, frame(17, s, c, 0), now), ConnState::Established);
+        // Past the timeout the tuple is a new flow, started by the other side
+        let later = now + Duration::from_secs(5);
+        assert_eq!(track(&mut ct, frame(17, s, c, 0), later), ConnState::New);
+        assert_eq!(track(&mut ct, frame(17, c, s, 0), later), ConnState::Established);
+        assert_eq!(ct.len(), 1);
+
+        // An expired TCP flow does not carry on mid-stream
+        let (c, s) = ((CLIENT, 40000), (SERVER, 80));
+        track(&mut ct, frame(6, c, s, TCP_SYN), now);
+        track(&mut ct, frame(6, s, c, TCP_SYN | TCP_ACK), now);
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_ACK), now), ConnState::Established);
+        let later = now + Duration::from_secs(61);
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_ACK), later), ConnState::Invalid);
+        assert_eq!(track(&mut ct, frame(6, c, s, TCP_SYN), later), ConnState::New);
+    }
+
+    #[test]
+    fn test_lru_eviction_when_full() {
+        let mut ct = ConnTrack::new(2);
+        let now = Instant::now();
+        let s = (SERVER, 53);
+
+        track(&mut ct, frame(17, (CLIENT, 1), s, 0), now);
+        track(&mut ct, frame(17, (CLIENT, 2), s, 0), now);
+        // Refresh flow 1 so flow 2 becomes the least recently seen.
+        track(&mut ct, frame(17, s, (CLIENT, 1), 0), now);
+        track(&mut ct, frame(17, (CLIENT, 3), s, 0), now);
+        assert_eq!(ct.len(), 2);
+
+        assert_eq!(track(&mut ct, frame(17, s, (CLIENT, 1), 0), now), ConnState::Established);
+        // Flow 2 was evicted, so it starts over.
+        assert_eq!(track(&mut ct, frame(17, s, (CLIENT, 2), 0), now), ConnState::New);
+    }
+}
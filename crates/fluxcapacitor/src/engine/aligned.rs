@@ -0,0 +1,92 @@
+use std::alloc::{self, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
+
+/// Size of a CPU cache line on the architectures we target (x86_64, aarch64).
+pub const CACHE_LINE_SIZE: usize = 64;
+
+/// A fixed-length, heap-allocated buffer that starts on a cache-line boundary
+/// and is padded out to a whole number of cache lines.
+///
+/// The engine rewrites its descriptor and action scratch buffers on every batch.
+/// A plain `Vec` gives no alignment guarantee, so two buffers owned by engines on
+/// different cores could end up sharing a cache line (false sharing). Keeping each
+/// buffer on its own lines makes the hot loop safe to fan out across threads.
+pub struct AlignedBuf<T: Copy> {
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+// Safety: AlignedBuf uniquely owns its allocation, exactly like a Box<[T]>.
+unsafe impl<T: Copy + Send> Send for AlignedBuf<T> {}
+unsafe impl<T: Copy + Sync> Sync for AlignedBuf<T> {}
+
+impl<T: Copy> AlignedBuf<T> {
+    /// Allocate a buffer of `len` elements, each initialized to `value`.
+    pub fn new(len: usize, value: T) -> Self {
+        assert!(std::mem::size_of::<T>() > 0, "AlignedBuf does not support zero-sized types");
+
+        let layout = Self::layout(len);
+        let raw = unsafe { alloc::alloc(layout) } as *mut T;
+        let ptr = match NonNull::new(raw) {
+            Some(p) => p,
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        for i in 0..len {
+            unsafe { ptr.as_ptr().add(i).write(value) };
+        }
+
+        Self { ptr, len }
+    }
+
+    fn layout(len: usize) -> Layout {
+        // Always allocate at least one element so the pointer is real and aligned.
+        let size = std::mem::size_of::<T>() * len.max(1);
+        let align = CACHE_LINE_SIZE.max(std::mem::align_of::<T>());
+        Layout::from_size_align(size, align)
+            .expect("AlignedBuf layout overflow")
+            .pad_to_align()
+    }
+}
+
+impl<T: Copy> Deref for AlignedBuf<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for AlignedBuf<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> Drop for AlignedBuf<T> {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr.as_ptr() as *mut u8, Self::layout(self.len)) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Action;
+    use fluxcapacitor_core::ring::XDPDesc;
+
+    #[test]
+    fn test_buffers_are_cache_line_aligned() {
+        for len in [1, 3, 64, 100] {
+            let descs = AlignedBuf::new(len, XDPDesc::default());
+            let actions = AlignedBuf::new(len, Action::Drop);
+
+            assert_eq!(descs.as_ptr() as usize % CACHE_LINE_SIZE, 0);
+            assert_eq!(actions.as_ptr() as usize % CACHE_LINE_SIZE, 0);
+            assert_eq!(descs.len(), len);
+            assert!(actions.iter().all(|a| *a == Action::Drop));
+        }
+    }
+}
@@ -1,22 +1,32 @@
+use crate::filter::FlowMatcher;
 use crate::packet::{PacketRef, Action};
+use crate::packet::meta::XdpRxMeta;
 use fluxcapacitor_core::ring::XDPDesc;
 use fluxcapacitor_core::umem::mmap::UmemRegion;
+use fluxcapacitor_proto::UdpHeader;
+use std::ops::Range;
 
 pub struct PacketBatch<'a> {
     descriptors: &'a mut [XDPDesc],
     umem: &'a mut UmemRegion,
     actions: &'a mut [Action],
+    zerocopy: bool,
+    rx_meta: bool,
 }
 
 impl<'a> PacketBatch<'a> {
-    pub(crate) fn new(descriptors: &'a mut [XDPDesc], umem: &'a mut UmemRegion, actions: &'a mut [Action]) -> Self {
+    pub(crate) fn new(descriptors: &'a mut [XDPDesc], umem: &'a mut UmemRegion, actions: &'a mut [Action], zerocopy: bool, rx_meta: bool) -> Self {
         // Initialize all actions to Drop by default (safe default)
         actions.fill(Action::Drop);
+        #[cfg(debug_assertions)]
+        check_disjoint(descriptors, umem.layout().chunk_size);
         
         Self {
             descriptors,
             umem,
             actions,
+            zerocopy,
+            rx_meta,
         }
     }
     
@@ -25,10 +35,128 @@ impl<'a> PacketBatch<'a> {
             descriptors: self.descriptors,
             umem: self.umem,
             actions: self.actions,
+            zerocopy: self.zerocopy,
+            rx_meta: self.rx_meta,
             idx: 0,
         }
     }
 
+    /// Split the batch into at most `n` iterators over disjoint, contiguous runs of
+    /// packets, e.g. to process them on several threads.
+    ///
+    /// The iterators are `Send`. Every descriptor names its own UMEM frame and each
+    /// action slot belongs to exactly one iterator, so they never touch the same bytes.
+    pub fn split_mut(&mut self, n: usize) -> Vec<BatchIterator<'_>> {
+        if self.descriptors.is_empty() {
+            return Vec::new();
+        }
+        let chunk = self.descriptors.len().div_ceil(n.max(1));
+        let umem: &UmemRegion = self.umem;
+        self.descriptors.chunks(chunk)
+            .zip(self.actions.chunks_mut(chunk))
+            .map(|(descriptors, actions)| BatchIterator {
+                descriptors,
+                umem,
+                actions,
+                zerocopy: self.zerocopy,
+                rx_meta: self.rx_meta,
+                idx: 0,
+            })
+            .collect()
+    }
+
+    /// Iterate over the UDP packets of the batch, yielding the header, the mutable
+    /// payload and the packet's verdict.
+    ///
+    /// Eth + IPv4 + UDP is parsed once per packet; anything else (and non-first
+    /// fragments) is skipped and keeps its current action. The payload is trimmed to
+    /// the UDP length field, so Ethernet padding is not included.
+    pub fn udp_payloads(&mut self) -> UdpPayloads<'_> {
+        UdpPayloads {
+            descriptors: self.descriptors,
+            umem: self.umem,
+            actions: self.actions,
+            idx: 0,
+        }
+    }
+
+    /// Iterate over the packets `matcher` accepts. The others are skipped and keep
+    /// their current action.
+    pub fn iter_matching<'m>(&'m mut self, matcher: &'m FlowMatcher) -> impl Iterator<Item = PacketRef<'m>> + 'm {
+        self.iter_mut().filter(move |packet| matcher.matches(packet))
+    }
+
+    /// Whole-batch access for vectorized or multi-pass processing.
+    ///
+    /// `descriptors[i]` and `actions[i]` describe the same packet; frame bytes live at
+    /// `umem.as_ptr() + descriptors[i].addr` for `descriptors[i].len` bytes.
+    /// Descriptors are committed as they are when the callback returns: editing
+    /// `addr`/`len` changes what is transmitted for `Action::Tx` (and which frame
+    /// is recycled otherwise), so keep `addr` inside the frame it came from.
+    pub fn as_slices_mut(&mut self) -> (&mut [XDPDesc], &mut [Action], &UmemRegion) {
+        (self.descriptors, self.actions, self.umem)
+    }
+
+    /// Copy every packet out of UMEM into an `OwnedBatch` that can be moved to
+    /// another thread, e.g. to hand work to an async runtime or a work-stealing pool.
+    ///
+    /// The engine commits the batch when the callback returns, so the owned copy
+    /// must come back through `apply_owned` before then for its verdicts (and any
+    /// edits to the packet bytes) to take effect.
+    pub fn into_owned(&mut self) -> OwnedBatch {
+        let packets = self.descriptors.iter().zip(self.actions.iter())
+            .map(|(desc, action)| {
+                let frame = unsafe {
+                    std::slice::from_raw_parts(self.umem.as_ptr().add(desc.addr as usize), desc.len as usize)
+                };
+                OwnedPacket { data: frame.to_vec().into_boxed_slice(), action: *action }
+            })
+            .collect();
+        OwnedBatch { packets }
+    }
+
+    /// Write the packet bytes and actions of `owned` (from `into_owned` on this
+    /// batch) back into UMEM and this batch.
+    ///
+    /// Panics if `owned` does not have one packet per packet of this batch.
+    pub fn apply_owned(&mut self, owned: OwnedBatch) {
+        assert_eq!(owned.packets.len(), self.descriptors.len(), "owned batch does not match this batch");
+        for ((desc, action), packet) in self.descriptors.iter().zip(self.actions.iter_mut()).zip(owned.packets) {
+            // Lengths are fixed by `OwnedPacket`, so this stays inside the frame.
+            let frame = unsafe {
+                std::slice::from_raw_parts_mut(self.umem.as_ptr().add(desc.addr as usize), desc.len as usize)
+            };
+            frame.copy_from_slice(&packet.data);
+            *action = packet.action;
+        }
+    }
+
+    /// Append the bytes of every packet, minus its first `header_skip` bytes, to
+    /// `out` back to back, e.g. for a capture-to-disk sink.
+    ///
+    /// Returns where each packet landed in `out`, in batch order; a packet no
+    /// longer than `header_skip` gets an empty range. `out` grows once for the
+    /// whole batch rather than once per packet.
+    pub fn gather_payloads(&self, out: &mut Vec<u8>, header_skip: usize) -> Vec<Range<usize>> {
+        let total: usize = self.descriptors.iter()
+            .map(|desc| (desc.len as usize).saturating_sub(header_skip))
+            .sum();
+        out.reserve(total);
+        self.descriptors.iter()
+            .map(|desc| {
+                let start = out.len();
+                let len = desc.len as usize;
+                if len > header_skip {
+                    let frame = unsafe {
+                        std::slice::from_raw_parts(self.umem.as_ptr().add(desc.addr as usize), len)
+                    };
+                    out.extend_from_slice(&frame[header_skip..]);
+                }
+                start..out.len()
+            })
+            .collect()
+    }
+
     pub fn len(&self) -> usize {
         self.descriptors.len()
     }
@@ -38,10 +166,107 @@ impl<'a> PacketBatch<'a> {
     }
 }
 
+/// Heap copy of a `PacketBatch`, detached from UMEM and `Send`.
+/// See `PacketBatch::into_owned`.
+#[derive(Debug, Clone)]
+pub struct OwnedBatch {
+    packets: Vec<OwnedPacket>,
+}
+
+impl OwnedBatch {
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, OwnedPacket> {
+        self.packets.iter_mut()
+    }
+
+    pub fn packets(&self) -> &[OwnedPacket] {
+        &self.packets
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+}
+
+/// One packet of an `OwnedBatch`: a copy of the frame bytes and its verdict.
+///
+/// The length is fixed, since the bytes are copied back into the original frame.
+#[derive(Debug, Clone)]
+pub struct OwnedPacket {
+    data: Box<[u8]>,
+    action: Action,
+}
+
+impl OwnedPacket {
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    pub fn send(&mut self) {
+        self.action = Action::Tx;
+    }
+
+    // Same verbs as `PacketRef`, so handlers move between the two unchanged.
+    #[allow(clippy::should_implement_trait)]
+    pub fn drop(&mut self) {
+        self.action = Action::Drop;
+    }
+
+    pub fn pass(&mut self) {
+        self.action = Action::Pass;
+    }
+}
+
+// Every `PacketRef` of a batch hands out `&mut` access to its frame through the
+// shared `UmemRegion`, which is only sound if no two descriptors touch the same
+// chunk. A buggy producer (or a hand-built descriptor list) could break that, so
+// debug builds check it up front.
+#[cfg(debug_assertions)]
+fn check_disjoint(descriptors: &[XDPDesc], chunk_size: u32) {
+    let chunk_size = chunk_size as u64;
+    let mut chunks: Vec<(u64, usize)> = descriptors.iter().enumerate().map(|(i, desc)| {
+        let offset = desc.addr % chunk_size;
+        assert!(
+            offset + desc.len as u64 <= chunk_size,
+            "descriptor {} ({} bytes at {:#x}) crosses a UMEM chunk boundary", i, desc.len, desc.addr
+        );
+        (desc.addr / chunk_size, i)
+    }).collect();
+
+    chunks.sort_unstable();
+    for pair in chunks.windows(2) {
+        assert!(
+            pair[0].0 != pair[1].0,
+            "descriptors {} and {} share UMEM chunk {:#x}", pair[0].1, pair[1].1, pair[0].0 * chunk_size
+        );
+    }
+}
+
 pub struct BatchIterator<'a> {
     descriptors: &'a [XDPDesc],
     umem: &'a UmemRegion, // Umem is thread-safe/shared usually, or at least we only need read access for ptr
     actions: &'a mut [Action],
+    zerocopy: bool,
+    rx_meta: bool,
     idx: usize,
 }
 
@@ -66,18 +291,81 @@ impl<'a> Iterator for BatchIterator<'a> {
             &mut *action_ptr
         };
         
+        // Metadata sits in the frame's headroom, never before the frame itself.
+        let layout = self.umem.layout();
+        let headroom = desc.addr % layout.chunk_size as u64;
+        let has_meta = self.rx_meta && headroom >= XdpRxMeta::LEN as u64;
+        let room = ((layout.headroom + layout.frame_size) as u64).saturating_sub(headroom) as usize;
+        // The configured UMEM headroom opens the chunk, ahead of any kernel headroom.
+        let scratch = unsafe { ptr.sub(headroom as usize) };
+        let scratch_len = (layout.headroom as u64).min(headroom) as usize;
+        
         let packet = unsafe {
              PacketRef::new(ptr, desc.len as usize, desc.addr, action_ref)
-        };
+                 .with_rx_meta(has_meta)
+                 .with_room(room)
+                 .with_scratch(scratch, scratch_len)
+        }.with_zerocopy(self.zerocopy);
         
         self.idx += 1;
         Some(packet)
     }
 }
 
+pub struct UdpPayloads<'a> {
+    descriptors: &'a [XDPDesc],
+    umem: &'a UmemRegion,
+    actions: &'a mut [Action],
+    idx: usize,
+}
+
+impl<'a> Iterator for UdpPayloads<'a> {
+    type Item = (&'a UdpHeader, &'a mut [u8], &'a mut Action);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.idx < self.descriptors.len() {
+            let idx = self.idx;
+            self.idx += 1;
+
+            let desc = self.descriptors[idx];
+            let frame = unsafe {
+                std::slice::from_raw_parts_mut(self.umem.as_ptr().add(desc.addr as usize), desc.len as usize)
+            };
+            let Some((payload_off, payload_len)) = udp_payload_range(frame) else {
+                continue;
+            };
+
+            // Header and payload are disjoint, and each index is yielded once.
+            let (head, rest) = frame.split_at_mut(payload_off);
+            let header = unsafe { &*(head.as_ptr().add(payload_off - 8) as *const UdpHeader) };
+            let action = unsafe { &mut *(&mut self.actions[idx] as *mut Action) };
+            return Some((header, &mut rest[..payload_len], action));
+        }
+        None
+    }
+}
+
+/// Offset and length of the UDP payload in an Eth + IPv4 + UDP frame.
+fn udp_payload_range(frame: &[u8]) -> Option<(usize, usize)> {
+    let (_, ip_payload) = fluxcapacitor_proto::parse_eth(frame)?;
+    let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
+    // Only the first fragment carries the UDP header.
+    if ip_header.proto != 17 || u16::from_be(ip_header.frag_off) & 0x1FFF != 0 { // UDP
+        return None;
+    }
+    let (udp_header, payload) = fluxcapacitor_proto::parse_udp(l4_payload)?;
+
+    let payload_len = (udp_header.length() as usize).checked_sub(8)?;
+    if payload_len > payload.len() {
+        return None;
+    }
+    Some((frame.len() - payload.len(), payload_len))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::udp_frame;
     use fluxcapacitor_core::umem::layout::UmemLayout;
     use fluxcapacitor_core::umem::mmap::UmemRegion;
 
@@ -99,7 +387,7 @@ mod tests {
         let mut actions = vec![Action::Drop; 3];
 
         // 4. Create Batch
-        let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions);
+        let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
 
         // 5. Verify Iteration
         let mut count = 0;
@@ -129,8 +417,189 @@ mod tests {
         let mut descriptors = vec![];
         let mut actions = vec![];
 
-        let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions);
+        let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
         assert_eq!(batch.iter_mut().count(), 0);
     }
-}
 
+    #[test]
+    fn test_as_slices_mut() {
+        let layout = UmemLayout::new(2048, 16);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        unsafe { *umem.as_ptr().add(2048) = 0xAB };
+
+        let mut descriptors = vec![
+            XDPDesc { addr: 0, len: 64, options: 0 },
+            XDPDesc { addr: 2048, len: 64, options: 0 },
+        ];
+        let mut actions = vec![Action::Drop; 2];
+
+        {
+            let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+            let (descs, actions, umem) = batch.as_slices_mut();
+
+            // Pass 1: classify, pass 2: act
+            let marked: Vec<bool> = descs.iter()
+                .map(|d| unsafe { *umem.as_ptr().add(d.addr as usize) } == 0xAB)
+                .collect();
+            for (i, hit) in marked.into_iter().enumerate() {
+                if hit {
+                    actions[i] = Action::Tx;
+                    descs[i].len = 32;
+                }
+            }
+        }
+
+        assert_eq!(actions, [Action::Drop, Action::Tx]);
+        assert_eq!(descriptors[1].len, 32);
+    }
+
+    #[test]
+    fn test_udp_payloads() {
+        let layout = UmemLayout::new(2048, 16);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+
+        // Frame 0: UDP with 4 payload bytes and 2 bytes of padding. Frame 1: ARP.
+        let udp = udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], 53), b"ping");
+        umem.write_frame(0, &udp).expect("Failed to write frame");
+        let arp = unsafe { std::slice::from_raw_parts_mut(umem.as_ptr().add(2048), 42) };
+        arp[12..14].copy_from_slice(&[0x08, 0x06]);
+
+        let mut descriptors = vec![
+            XDPDesc { addr: 0, len: 48, options: 0 },
+            XDPDesc { addr: 2048, len: 42, options: 0 },
+        ];
+        let mut actions = vec![Action::Drop; 2];
+
+        {
+            let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+            let mut count = 0;
+            for (header, payload, action) in batch.udp_payloads() {
+                assert_eq!(header.dst_port(), 53);
+                assert_eq!(payload, b"ping");
+                payload.copy_from_slice(b"pong");
+                *action = Action::Tx;
+                count += 1;
+            }
+            assert_eq!(count, 1);
+        }
+
+        assert_eq!(actions, [Action::Tx, Action::Drop]);
+        assert_eq!(unsafe { std::slice::from_raw_parts(umem.as_ptr().add(42), 4) }, b"pong");
+    }
+
+    #[test]
+    fn test_iter_matching() {
+        let layout = UmemLayout::new(2048, 16);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+
+        // UDP (source, destination port): only the first is a DNS query from 10.0.0.0/24
+        let flows = [([10, 0, 0, 1], 53u16), ([10, 0, 0, 1], 8080), ([172, 16, 0, 1], 53)];
+        for (i, &(src, port)) in flows.iter().enumerate() {
+            let frame = udp_frame((src, 1000), ([10, 0, 0, 2], port), &[]);
+            umem.write_frame(i as u64 * 2048, &frame).expect("Failed to write frame");
+        }
+
+        let mut descriptors: Vec<_> = (0..3).map(|i| XDPDesc { addr: i * 2048, len: 42, options: 0 }).collect();
+        let mut actions = vec![Action::Drop; 3];
+        {
+            let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+            let dns = FlowMatcher::new().src_net("10.0.0.0".parse().unwrap(), 24).dst_port(1..=1023);
+            for mut packet in batch.iter_matching(&dns) {
+                packet.pass();
+            }
+        }
+        assert_eq!(actions, [Action::Pass, Action::Drop, Action::Drop]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "share UMEM chunk")]
+    fn test_duplicate_descriptors_rejected() {
+        let layout = UmemLayout::new(2048, 4);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        // Same frame twice, the second at a different offset into it
+        let mut descriptors = vec![
+            XDPDesc { addr: 2048, len: 60, options: 0 },
+            XDPDesc { addr: 0, len: 60, options: 0 },
+            XDPDesc { addr: 2048 + 256, len: 60, options: 0 },
+        ];
+        let mut actions = vec![Action::Drop; 3];
+        let _ = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "crosses a UMEM chunk boundary")]
+    fn test_descriptor_past_chunk_rejected() {
+        let layout = UmemLayout::new(2048, 4);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        let mut descriptors = vec![XDPDesc { addr: 2000, len: 60, options: 0 }];
+        let mut actions = vec![Action::Drop; 1];
+        let _ = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+    }
+
+    #[test]
+    fn test_gather_payloads() {
+        let layout = UmemLayout::new(2048, 4);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        umem.write_frame(0, b"HDRalpha").unwrap();
+        umem.write_frame(2048, b"HDRbe").unwrap();
+        umem.write_frame(4096, b"HDRgamma!").unwrap();
+        let mut descriptors = vec![
+            XDPDesc { addr: 0, len: 8, options: 0 },
+            XDPDesc { addr: 2048, len: 5, options: 0 },
+            XDPDesc { addr: 4096, len: 9, options: 0 },
+        ];
+        let mut actions = vec![Action::Drop; 3];
+        let batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+
+        // Appends after what is already there
+        let mut out = b"log:".to_vec();
+        let ranges = batch.gather_payloads(&mut out, 3);
+        assert_eq!(out, b"log:alphabegamma!");
+        assert_eq!(ranges, [4..9, 9..11, 11..17]);
+        assert_eq!(&out[ranges[2].clone()], b"gamma!");
+
+        // Packets shorter than the skip contribute nothing
+        let mut out = Vec::new();
+        assert_eq!(batch.gather_payloads(&mut out, 8), [0..0, 0..0, 0..1]);
+        assert_eq!(out, b"!");
+    }
+
+    #[test]
+    fn test_owned_batch_round_trip() {
+        let layout = UmemLayout::new(2048, 4);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        umem.write_frame(0, &[1; 64]).unwrap();
+        umem.write_frame(2048, &[2; 64]).unwrap();
+        let mut descriptors = vec![
+            XDPDesc { addr: 0, len: 64, options: 0 },
+            XDPDesc { addr: 2048, len: 64, options: 0 },
+        ];
+        let mut actions = vec![Action::Drop; 2];
+
+        {
+            let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+            let owned = batch.into_owned();
+
+            // Echo packets starting with 2, rewriting their first byte
+            let owned = std::thread::spawn(move || {
+                let mut owned = owned;
+                for packet in owned.iter_mut() {
+                    if packet.data()[0] == 2 {
+                        packet.data_mut()[0] = 0xEE;
+                        packet.send();
+                    }
+                }
+                owned
+            }).join().expect("worker panicked");
+
+            batch.apply_owned(owned);
+        }
+
+        assert_eq!(actions, [Action::Drop, Action::Tx]);
+        let frames = unsafe { std::slice::from_raw_parts(umem.as_ptr(), 4096) };
+        assert_eq!(frames[0], 1);
+        assert_eq!(&frames[2048..2050], &[0xEE, 2]);
+    }
+}
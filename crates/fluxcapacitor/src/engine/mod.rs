@@ -1,4 +1,11 @@
+pub mod aligned;
 pub mod batch;
 pub mod runner;
+pub mod multi;
+pub mod parallel;
+pub mod stats;
 
 pub use runner::FluxEngine;
+pub use multi::MultiEngine;
+pub use stats::{EngineStats, EngineStatsSnapshot, StatsPoller, StatsSample};
+pub use batch::{OwnedBatch, OwnedPacket};
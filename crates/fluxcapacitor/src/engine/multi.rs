@@ -0,0 +1,66 @@
+use crate::engine::batch::PacketBatch;
+use crate::engine::runner::FluxEngine;
+use crate::raw::FluxRaw;
+use fluxcapacitor_core::sys::socket::RxPollSet;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Services several sockets (e.g. WAN and LAN queues) from one thread.
+///
+/// Each socket keeps its own `FluxEngine`. `process_batch` takes one batch from
+/// every socket in turn and tags it with the socket's index in the original `Vec`.
+/// When all of them are idle, `run` sleeps in a single `poll()` over every fd
+/// instead of one thread per interface.
+pub struct MultiEngine {
+    engines: Vec<FluxEngine>,
+    // Every socket's fd, polled together when all are idle.
+    poll: RxPollSet,
+}
+
+impl FluxEngine {
+    /// Build a `MultiEngine` over `sockets`, each processed in batches of `batch_size`.
+    pub fn multi(sockets: Vec<FluxRaw>, batch_size: usize) -> MultiEngine {
+        let fds: Vec<_> = sockets.iter().map(|s| s.fd()).collect();
+        let engines = sockets.into_iter().map(|s| FluxEngine::new(s, batch_size)).collect();
+        MultiEngine { engines, poll: RxPollSet::new(&fds) }
+    }
+}
+
+impl MultiEngine {
+    /// Per-socket engines, in socket index order, e.g. to set observers.
+    pub fn engines_mut(&mut self) -> &mut [FluxEngine] {
+        &mut self.engines
+    }
+
+    /// Run until `stop` is set, calling `callback(socket_index, batch)` per batch.
+    pub fn run<F>(&mut self, stop: &AtomicBool, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(usize, &mut PacketBatch),
+    {
+        while !stop.load(Ordering::Relaxed) {
+            if self.process_batch(&mut callback)? == 0 {
+                self.wait(Duration::from_millis(1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Process one batch from every socket. Returns the total number of packets.
+    pub fn process_batch<F>(&mut self, callback: &mut F) -> io::Result<usize>
+    where
+        F: FnMut(usize, &mut PacketBatch),
+    {
+        let mut total = 0;
+        for (idx, engine) in self.engines.iter_mut().enumerate() {
+            total += engine.process_batch(&mut |batch: &mut PacketBatch| callback(idx, batch))?;
+        }
+        Ok(total)
+    }
+
+    // Block until any socket has RX data, or `timeout` elapses.
+    fn wait(&mut self, timeout: Duration) -> io::Result<()> {
+        self.poll.wait(timeout.as_millis().min(i32::MAX as u128) as i32)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Fan a batch out to a fixed pool of worker threads.
+//!
+//! For CPU-heavy per-packet work (decryption, deep inspection) one thread becomes the
+//! bottleneck long before the rings do. `FluxEngine::run_parallel` keeps ring handling
+//! on the calling thread and hands each worker a disjoint slice of every batch:
+//! frames stay in UMEM, workers get `PacketRef`s into them, and TX/Fill are committed
+//! once on the calling thread after all workers have finished.
+//!
+//! # Safety argument
+//!
+//! `PacketRef` holds raw pointers into UMEM and is neither `Send` nor `Sync`, so a
+//! batch cannot be shared with threads directly. The pool relies on three facts:
+//!
+//! 1. Each descriptor in a batch names a distinct UMEM frame (the kernel hands a
+//!    frame out once until it is recycled), so workers given disjoint descriptor
+//!    ranges write disjoint bytes of UMEM.
+//! 2. Each action slot is handed to exactly one worker (`PacketBatch::split_mut`).
+//! 3. `dispatch` does not return until every worker has acknowledged its slice, so
+//!    no worker can still hold a `PacketRef` when the batch is committed and its
+//!    frames go back to the kernel. The lifetime erasure in `dispatch` is sound only
+//!    because of this wait, which also holds if a worker panics: its channel closes,
+//!    the remaining acknowledgements are still collected, and only then does the
+//!    calling thread panic.
+
+use crate::engine::batch::{BatchIterator, PacketBatch};
+use crate::engine::runner::FluxEngine;
+use crate::packet::PacketRef;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::Scope;
+
+// A worker's share of one batch, with its borrow erased (see the module docs).
+struct Job(BatchIterator<'static>);
+
+struct Worker {
+    jobs: Sender<Job>,
+    done: Receiver<()>,
+}
+
+/// Fixed set of worker threads living in a `std::thread::scope`.
+pub(crate) struct WorkerPool {
+    workers: Vec<Worker>,
+}
+
+impl WorkerPool {
+    /// Start `count` workers (at least 1) that run `f` on every packet they are given.
+    pub(crate) fn spawn<'scope, 'env, F>(scope: &'scope Scope<'scope, 'env>, count: usize, f: &'env F) -> Self
+    where
+        F: Fn(&mut PacketRef) + Sync,
+    {
+        let workers = (0..count.max(1))
+            .map(|_| {
+                let (jobs, job_rx) = mpsc::channel::<Job>();
+                let (done_tx, done) = mpsc::channel();
+                scope.spawn(move || {
+                    for Job(packets) in job_rx {
+                        for mut packet in packets {
+                            f(&mut packet);
+                        }
+                        if done_tx.send(()).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Worker { jobs, done }
+            })
+            .collect();
+        Self { workers }
+    }
+
+    /// Process `batch` on the workers and wait for all of them to finish.
+    pub(crate) fn dispatch(&self, batch: &mut PacketBatch) {
+        let mut sent = Vec::with_capacity(self.workers.len());
+        for (worker, part) in self.workers.iter().zip(batch.split_mut(self.workers.len())) {
+            // Safety: we wait for the worker's acknowledgement below, before `batch`
+            // (and the buffers it borrows) can be used again.
+            let part = unsafe { std::mem::transmute::<BatchIterator<'_>, BatchIterator<'static>>(part) };
+            // A dead worker hands the job back, which drops it unprocessed.
+            sent.push(worker.jobs.send(Job(part)).is_ok());
+        }
+
+        let mut healthy = true;
+        for (worker, was_sent) in self.workers.iter().zip(sent) {
+            healthy &= was_sent && worker.done.recv().is_ok();
+        }
+        assert!(healthy, "fluxcapacitor: worker thread panicked");
+    }
+}
+
+impl FluxEngine {
+    /// Like `run`, but runs `f` on every packet across `workers` threads.
+    ///
+    /// Each batch is split into `workers` contiguous slices; the workers set each
+    /// packet's action (`send`, `drop`, ...) and the engine commits the batch once
+    /// they are all done. Packet order within a batch is preserved on TX. Worth it
+    /// only when per-packet work dwarfs the cost of waking the workers (a few
+    /// microseconds per batch); use a large `batch_size`.
+    pub fn run_parallel<F>(&mut self, stop: &AtomicBool, workers: usize, f: F) -> io::Result<()>
+    where
+        F: Fn(&mut PacketRef) + Sync,
+    {
+        std::thread::scope(|scope| {
+            let pool = WorkerPool::spawn(scope, workers, &f);
+            self.run(stop, |batch| pool.dispatch(batch))
+            // Dropping the pool closes the job channels; the scope joins the workers.
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::Action;
+    use fluxcapacitor_core::ring::XDPDesc;
+    use fluxcapacitor_core::umem::layout::UmemLayout;
+    use fluxcapacitor_core::umem::mmap::UmemRegion;
+
+    #[test]
+    fn test_parallel_checksum_verification() {
+        let layout = UmemLayout::new(2048, 64);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+
+        // 64 frames of 64 bytes ending in their own checksum; every third one corrupted
+        let mut descriptors = Vec::new();
+        for i in 0..64u64 {
+            let frame = unsafe { std::slice::from_raw_parts_mut(umem.as_ptr().add(i as usize * 2048), 64) };
+            frame[..62].fill(i as u8);
+            let csum = fluxcapacitor_proto::checksum(&frame[..62]);
+            frame[62..].copy_from_slice(&csum.to_be_bytes());
+            if i % 3 == 0 {
+                frame[0] ^= 0xFF;
+            }
+            descriptors.push(XDPDesc { addr: i * 2048, len: 64, options: 0 });
+        }
+        let mut actions = [Action::Drop; 64];
+
+        let threads = std::sync::Mutex::new(std::collections::HashSet::new());
+        let verify = |packet: &mut PacketRef| {
+            if fluxcapacitor_proto::checksum(packet.data()) == 0 {
+                packet.send();
+            }
+            threads.lock().unwrap().insert(std::thread::current().id());
+        };
+        std::thread::scope(|scope| {
+            let pool = WorkerPool::spawn(scope, 4, &verify);
+            // Two batches through the same workers
+            for range in [0..40, 40..64] {
+                let mut batch = PacketBatch::new(&mut descriptors[range.clone()], &mut umem, &mut actions[range], false, false);
+                pool.dispatch(&mut batch);
+            }
+        });
+
+        // Every worker took a share, none of it on the calling thread
+        let threads = threads.into_inner().unwrap();
+        assert_eq!(threads.len(), 4);
+        assert!(!threads.contains(&std::thread::current().id()));
+
+        for (i, action) in actions.iter().enumerate() {
+            let expected = if i % 3 == 0 { Action::Drop } else { Action::Tx };
+            assert_eq!(*action, expected, "packet {}", i);
+        }
+    }
+
+    #[test]
+    fn test_split_mut_covers_batch() {
+        let layout = UmemLayout::new(2048, 16);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        let mut descriptors: Vec<_> = (0..10).map(|i| XDPDesc { addr: i * 2048, len: 60, options: 0 }).collect();
+        let mut actions = vec![Action::Drop; 10];
+
+        let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+        let sizes: Vec<usize> = batch.split_mut(4).into_iter().map(|it| it.count()).collect();
+        assert_eq!(sizes, [3, 3, 3, 1]);
+        assert_eq!(batch.split_mut(64).len(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "worker thread panicked")]
+    fn test_worker_panic_propagates() {
+        let layout = UmemLayout::new(2048, 4);
+        let mut umem = UmemRegion::new(layout).expect("Failed to create umem");
+        let mut descriptors: Vec<_> = (0..4).map(|i| XDPDesc { addr: i * 2048, len: 60, options: 0 }).collect();
+        let mut actions = vec![Action::Drop; 4];
+
+        let boom = |packet: &mut PacketRef| {
+            if packet.len() == 60 {
+                panic!("boom");
+            }
+        };
+        std::thread::scope(|scope| {
+            let pool = WorkerPool::spawn(scope, 2, &boom);
+            let mut batch = PacketBatch::new(&mut descriptors, &mut umem, &mut actions, false, false);
+            pool.dispatch(&mut batch);
+        });
+    }
+}
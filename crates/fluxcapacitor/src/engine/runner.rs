@@ -1,18 +1,44 @@
 use crate::raw::FluxRaw;
+use crate::engine::aligned::AlignedBuf;
 use crate::engine::batch::PacketBatch;
-use crate::packet::Action;
-use crate::config::Poller;
+use crate::engine::stats::EngineStats;
+use crate::packet::{Action, PacketRef};
+use crate::config::{AdaptiveIdle, Clock, CommitOrder, Poller, PollerControl, SystemClock, DEFAULT_POLL_TIMEOUT};
+use crate::observer::FluxObserver;
 use fluxcapacitor_core::ring::XDPDesc;
+use fluxcapacitor_core::sys::socket::PollStatus;
+use fluxcapacitor_core::umem::layout::UmemLayout;
 use std::io;
-use std::time::{Instant, Duration};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Completion Ring entries recycled per reclaim unless `set_comp_batch` says otherwise.
+const DEFAULT_COMP_BATCH: u32 = 32;
 
 pub struct FluxEngine {
     pub socket: FluxRaw,
     batch_size: usize,
-    poller: Poller,
-    // Reuse buffers to avoid per-batch allocations
-    descs_buf: Vec<XDPDesc>,
-    actions_buf: Vec<Action>,
+    poller: Arc<PollerControl>,
+    poll_timeout: Duration,
+    // Time source for `Poller::Adaptive`.
+    clock: Arc<dyn Clock>,
+    drain_rx: bool,
+    commit_order: CommitOrder,
+    // Most Completion Ring entries recycled per reclaim.
+    comp_batch: u32,
+    observer: Option<Box<dyn FluxObserver>>,
+    stats: Arc<EngineStats>,
+    // Reuse buffers to avoid per-batch allocations.
+    // Cache-line aligned so engines on different cores never share a line.
+    descs_buf: AlignedBuf<XDPDesc>,
+    actions_buf: AlignedBuf<Action>,
+    // Frames waiting for room in the Fill Ring: startup leftovers and released holds.
+    spare_frames: Vec<u64>,
+    // Frames kept out of the rings by `Action::Hold`, at most `max_held` of them.
+    held: Vec<XDPDesc>,
+    max_held: usize,
+    // RX descriptors seen by `peek_batch` and not yet released by `commit`.
+    peeked: usize,
 }
 
 impl FluxEngine {
@@ -24,97 +50,361 @@ impl FluxEngine {
         let mut engine = Self {
             socket,
             batch_size: batch_size.max(1),
-            poller,
-            descs_buf: vec![XDPDesc::default(); batch_size.max(1)],
-            actions_buf: vec![Action::Drop; batch_size.max(1)],
+            poller: Arc::new(PollerControl::new(poller)),
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            clock: Arc::new(SystemClock),
+            drain_rx: false,
+            commit_order: CommitOrder::default(),
+            comp_batch: DEFAULT_COMP_BATCH,
+            observer: None,
+            stats: Arc::new(EngineStats::default()),
+            descs_buf: AlignedBuf::new(batch_size.max(1), XDPDesc::default()),
+            actions_buf: AlignedBuf::new(batch_size.max(1), Action::Drop),
+            spare_frames: Vec::new(),
+            held: Vec::new(),
+            max_held: 0,
+            peeked: 0,
         };
         
-        // Initialize Fill Ring with all available UMEM frames
+        // Initialize Fill Ring with all available UMEM frames, unless the user manages them
         let frame_count = engine.socket.umem.layout().frame_count;
-        let frame_size = engine.socket.umem.layout().frame_size;
+        let chunk_size = engine.socket.umem.layout().chunk_size as u64;
+        // Holding more than a quarter of UMEM would starve RX.
+        engine.max_held = frame_count as usize / 4;
+        
+        if !engine.socket.auto_fill {
+            return engine;
+        }
         
-        let to_fill = frame_count; 
+        // The ring may be smaller than the UMEM; keep the rest for later.
+        let to_fill = frame_count.min(engine.socket.fill.available());
         
         if let Some(mut prod) = engine.socket.fill.reserve(to_fill) {
              for i in 0..to_fill {
-                 let addr = (i * frame_size) as u64;
+                 let addr = i as u64 * chunk_size;
                  unsafe { engine.socket.fill.write_at(prod, addr) };
                  prod += 1;
              }
              engine.socket.fill.submit(prod);
         }
+        engine.spare_frames = (to_fill..frame_count).map(|i| i as u64 * chunk_size).collect();
         
         engine
     }
 
-    pub fn run<F>(&mut self, stop: &std::sync::atomic::AtomicBool, mut callback: F) -> io::Result<()>
+    pub fn run<F>(&mut self, stop: &std::sync::atomic::AtomicBool, callback: F) -> io::Result<()>
     where
         F: FnMut(&mut PacketBatch),
     {
-        match self.poller {
-            Poller::Busy => loop {
-                if stop.load(std::sync::atomic::Ordering::Relaxed) { break Ok(()); }
-                self.process_batch(&mut callback)?;
-            },
-            Poller::Wait => loop {
-                if stop.load(std::sync::atomic::Ordering::Relaxed) { break Ok(()); }
-                let count = self.process_batch(&mut callback)?;
-                if count == 0 {
-                    // Block until next packet.
-                    // For now, we use a short sleep to simulate waiting.
-                    std::thread::sleep(Duration::from_millis(1));
-                }
-            },
-            Poller::Adaptive => {
-                let mut last_packet_time = Instant::now();
-                let spin_duration = Duration::from_micros(50);
-                
-                loop {
-                    if stop.load(std::sync::atomic::Ordering::Relaxed) { break Ok(()); }
-                    let count = self.process_batch(&mut callback)?;
-                    if count > 0 {
-                        last_packet_time = Instant::now();
-                    } else if last_packet_time.elapsed() > spin_duration {
-                        std::thread::sleep(Duration::from_millis(1));
-                    } else {
-                        std::thread::yield_now();
+        self.run_until(stop, None, callback).map(|_| ())
+    }
+
+    /// Like `run`, but also returns once `max_packets` packets have been processed.
+    ///
+    /// Batches are trimmed so that exactly `max_packets` are handed to the callback;
+    /// anything beyond stays on the RX Ring. Returns the number of packets processed.
+    pub fn run_until<F>(&mut self, stop: &std::sync::atomic::AtomicBool, max_packets: Option<u64>, mut callback: F) -> io::Result<u64>
+    where
+        F: FnMut(&mut PacketBatch),
+    {
+        let mut total = 0u64;
+        let done = |total: u64| {
+            stop.load(std::sync::atomic::Ordering::Relaxed) || max_packets.is_some_and(|max| total >= max)
+        };
+        // Packets left in the budget, if any.
+        let limit = |total: u64| max_packets.map(|max| (max - total).min(usize::MAX as u64) as usize);
+
+        let mut adaptive = AdaptiveIdle::new(self.clock.now());
+
+        loop {
+            if done(total) { break Ok(total); }
+            let count = self.process_limited(&mut callback, limit(total))?;
+            total += count as u64;
+            if count > 0 {
+                adaptive.packet(self.clock.now());
+                continue;
+            }
+            // Re-read every time: `poller_control` may have switched it.
+            match self.poller.get() {
+                Poller::Busy => {}
+                Poller::Wait => {
+                    // Block until the next packet, but wake up to re-check `stop`.
+                    // A dead socket would wake us immediately, forever: give up instead.
+                    if let PollStatus::Error(revents) = self.socket.wait_rx(self.poll_timeout)? {
+                        break Err(io::Error::new(
+                            io::ErrorKind::BrokenPipe,
+                            format!("socket error while waiting for RX (revents {:#x})", revents),
+                        ));
                     }
                 }
+                Poller::Adaptive => adaptive.idle(self.clock.now()),
             }
         }
     }
 
+    /// Run `callback` over `descs` pointing into the engine's UMEM, without touching
+    /// any ring.
+    ///
+    /// Offline mode for testing packet-processing logic: the batch is built exactly
+    /// as in `process_batch`, and the verdict for each descriptor is returned instead
+    /// of being committed. Descriptors `process_batch` would reject as malformed, and
+    /// any sharing a UMEM chunk with an earlier one, never reach `callback` and come
+    /// back as `Action::Drop`.
+    pub fn process_descriptors<F>(&mut self, descs: &[XDPDesc], mut callback: F) -> Vec<Action>
+    where
+        F: FnMut(&mut PacketBatch),
+    {
+        let layout = self.socket.umem.layout();
+        let umem_len = self.socket.umem.len() as u64;
+        let mut chunks = std::collections::HashSet::new();
+        let (positions, mut valid): (Vec<usize>, Vec<XDPDesc>) = descs.iter().enumerate()
+            .filter(|(_, desc)| {
+                rx_desc_valid(desc, &layout)
+                    && desc.addr + desc.len as u64 <= umem_len
+                    && chunks.insert(desc.addr / layout.chunk_size as u64)
+            })
+            .map(|(i, desc)| (i, *desc))
+            .unzip();
+
+        let mut valid_actions = vec![Action::Drop; valid.len()];
+        let zerocopy = self.socket.is_zerocopy();
+        let mut batch = PacketBatch::new(&mut valid, &mut self.socket.umem, &mut valid_actions, zerocopy, self.socket.rx_meta);
+        callback(&mut batch);
+
+        let mut actions = vec![Action::Drop; descs.len()];
+        for (i, action) in positions.into_iter().zip(valid_actions) {
+            actions[i] = action;
+        }
+        actions
+    }
+
     pub fn socket_fd(&self) -> fluxcapacitor_core::sys::socket::RawFd {
         self.socket.fd()
     }
 
-    /// Process a single batch of packets.
+    /// Interface of the socket. See `FluxRaw::interface`.
+    pub fn interface(&self) -> &str {
+        self.socket.interface()
+    }
+
+    pub fn if_index(&self) -> u32 {
+        self.socket.if_index()
+    }
+
+    pub fn queue_id(&self) -> u32 {
+        self.socket.queue_id()
+    }
+
+    /// Keep consuming `batch_size` chunks in one `process_batch` call until the RX Ring is empty.
+    ///
+    /// Off by default. Draining clears bursts with fewer trips through the poll loop,
+    /// which raises throughput, but a large backlog is processed in one go before the
+    /// caller regains control (stop flag checks, other sockets), so per-call latency
+    /// becomes unbounded.
+    pub fn set_drain_rx(&mut self, drain: bool) {
+        self.drain_rx = drain;
+    }
+
+    /// Longest a `Poller::Wait` engine blocks waiting for RX before re-checking
+    /// `stop`. Defaults to `DEFAULT_POLL_TIMEOUT`.
+    pub fn set_poll_timeout(&mut self, timeout: Duration) {
+        self.poll_timeout = timeout;
+    }
+
+    /// Time source for `Poller::Adaptive`'s switch from spinning to sleeping
+    /// (`ADAPTIVE_SPIN` after the last packet). Defaults to `SystemClock`; tests
+    /// can pass a `MockClock`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn poller(&self) -> Poller {
+        self.poller.get()
+    }
+
+    /// Switch the idle strategy. Takes effect on the next loop iteration of `run`.
+    pub fn set_poller(&mut self, poller: Poller) {
+        self.poller.set(poller);
+    }
+
+    /// Handle for switching the poller from another thread while `run` holds the
+    /// engine.
+    pub fn poller_control(&self) -> Arc<PollerControl> {
+        Arc::clone(&self.poller)
+    }
+
+    /// Choose whether completion reclaim runs before RX or after the TX kick.
+    /// See `CommitOrder`.
+    pub fn set_commit_order(&mut self, order: CommitOrder) {
+        self.commit_order = order;
+    }
+
+    /// Most completed TX frames recycled to the Fill Ring per pass (default 32,
+    /// at least 1), independent of the RX `batch_size` (`FluxBuilder::rx_batch`).
+    ///
+    /// Completions pile up between passes when transmitting heavily, so a value
+    /// above `batch_size` recycles them in fewer passes; a smaller one spreads
+    /// the work out. Recycling is still limited by room on the Fill Ring.
+    pub fn set_comp_batch(&mut self, frames: usize) {
+        self.comp_batch = frames.clamp(1, u32::MAX as usize) as u32;
+    }
+
+    /// Counters for this engine. Clone the `Arc` to read them from another thread.
+    pub fn stats(&self) -> Arc<EngineStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Limit how many frames may be held (`PacketRef::retain`) at once.
+    ///
+    /// Defaults to a quarter of the UMEM frames. Holds beyond the limit are
+    /// treated as `Action::Drop`.
+    pub fn set_max_held(&mut self, max: usize) {
+        self.max_held = max;
+    }
+
+    /// Number of frames currently held.
+    pub fn held_count(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Contents of the held frame at `addr`, if it is held.
+    pub fn held_frame(&self, addr: u64) -> Option<&[u8]> {
+        let desc = self.held.iter().find(|d| d.addr == addr)?;
+        Some(unsafe { std::slice::from_raw_parts(self.socket.umem.as_ptr().add(desc.addr as usize), desc.len as usize) })
+    }
+
+    /// Return a held frame to the Fill Ring. Returns `false` if `addr` is not held.
+    ///
+    /// The frame is handed to the kernel on the next poll.
+    pub fn release_held(&mut self, addr: u64) -> bool {
+        match self.held.iter().position(|d| d.addr == addr) {
+            Some(pos) => {
+                self.held.swap_remove(pos);
+                self.spare_frames.push(addr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look at the next batch without consuming it: `callback` sees each packet
+    /// read-only, in order, and the descriptors stay on the RX Ring. Returns the
+    /// number of packets seen.
+    ///
+    /// Decide each packet's fate later (e.g. after an asynchronous policy lookup) and
+    /// pass the verdicts to `commit`. Until then the frames stay with the engine, so
+    /// the kernel has that many fewer Fill buffers to receive into; do not leave a
+    /// batch peeked for long, and do not call `process_batch` in between.
+    /// Peeking again shows the same packets, plus any that arrived since.
+    pub fn peek_batch<F>(&mut self, mut callback: F) -> io::Result<usize>
+    where
+        F: FnMut(&PacketRef),
+    {
+        let count = self.socket.rx.peek(self.batch_size as u32);
+        if count == 0 && self.socket.needs_wakeup_rx() {
+            let _ = self.socket.wakeup_rx();
+        }
+
+        // Skip malformed descriptors like `process_batch` will, so the indices of
+        // `commit`'s verdicts line up.
+        let layout = self.socket.umem.layout();
+        let mut valid = 0;
+        for i in 0..count {
+            let desc = unsafe { self.socket.rx.read_at(self.socket.rx.consumer_idx() + i as u32) };
+            if rx_desc_valid(&desc, &layout) {
+                self.descs_buf[valid] = desc;
+                valid += 1;
+            }
+        }
+        self.peeked = count;
+
+        let zerocopy = self.socket.is_zerocopy();
+        let rx_meta = self.socket.rx_meta;
+        let mut batch = PacketBatch::new(&mut self.descs_buf[..valid], &mut self.socket.umem, &mut self.actions_buf[..valid], zerocopy, rx_meta);
+        for packet in batch.iter_mut() {
+            callback(&packet);
+        }
+        Ok(valid)
+    }
+
+    /// Apply verdicts to the batch seen by the last `peek_batch` and release it
+    /// from the RX Ring, exactly as `process_batch` would have. `actions[i]` is the
+    /// verdict for the `i`th packet peeked; packets without one are dropped.
+    ///
+    /// Returns the number of packets committed, 0 if nothing was peeked.
+    pub fn commit(&mut self, actions: &[Action]) -> io::Result<usize> {
+        let peeked = std::mem::take(&mut self.peeked);
+        if peeked == 0 {
+            return Ok(0);
+        }
+        self.process_chunk(&mut |batch: &mut PacketBatch| {
+            let (_, batch_actions, _) = batch.as_slices_mut();
+            for (slot, action) in batch_actions.iter_mut().zip(actions) {
+                *slot = *action;
+            }
+        }, peeked)
+    }
+
+    /// Register an observer for engine events. Replaces any previous one.
+    pub fn set_observer(&mut self, observer: Box<dyn FluxObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Process a batch of packets, or every pending batch if `drain_rx` is set.
+    /// Returns the total number of packets processed.
+    ///
+    /// Malformed RX descriptors (empty, oversized, or outside their UMEM chunk) never
+    /// reach `callback` and are counted in `EngineStatsSnapshot::rx_invalid`. Their
+    /// frames are not recycled, since the address cannot be trusted.
     pub fn process_batch<F>(&mut self, callback: &mut F) -> io::Result<usize>
     where
         F: FnMut(&mut PacketBatch),
     {
-        // 1. Recycle Completed TX Frames
-        {
-                let count = self.socket.comp.peek(32);
-                if count > 0 {
-                    if let Some(mut producer_idx) = self.socket.fill.reserve(count as u32) {
-                        for i in 0..count {
-                            let addr = unsafe { self.socket.comp.read_at(self.socket.comp.consumer_idx() + i as u32) };
-                            unsafe { self.socket.fill.write_at(producer_idx, addr) };
-                            producer_idx += 1;
-                        }
-                        self.socket.fill.submit(producer_idx);
-                        self.socket.comp.release(count as u32);
-                    } else {
-                        self.socket.comp.release(count as u32);
-                    }
-                }
+        self.process_limited(callback, None)
+    }
+
+    /// `process_batch` processing at most `budget` packets in total, even when
+    /// draining.
+    fn process_limited<F>(&mut self, callback: &mut F, budget: Option<usize>) -> io::Result<usize>
+    where
+        F: FnMut(&mut PacketBatch),
+    {
+        let mut total = 0;
+        loop {
+            let max = budget.map_or(self.batch_size, |budget| (budget - total).min(self.batch_size));
+            let count = self.process_chunk(callback, max)?;
+            total += count;
+            if !self.drain_rx || count < self.batch_size || budget == Some(total) {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Process at most `max` packets from the RX Ring.
+    fn process_chunk<F>(&mut self, callback: &mut F, max: usize) -> io::Result<usize>
+    where
+        F: FnMut(&mut PacketBatch),
+    {
+        let tx_first = self.commit_order == CommitOrder::TxFirst;
+
+        // 0-1. Housekeeping, unless deferred until after the TX kick
+        if !tx_first {
+            self.reclaim();
+        }
+
+        if let Some(observer) = self.observer.as_mut() {
+            if self.socket.fill.available() == self.socket.fill.len() {
+                observer.on_fill_empty();
+            }
         }
 
         // 2. Consume from RX Ring
         let rx_count = {
-            let consumer = self.socket.rx.peek(self.batch_size as u32);
+            let consumer = self.socket.rx.peek(max.min(self.batch_size) as u32);
             if consumer == 0 {
+                if tx_first {
+                    self.reclaim();
+                }
                 if self.socket.needs_wakeup_rx() {
                         let _ = self.socket.wakeup_rx();
                 }
@@ -122,68 +412,500 @@ impl FluxEngine {
             }
             
             let count = consumer;
+            let layout = self.socket.umem.layout();
+            let mut valid = 0;
+            let mut invalid = 0;
             for i in 0..count {
-                self.descs_buf[i as usize] = unsafe { self.socket.rx.read_at(self.socket.rx.consumer_idx() + i as u32) };
-                self.actions_buf[i as usize] = Action::Drop; // Default to drop
+                let desc = unsafe { self.socket.rx.read_at(self.socket.rx.consumer_idx() + i as u32) };
+                if !rx_desc_valid(&desc, &layout) {
+                    // Nothing proves which frame, if any, this names: recycling a guess
+                    // could put a frame on the Fill Ring twice.
+                    invalid += 1;
+                    continue;
+                }
+                self.descs_buf[valid] = desc;
+                self.actions_buf[valid] = Action::Drop; // Default to drop
+                valid += 1;
             }
             
             self.socket.rx.release(count as u32);
-            count
+            if invalid > 0 {
+                self.stats.add_rx_invalid(invalid);
+            }
+            valid
         };
 
         if rx_count > 0 {
-            let active_descs = &mut self.descs_buf[0..rx_count as usize];
-            let active_actions = &mut self.actions_buf[0..rx_count as usize];
-            
             // 3. User Callback
             {
-                let mut batch = PacketBatch::new(active_descs, &mut self.socket.umem, active_actions);
+                let zerocopy = self.socket.is_zerocopy();
+                let rx_meta = self.socket.rx_meta;
+                let mut batch = PacketBatch::new(&mut self.descs_buf[0..rx_count as usize], &mut self.socket.umem, &mut self.actions_buf[0..rx_count as usize], zerocopy, rx_meta);
                 callback(&mut batch);
             }
-            
-            // 4. Commit Actions
-            let mut tx_needed = 0;
-            for a in active_actions.iter() {
-                if *a == Action::Tx { tx_needed += 1; }
+
+            // 4. Commit Actions. Low-rate flows mostly arrive one packet at a time.
+            if rx_count == 1 {
+                self.commit_one();
+            } else {
+                self.commit_actions(rx_count as usize);
             }
-            
-            if tx_needed > 0 {
-                if let Some(mut tx_prod) = self.socket.tx.reserve(tx_needed) {
-                    for (i, action) in active_actions.iter().enumerate() {
-                        if *action == Action::Tx {
-                            unsafe { self.socket.tx.write_at(tx_prod, active_descs[i]) };
-                            tx_prod += 1;
-                        }
-                    }
-                    self.socket.tx.submit(tx_prod);
-                    if self.socket.needs_wakeup_tx() {
-                            let _ = self.socket.wakeup_tx();
+
+            // 5. Deferred housekeeping, off the RX-to-TX path
+            if tx_first {
+                self.reclaim();
+            }
+        }
+        
+        Ok(rx_count as usize)
+    }
+
+    /// Apply the first `count` verdicts in `actions_buf` to their descriptors.
+    fn commit_actions(&mut self, count: usize) {
+        let active_descs = &self.descs_buf[0..count];
+        let active_actions = &mut self.actions_buf[0..count];
+
+        let rx_bytes: u64 = active_descs.iter().map(|d| d.len as u64).sum();
+        self.stats.add_rx(count as u64, rx_bytes);
+
+        let mut tx_needed = 0;
+        let mut tx_bytes = 0u64;
+        for (i, a) in active_actions.iter().enumerate() {
+            if *a == Action::Tx {
+                tx_needed += 1;
+                tx_bytes += active_descs[i].len as u64;
+            }
+        }
+        
+        // Tx reuses the RX descriptor as-is, so its `options` (e.g. XDP_PKT_CONTD)
+        // go back out with the frame. Dropped frames return to Fill as a bare
+        // address, which clears them.
+        if tx_needed > 0 {
+            if let Some(mut tx_prod) = self.socket.tx.reserve(tx_needed) {
+                for (i, action) in active_actions.iter().enumerate() {
+                    if *action == Action::Tx {
+                        unsafe { self.socket.tx.write_at(tx_prod, active_descs[i]) };
+                        tx_prod += 1;
                     }
+                }
+                self.socket.tx.submit(tx_prod);
+                self.stats.add_tx(tx_needed as u64, tx_bytes);
+                if self.socket.needs_wakeup_tx() {
+                        let _ = self.socket.wakeup_tx();
+                }
+            } else {
+                for action in active_actions.iter_mut() {
+                    if *action == Action::Tx { *action = Action::Drop; }
+                }
+                tx_needed = 0;
+                if let Some(observer) = self.observer.as_mut() {
+                    observer.on_tx_full();
+                }
+            }
+        }
+        
+        // Held frames leave the rings until released; holds over the limit are dropped.
+        for (i, action) in active_actions.iter_mut().enumerate() {
+            if *action == Action::Hold {
+                if self.held.len() < self.max_held {
+                    self.held.push(active_descs[i]);
                 } else {
-                    for action in active_actions.iter_mut() {
-                        if *action == Action::Tx { *action = Action::Drop; }
+                    *action = Action::Drop;
+                }
+            }
+        }
+
+        // Tx frames stay owned by the kernel until they show up on the Completion
+        // Ring (step 1); only frames that were never queued go straight back to Fill.
+        // Pass cannot be reinjected into the kernel from UMEM, so recycle it like Drop.
+        let mut fill_needed = 0;
+        let mut passed = 0;
+        for a in active_actions.iter() {
+            if matches!(a, Action::Drop | Action::Pass) { fill_needed += 1; }
+            if *a == Action::Pass { passed += 1; }
+        }
+        
+        // `reclaim` may have topped the Fill Ring up from the spares: whatever does
+        // not fit waits with them rather than leaking.
+        if fill_needed > 0 {
+            let (mut fill_prod, room) = self.socket.fill.reserve_up_to(fill_needed);
+            let mut written = 0;
+            for (i, action) in active_actions.iter().enumerate() {
+                if matches!(action, Action::Drop | Action::Pass) {
+                    if written < room {
+                        unsafe { self.socket.fill.write_at(fill_prod, active_descs[i].addr) };
+                        fill_prod = fill_prod.wrapping_add(1);
+                        written += 1;
+                    } else {
+                        self.spare_frames.push(active_descs[i].addr);
                     }
                 }
             }
-            
-            let mut fill_needed = 0;
-            for a in active_actions.iter() {
-                if *a == Action::Drop { fill_needed += 1; }
+            if room > 0 {
+                self.socket.fill.submit(fill_prod);
             }
-            
-            if fill_needed > 0 {
-                if let Some(mut fill_prod) = self.socket.fill.reserve(fill_needed) {
-                        for (i, action) in active_actions.iter().enumerate() {
-                        if *action == Action::Drop {
-                            unsafe { self.socket.fill.write_at(fill_prod, active_descs[i].addr) };
-                            fill_prod += 1;
-                        }
+        }
+
+        // Same handling, but "not ours" is worth telling apart from "rejected".
+        self.stats.add_dropped((fill_needed - passed) as u64);
+        self.stats.add_passed(passed as u64);
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_batch(count, tx_needed as usize, fill_needed as usize);
+        }
+    }
+
+    /// `commit_actions(1)` without the counting passes over the batch.
+    fn commit_one(&mut self) {
+        let desc = self.descs_buf[0];
+        let mut action = self.actions_buf[0];
+        self.stats.add_rx(1, desc.len as u64);
+
+        if action == Action::Tx {
+            if let Some(tx_prod) = self.socket.tx.reserve(1) {
+                unsafe { self.socket.tx.write_at(tx_prod, desc) };
+                self.socket.tx.submit(tx_prod.wrapping_add(1));
+                self.stats.add_tx(1, desc.len as u64);
+                if self.socket.needs_wakeup_tx() {
+                    let _ = self.socket.wakeup_tx();
+                }
+            } else {
+                action = Action::Drop;
+                if let Some(observer) = self.observer.as_mut() {
+                    observer.on_tx_full();
+                }
+            }
+        }
+
+        if action == Action::Hold {
+            if self.held.len() < self.max_held {
+                self.held.push(desc);
+            } else {
+                action = Action::Drop;
+            }
+        }
+
+        let recycle = matches!(action, Action::Drop | Action::Pass);
+        if recycle {
+            if let Some(fill_prod) = self.socket.fill.reserve(1) {
+                unsafe { self.socket.fill.write_at(fill_prod, desc.addr) };
+                self.socket.fill.submit(fill_prod.wrapping_add(1));
+            } else {
+                self.spare_frames.push(desc.addr);
+            }
+        }
+
+        self.stats.add_dropped((action == Action::Drop) as u64);
+        self.stats.add_passed((action == Action::Pass) as u64);
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_batch(1, (action == Action::Tx) as usize, recycle as usize);
+        }
+        self.actions_buf[0] = action;
+    }
+
+    /// Hand startup spares, released holds and completed TX frames back to the Fill Ring.
+    fn reclaim(&mut self) {
+        // 0. Hand spare frames to the kernel as the Fill Ring drains
+        if !self.spare_frames.is_empty() {
+            let count = (self.spare_frames.len() as u32).min(self.socket.fill.available());
+            if let Some(mut fill_prod) = self.socket.fill.reserve(count) {
+                for addr in self.spare_frames.drain(self.spare_frames.len() - count as usize..) {
+                    unsafe { self.socket.fill.write_at(fill_prod, addr) };
+                    fill_prod += 1;
+                }
+                self.socket.fill.submit(fill_prod);
+            }
+        }
+
+        // 1. Recycle Completed TX Frames. Only as many as the Fill Ring has room
+        // for: the rest stay on the Completion Ring until the next pass.
+        {
+                let count = self.socket.comp.peek(self.comp_batch) as u32;
+                let (mut producer_idx, recycled) = self.socket.fill.reserve_up_to(count);
+                if recycled > 0 {
+                    let start = self.socket.comp.consumer_idx();
+                    for i in 0..recycled {
+                        let addr = unsafe { self.socket.comp.read_at(start.wrapping_add(i)) };
+                        unsafe { self.socket.fill.write_at(producer_idx, addr) };
+                        producer_idx = producer_idx.wrapping_add(1);
                     }
-                    self.socket.fill.submit(fill_prod);
+                    self.socket.fill.submit(producer_idx);
+                    self.socket.comp.release(recycled);
                 }
+        }
+    }
+}
+
+/// Whether an RX descriptor names real packet data inside one UMEM chunk.
+///
+/// A descriptor failing this means the kernel and userspace disagree on the ring
+/// state; processing it would read (or transmit) another frame's bytes. Mirrors the
+/// kernel's own `rx_invalid_descs` checks: the data must be non-empty, at most a
+/// frame long, start after the configured headroom and end inside its chunk.
+fn rx_desc_valid(desc: &XDPDesc, layout: &UmemLayout) -> bool {
+    let chunk_size = layout.chunk_size as u64;
+    let offset = desc.addr % chunk_size;
+    desc.len > 0
+        && desc.len <= layout.frame_size
+        && desc.addr < layout.size() as u64
+        && offset >= layout.headroom as u64
+        && offset + desc.len as u64 <= chunk_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fluxcapacitor_core::umem::layout::UmemLayout;
+    use fluxcapacitor_core::umem::mmap::UmemRegion;
+
+    // An engine over in-memory rings that are never serviced, for offline processing.
+    #[cfg(target_os = "linux")]
+    fn offline_engine(umem: UmemRegion) -> FluxEngine {
+        use crate::raw::af_packet::ring_pair;
+
+        let (rx_map, _, rx) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+        let (fill_map, fill, _) = ring_pair::<u64>(4).expect("Failed to map ring");
+        let (tx_map, tx, _) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(4).expect("Failed to map ring");
+        let raw = FluxRaw::new(umem, rx, rx_map, fill, fill_map, tx, tx_map, comp, comp_map, -1);
+        FluxEngine::new(raw, 4)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_descriptors_offline() {
+        let umem = UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem");
+        let descs: Vec<XDPDesc> = (0..3u8).map(|i| {
+            let addr = i as u64 * 2048;
+            umem.write_frame(addr, &[i; 60]).expect("Failed to write frame");
+            XDPDesc { addr, len: 60, options: 0 }
+        }).collect();
+        let mut engine = offline_engine(umem);
+
+        // Echo frame 0, hold frame 2, leave frame 1 to the default
+        let actions = engine.process_descriptors(&descs, |batch| {
+            for mut packet in batch.iter_mut() {
+                match packet.data()[0] {
+                    0 => packet.send(),
+                    2 => packet.retain(),
+                    _ => {}
+                }
+            }
+        });
+        assert_eq!(actions, [Action::Tx, Action::Drop, Action::Hold]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_process_descriptors_skips_bad_descriptors() {
+        let umem = UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem");
+        umem.write_frame(2048, &[7; 60]).expect("Failed to write frame");
+        let mut engine = offline_engine(umem);
+        let descs = [
+            XDPDesc { addr: 2048, len: 60, options: 0 },
+            // Past the end of UMEM, across a chunk, empty, and a second view of chunk 1
+            XDPDesc { addr: 1 << 40, len: 60, options: 0 },
+            XDPDesc { addr: 2000, len: 60, options: 0 },
+            XDPDesc { addr: 4096, len: 0, options: 0 },
+            XDPDesc { addr: 2048 + 100, len: 60, options: 0 },
+        ];
+
+        let mut seen = Vec::new();
+        let actions = engine.process_descriptors(&descs, |batch| {
+            for mut packet in batch.iter_mut() {
+                seen.push(packet.data()[0]);
+                packet.send();
+            }
+        });
+        assert_eq!(seen, [7]);
+        assert_eq!(actions, [Action::Tx, Action::Drop, Action::Drop, Action::Drop, Action::Drop]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_metadata_survives_between_passes() {
+        let layout = UmemLayout::with_chunk_size(2048, 4, 4096).with_headroom(64);
+        let umem = UmemRegion::new(layout).expect("Failed to create umem");
+        let descs: Vec<XDPDesc> = (0..2u8).map(|i| {
+            let addr = i as u64 * 4096 + 64;
+            umem.write_frame(addr, &[i; 60]).expect("Failed to write frame");
+            XDPDesc { addr, len: 60, options: 0 }
+        }).collect();
+        let mut engine = offline_engine(umem);
+
+        // Stage 1 tags each packet, stage 2 acts on the tag
+        engine.process_descriptors(&descs, |batch| {
+            for mut packet in batch.iter_mut() {
+                let class = packet.data()[0] + 10;
+                assert_eq!(packet.metadata().len(), 64);
+                packet.metadata_mut()[0] = class;
+            }
+        });
+        let actions = engine.process_descriptors(&descs, |batch| {
+            for mut packet in batch.iter_mut() {
+                if packet.metadata()[0] == 11 {
+                    packet.send();
+                }
+                // The tag is not part of the packet
+                assert_eq!(packet.data(), [packet.data()[0]; 60]);
+            }
+        });
+        assert_eq!(actions, [Action::Drop, Action::Tx]);
+    }
+
+    #[test]
+    fn test_adaptive_idle_follows_clock() {
+        use crate::config::{IdleStep, MockClock, ADAPTIVE_SPIN};
+        use std::time::Duration;
+
+        let clock = MockClock::new();
+        let mut idle = AdaptiveIdle::new(clock.now());
+        assert_eq!(idle.step(clock.now()), IdleStep::Spin);
+
+        // Spins up to the threshold, sleeps past it
+        clock.advance(ADAPTIVE_SPIN);
+        assert_eq!(idle.step(clock.now()), IdleStep::Spin);
+        clock.advance(Duration::from_nanos(1));
+        assert_eq!(idle.step(clock.now()), IdleStep::Sleep);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(idle.step(clock.now()), IdleStep::Sleep);
+
+        // A packet restarts the spin phase
+        idle.packet(clock.now());
+        assert_eq!(idle.step(clock.now()), IdleStep::Spin);
+        clock.advance(ADAPTIVE_SPIN * 2);
+        assert_eq!(idle.step(clock.now()), IdleStep::Sleep);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_invalid_rx_frame_not_recycled() {
+        use crate::raw::af_packet::ring_pair;
+
+        let umem = UmemRegion::new(UmemLayout::new(2048, 8)).expect("Failed to create umem");
+        let (rx_map, mut rx_k, rx) = ring_pair::<XDPDesc>(16).expect("Failed to map ring");
+        let (fill_map, fill, mut fill_k) = ring_pair::<u64>(16).expect("Failed to map ring");
+        let (tx_map, tx, _) = ring_pair::<XDPDesc>(16).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(16).expect("Failed to map ring");
+        let raw = FluxRaw::new(umem, rx, rx_map, fill, fill_map, tx, tx_map, comp, comp_map, -1);
+        let mut engine = FluxEngine::new(raw, 4);
+
+        // The kernel takes every frame, then returns frame 0 and a garbage
+        // descriptor pointing into frame 2, which it still owns
+        assert_eq!(fill_k.peek(16), 8);
+        fill_k.release(8);
+        let idx = rx_k.reserve(2).expect("RX Ring has room");
+        unsafe {
+            rx_k.write_at(idx, XDPDesc { addr: 0, len: 60, options: 0 });
+            rx_k.write_at(idx + 1, XDPDesc { addr: 3 * 2048 - 10, len: 60, options: 0 });
+        }
+        rx_k.submit(idx + 2);
+
+        assert_eq!(engine.process_batch(&mut |_batch| {}).unwrap(), 1);
+        assert_eq!(engine.process_batch(&mut |_batch| {}).unwrap(), 0);
+        assert_eq!(engine.stats().snapshot().rx_invalid, 1);
+
+        // Only frame 0 came back to the Fill Ring
+        let fills: Vec<u64> = (0..fill_k.peek(16) as u32).map(|i| unsafe { fill_k.read_at(fill_k.consumer_idx() + i) }).collect();
+        assert_eq!(fills, [0]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_run_until_budget_caps_drain() {
+        use crate::raw::af_packet::ring_pair;
+        use std::sync::atomic::AtomicBool;
+
+        let umem = UmemRegion::new(UmemLayout::new(2048, 16)).expect("Failed to create umem");
+        let (rx_map, mut rx_k, rx) = ring_pair::<XDPDesc>(16).expect("Failed to map ring");
+        let (fill_map, fill, _) = ring_pair::<u64>(16).expect("Failed to map ring");
+        let (tx_map, tx, _) = ring_pair::<XDPDesc>(16).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(16).expect("Failed to map ring");
+        let raw = FluxRaw::new(umem, rx, rx_map, fill, fill_map, tx, tx_map, comp, comp_map, -1);
+        let mut engine = FluxEngine::new(raw, 4);
+        engine.set_drain_rx(true);
+
+        // Twelve packets waiting, three batches' worth
+        let mut idx = rx_k.reserve(12).expect("RX Ring has room");
+        for i in 0..12u64 {
+            unsafe { rx_k.write_at(idx, XDPDesc { addr: i * 2048, len: 60, options: 0 }) };
+            idx += 1;
+        }
+        rx_k.submit(idx);
+
+        let stop = AtomicBool::new(false);
+        let mut seen = 0;
+        assert_eq!(engine.run_until(&stop, Some(5), |batch| seen += batch.len()).unwrap(), 5);
+        assert_eq!(seen, 5);
+
+        // The rest stays on the ring for the next call
+        assert_eq!(engine.run_until(&stop, Some(7), |batch| seen += batch.len()).unwrap(), 7);
+        assert_eq!(seen, 12);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_single_packet_commit_matches_batch_commit() {
+        use crate::raw::af_packet::ring_pair;
+        use crate::observer::FluxObserver;
+        use fluxcapacitor_core::ring::ConsumerRing;
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Events(Arc<Mutex<Vec<(usize, usize, usize)>>>);
+        impl FluxObserver for Events {
+            fn on_batch(&mut self, rx: usize, tx: usize, dropped: usize) {
+                self.0.lock().unwrap().push((rx, tx, dropped));
+            }
+            fn on_tx_full(&mut self) {
+                self.0.lock().unwrap().push((0, 0, 0));
+            }
+        }
+
+        // An engine over in-memory rings, with the ends the kernel would read.
+        let engine = || {
+            let umem = UmemRegion::new(UmemLayout::new(2048, 8)).expect("Failed to create umem");
+            let (rx_map, _, rx) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+            let (fill_map, fill, fill_k) = ring_pair::<u64>(4).expect("Failed to map ring");
+            let (tx_map, tx, tx_k) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+            let (comp_map, _, comp) = ring_pair::<u64>(4).expect("Failed to map ring");
+            let raw = FluxRaw::new(umem, rx, rx_map, fill, fill_map, tx, tx_map, comp, comp_map, -1);
+            let mut engine = FluxEngine::new(raw, 4);
+            let events = Events::default();
+            engine.set_observer(Box::new(events.clone()));
+            (engine, fill_k, tx_k, events)
+        };
+        let drain = |fill: &mut ConsumerRing<u64>, tx: &mut ConsumerRing<XDPDesc>| {
+            let fills: Vec<u64> = (0..fill.peek(4) as u32).map(|i| unsafe { fill.read_at(fill.consumer_idx() + i) }).collect();
+            let txs: Vec<u64> = (0..tx.peek(4) as u32).map(|i| unsafe { tx.read_at(tx.consumer_idx() + i) }.addr).collect();
+            (fills, txs)
+        };
+
+        let desc = XDPDesc { addr: 2048, len: 60, options: 0 };
+        for action in [Action::Tx, Action::Drop, Action::Pass, Action::Hold] {
+            for (max_held, tx_full) in [(0, false), (1, false), (1, true)] {
+                let (mut fast, mut fast_fill, mut fast_tx, fast_events) = engine();
+                let (mut general, mut general_fill, mut general_tx, general_events) = engine();
+                for engine in [&mut fast, &mut general] {
+                    engine.set_max_held(max_held);
+                    if tx_full {
+                        let idx = engine.socket.tx.reserve(4).expect("TX Ring has room");
+                        engine.socket.tx.submit(idx + 4);
+                    }
+                    engine.descs_buf[0] = desc;
+                    engine.actions_buf[0] = action;
+                }
+
+                fast.commit_one();
+                general.commit_actions(1);
+
+                let case = format!("{action:?} max_held={max_held} tx_full={tx_full}");
+                assert_eq!(fast.actions_buf[0], general.actions_buf[0], "{case}");
+                assert_eq!(fast.stats.snapshot(), general.stats.snapshot(), "{case}");
+                assert_eq!(fast.held_frame(desc.addr).is_some(), general.held_frame(desc.addr).is_some(), "{case}");
+                assert_eq!(fast.held_count(), general.held_count(), "{case}");
+                assert_eq!(drain(&mut fast_fill, &mut fast_tx), drain(&mut general_fill, &mut general_tx), "{case}");
+                assert_eq!(*fast_events.0.lock().unwrap(), *general_events.0.lock().unwrap(), "{case}");
             }
         }
-        
-        Ok(rx_count as usize)
     }
 }
@@ -0,0 +1,348 @@
+use fluxcapacitor_core::sys::if_xdp::XdpStatistics;
+use fluxcapacitor_core::sys::socket::{get_xdp_statistics, RawFd};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Packet and byte counters of a `FluxEngine`, shared with monitoring threads.
+///
+/// The engine is the only writer. Counters are `AtomicU64` so a reader on another
+/// thread never sees a torn value, even on 32-bit targets. Each counter is exact on
+/// its own; a snapshot taken mid-batch may be one batch ahead on some counters.
+#[derive(Debug, Default)]
+pub struct EngineStats {
+    rx_packets: AtomicU64,
+    rx_bytes: AtomicU64,
+    tx_packets: AtomicU64,
+    tx_bytes: AtomicU64,
+    dropped: AtomicU64,
+    passed: AtomicU64,
+    rx_invalid: AtomicU64,
+}
+
+/// Plain copy of `EngineStats` at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineStatsSnapshot {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    /// Packets given `Action::Drop` (or held over the limit). Their frames go back
+    /// to the Fill Ring.
+    pub dropped: u64,
+    /// Packets given `Action::Pass`. Recycled like drops, but the application did
+    /// not handle them rather than reject them.
+    pub passed: u64,
+    /// RX descriptors rejected as malformed (see `FluxEngine::process_batch`).
+    /// Not counted in `rx_packets`.
+    pub rx_invalid: u64,
+}
+
+impl EngineStatsSnapshot {
+    /// All counters as one flat JSON object keyed by field name, e.g.
+    /// `{"rx_packets":10,"rx_bytes":640,...}`, for JSON-based metrics pipelines.
+    ///
+    /// Needs no dependencies; the `serde` feature also derives `Serialize` and
+    /// `Deserialize` with the same field names.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"rx_packets\":{},\"rx_bytes\":{},\"tx_packets\":{},\"tx_bytes\":{},\"dropped\":{},\"passed\":{},\"rx_invalid\":{}}}",
+            self.rx_packets, self.rx_bytes, self.tx_packets, self.tx_bytes,
+            self.dropped, self.passed, self.rx_invalid,
+        )
+    }
+}
+
+// Serializes as a snapshot, so live counters and snapshots look the same downstream.
+#[cfg(feature = "serde")]
+impl serde::Serialize for EngineStats {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snapshot().serialize(serializer)
+    }
+}
+
+impl EngineStats {
+    pub fn snapshot(&self) -> EngineStatsSnapshot {
+        EngineStatsSnapshot {
+            rx_packets: self.rx_packets.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            tx_packets: self.tx_packets.load(Ordering::Relaxed),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            passed: self.passed.load(Ordering::Relaxed),
+            rx_invalid: self.rx_invalid.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Current counters as a flat JSON object; see `EngineStatsSnapshot::to_json`.
+    pub fn to_json(&self) -> String {
+        self.snapshot().to_json()
+    }
+
+    pub(crate) fn add_rx(&self, packets: u64, bytes: u64) {
+        self.rx_packets.fetch_add(packets, Ordering::Relaxed);
+        self.rx_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_tx(&self, packets: u64, bytes: u64) {
+        self.tx_packets.fetch_add(packets, Ordering::Relaxed);
+        self.tx_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_dropped(&self, packets: u64) {
+        self.dropped.fetch_add(packets, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_passed(&self, packets: u64) {
+        self.passed.fetch_add(packets, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rx_invalid(&self, descs: u64) {
+        self.rx_invalid.fetch_add(descs, Ordering::Relaxed);
+    }
+}
+
+/// Counter increments over one `StatsPoller` interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsSample {
+    pub elapsed: Duration,
+    pub engine: EngineStatsSnapshot,
+    pub kernel: XdpStatistics,
+}
+
+impl StatsSample {
+    pub fn rx_pps(&self) -> f64 {
+        self.per_sec(self.engine.rx_packets)
+    }
+
+    /// Received bits per second (frame bytes, without preamble or FCS).
+    pub fn rx_bps(&self) -> f64 {
+        self.per_sec(self.engine.rx_bytes * 8)
+    }
+
+    pub fn tx_pps(&self) -> f64 {
+        self.per_sec(self.engine.tx_packets)
+    }
+
+    pub fn tx_bps(&self) -> f64 {
+        self.per_sec(self.engine.tx_bytes * 8)
+    }
+
+    /// Share of the packets reaching the socket that the kernel dropped before
+    /// userspace saw them (full RX ring or other drops), from 0.0 to 1.0.
+    /// Verdicts of `Action::Drop` are not losses and do not count.
+    pub fn drop_rate(&self) -> f64 {
+        let lost = self.kernel.rx_dropped + self.kernel.rx_ring_full;
+        let offered = self.engine.rx_packets + lost;
+        if offered == 0 {
+            return 0.0;
+        }
+        lost as f64 / offered as f64
+    }
+
+    fn per_sec(&self, count: u64) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        count as f64 / secs
+    }
+}
+
+/// Turns periodic snapshots of `EngineStats` and the socket's `XDP_STATISTICS`
+/// into per-interval rates, keeping the most recent samples.
+///
+/// Runs beside the engine, on its own thread and cadence: reading the kernel
+/// counters is a syscall, so keep it off the packet path.
+///
+/// ```ignore
+/// let mut poller = StatsPoller::new(engine.socket_fd(), engine.stats(), 60);
+/// std::thread::spawn(move || poller.run(Duration::from_secs(1), &stop, |s| {
+///     println!("{:.0} pps, {:.2}% lost", s.rx_pps(), s.drop_rate() * 100.0);
+/// }));
+/// ```
+pub struct StatsPoller {
+    fd: RawFd,
+    stats: Arc<EngineStats>,
+    last: Option<(Instant, EngineStatsSnapshot, XdpStatistics)>,
+    samples: VecDeque<StatsSample>,
+    capacity: usize,
+}
+
+unsafe impl Send for StatsPoller {}
+
+impl StatsPoller {
+    /// Poll the XDP socket `fd` and the engine counters `stats`, keeping the last
+    /// `capacity` samples.
+    pub fn new(fd: RawFd, stats: Arc<EngineStats>, capacity: usize) -> Self {
+        Self { fd, stats, last: None, samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Take a snapshot now and return the sample since the previous one.
+    /// The first call only sets the baseline and returns `None`.
+    pub fn poll(&mut self) -> io::Result<Option<StatsSample>> {
+        let kernel = get_xdp_statistics(self.fd)?;
+        Ok(self.record(Instant::now(), self.stats.snapshot(), kernel))
+    }
+
+    /// `poll` every `interval` until `stop` is set, passing each sample to `on_sample`.
+    pub fn run<F>(&mut self, interval: Duration, stop: &AtomicBool, mut on_sample: F) -> io::Result<()>
+    where
+        F: FnMut(&StatsSample),
+    {
+        while !stop.load(Ordering::Relaxed) {
+            if let Some(sample) = self.poll()? {
+                on_sample(&sample);
+            }
+            std::thread::sleep(interval);
+        }
+        Ok(())
+    }
+
+    /// Recent samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &StatsSample> {
+        self.samples.iter()
+    }
+
+    fn record(&mut self, now: Instant, engine: EngineStatsSnapshot, kernel: XdpStatistics) -> Option<StatsSample> {
+        let previous = self.last.replace((now, engine, kernel));
+        let (then, last_engine, last_kernel) = previous?;
+        let sample = StatsSample {
+            elapsed: now.saturating_duration_since(then),
+            engine: EngineStatsSnapshot {
+                rx_packets: engine.rx_packets.wrapping_sub(last_engine.rx_packets),
+                rx_bytes: engine.rx_bytes.wrapping_sub(last_engine.rx_bytes),
+                tx_packets: engine.tx_packets.wrapping_sub(last_engine.tx_packets),
+                tx_bytes: engine.tx_bytes.wrapping_sub(last_engine.tx_bytes),
+                dropped: engine.dropped.wrapping_sub(last_engine.dropped),
+                passed: engine.passed.wrapping_sub(last_engine.passed),
+                rx_invalid: engine.rx_invalid.wrapping_sub(last_engine.rx_invalid),
+            },
+            kernel: XdpStatistics {
+                rx_dropped: kernel.rx_dropped.wrapping_sub(last_kernel.rx_dropped),
+                rx_invalid_descs: kernel.rx_invalid_descs.wrapping_sub(last_kernel.rx_invalid_descs),
+                tx_invalid_descs: kernel.tx_invalid_descs.wrapping_sub(last_kernel.tx_invalid_descs),
+                rx_ring_full: kernel.rx_ring_full.wrapping_sub(last_kernel.rx_ring_full),
+                rx_fill_ring_empty_descs: kernel.rx_fill_ring_empty_descs.wrapping_sub(last_kernel.rx_fill_ring_empty_descs),
+                tx_ring_empty_descs: kernel.tx_ring_empty_descs.wrapping_sub(last_kernel.tx_ring_empty_descs),
+            },
+        };
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        if self.capacity > 0 {
+            self.samples.push_back(sample);
+        }
+        Some(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_from_reader_thread() {
+        let stats = Arc::new(EngineStats::default());
+        let reader_stats = Arc::clone(&stats);
+
+        // Values above u32::MAX would tear on a 32-bit target without atomics
+        const BYTES: u64 = 1 << 33;
+        let reader = std::thread::spawn(move || {
+            let mut last = EngineStatsSnapshot::default();
+            loop {
+                let snap = reader_stats.snapshot();
+                assert!(snap.rx_packets >= last.rx_packets, "counter went backwards");
+                assert_eq!(snap.rx_bytes % BYTES, 0, "torn read: {}", snap.rx_bytes);
+                last = snap;
+                if snap.rx_packets == 10_000 {
+                    break last;
+                }
+            }
+        });
+
+        for _ in 0..10_000 {
+            stats.add_rx(1, BYTES);
+        }
+        stats.add_tx(3, 180);
+        stats.add_dropped(2);
+        stats.add_passed(4);
+        stats.add_rx_invalid(1);
+
+        let last = reader.join().expect("reader panicked");
+        assert_eq!(last.rx_packets, 10_000);
+        assert_eq!(stats.snapshot(), EngineStatsSnapshot {
+            rx_packets: 10_000,
+            rx_bytes: 10_000 * BYTES,
+            tx_packets: 3,
+            tx_bytes: 180,
+            dropped: 2,
+            passed: 4,
+            rx_invalid: 1,
+        });
+    }
+
+    #[test]
+    fn test_to_json_round_trip() {
+        let stats = EngineStats::default();
+        stats.add_rx(10, 1 << 40);
+        stats.add_tx(3, 180);
+        stats.add_dropped(u64::MAX);
+        stats.add_passed(4);
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).expect("invalid JSON");
+        let object = json.as_object().expect("not an object");
+        assert_eq!(object.len(), 7);
+        let field = |name: &str| object[name].as_u64().expect("not a u64");
+        let parsed = EngineStatsSnapshot {
+            rx_packets: field("rx_packets"),
+            rx_bytes: field("rx_bytes"),
+            tx_packets: field("tx_packets"),
+            tx_bytes: field("tx_bytes"),
+            dropped: field("dropped"),
+            passed: field("passed"),
+            rx_invalid: field("rx_invalid"),
+        };
+        assert_eq!(parsed, stats.snapshot());
+
+        // The serde impls agree with the hand-written encoding
+        #[cfg(feature = "serde")]
+        {
+            assert_eq!(serde_json::to_string(&stats).unwrap(), stats.to_json());
+            let snapshot: EngineStatsSnapshot = serde_json::from_str(&stats.to_json()).unwrap();
+            assert_eq!(snapshot, stats.snapshot());
+        }
+    }
+
+    #[test]
+    fn test_stats_poller_rates() {
+        // Synthetic snapshots go straight to `record`; the socket is never read.
+        #[cfg(target_os = "linux")]
+        let fd = -1;
+        #[cfg(not(target_os = "linux"))]
+        let fd = std::ptr::null_mut();
+        let mut poller = StatsPoller::new(fd, Arc::new(EngineStats::default()), 2);
+        let start = Instant::now();
+        let engine = |rx_packets, rx_bytes| EngineStatsSnapshot { rx_packets, rx_bytes, ..Default::default() };
+
+        assert_eq!(poller.record(start, engine(100, 6_400), XdpStatistics::default()), None);
+
+        // One second later: 1000 more packets of 64 bytes, 10 lost to a full RX ring
+        let kernel = XdpStatistics { rx_ring_full: 10, ..Default::default() };
+        let sample = poller.record(start + Duration::from_secs(1), engine(1_100, 70_400), kernel).unwrap();
+        assert_eq!(sample.rx_pps(), 1_000.0);
+        assert_eq!(sample.rx_bps(), 512_000.0);
+        assert_eq!(sample.tx_pps(), 0.0);
+        assert!((sample.drop_rate() - 10.0 / 1_010.0).abs() < 1e-12);
+
+        // Half a second with nothing new, then the ring keeps only the last two
+        let idle = poller.record(start + Duration::from_millis(1_500), engine(1_100, 70_400), kernel).unwrap();
+        assert_eq!((idle.rx_pps(), idle.drop_rate()), (0.0, 0.0));
+        let busy = poller.record(start + Duration::from_secs(2), engine(1_600, 102_400), kernel).unwrap();
+        assert_eq!(busy.rx_pps(), 1_000.0);
+        assert_eq!(poller.samples().copied().collect::<Vec<_>>(), [idle, busy]);
+    }
+}
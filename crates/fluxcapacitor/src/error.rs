@@ -17,4 +17,7 @@ pub enum FluxError {
     
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
+
+    #[error("Ring of {ring} entries cannot hold a batch of {batch} packets")]
+    RingTooSmall { ring: u32, batch: usize },
 }
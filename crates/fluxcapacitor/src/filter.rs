@@ -0,0 +1,198 @@
+//! Tuple matching for firewall-style callbacks.
+//!
+//! A `FlowMatcher` is built from optional constraints on the source and
+//! destination networks, the protocol and the port ranges; anything left unset is
+//! a wildcard. Prefixes are turned into masks up front, so matching a packet is a
+//! single header walk and a few compares.
+//!
+//! ```ignore
+//! let ssh = FlowMatcher::new()
+//!     .src_net("10.0.0.0".parse()?, 8)
+//!     .proto(6)
+//!     .dst_port(22..=22);
+//! engine.run(&stop, |batch| {
+//!     for mut pkt in batch.iter_matching(&ssh) {
+//!         pkt.pass();
+//!     }
+//! })?;
+//! ```
+
+use crate::packet::PacketRef;
+use fluxcapacitor_proto::ethernet::{ETH_P_IP, ETH_P_IPV6};
+use fluxcapacitor_proto::Transport;
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+
+// A prefix as a masked address. IPv4 lives in the low 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Net {
+    v6: bool,
+    addr: u128,
+    mask: u128,
+}
+
+impl Net {
+    fn new(addr: IpAddr, prefix: u8) -> Self {
+        let (v6, bits, addr) = match addr {
+            IpAddr::V4(a) => (false, 32, u32::from(a) as u128),
+            IpAddr::V6(a) => (true, 128, u128::from(a)),
+        };
+        let prefix = (prefix as u32).min(bits);
+        let host_bits = bits - prefix;
+        // Shifting a u128 by 128 overflows; a /0 simply has no network bits.
+        let mask = if prefix == 0 { 0 } else { (u128::MAX >> (128 - bits)) >> host_bits << host_bits };
+        Self { v6, addr: addr & mask, mask }
+    }
+
+    fn contains(&self, v6: bool, addr: u128) -> bool {
+        self.v6 == v6 && addr & self.mask == self.addr
+    }
+}
+
+/// Wildcard-able match on the IP 5-tuple. Unset fields match anything.
+///
+/// Works on IPv4 and IPv6 (extension headers skipped). A network of the other
+/// address family never matches. Port constraints only match TCP and UDP packets
+/// that carry their header, so non-first fragments fail them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlowMatcher {
+    src_net: Option<Net>,
+    dst_net: Option<Net>,
+    proto: Option<u8>,
+    src_port: Option<(u16, u16)>,
+    dst_port: Option<(u16, u16)>,
+}
+
+impl FlowMatcher {
+    /// A matcher that accepts every IP packet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Source address within `addr/prefix`. Prefixes longer than the address are clamped.
+    pub fn src_net(mut self, addr: IpAddr, prefix: u8) -> Self {
+        self.src_net = Some(Net::new(addr, prefix));
+        self
+    }
+
+    /// Destination address within `addr/prefix`.
+    pub fn dst_net(mut self, addr: IpAddr, prefix: u8) -> Self {
+        self.dst_net = Some(Net::new(addr, prefix));
+        self
+    }
+
+    /// IP protocol number (6 for TCP, 17 for UDP, ...).
+    pub fn proto(mut self, proto: u8) -> Self {
+        self.proto = Some(proto);
+        self
+    }
+
+    pub fn src_port(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.src_port = Some((*ports.start(), *ports.end()));
+        self
+    }
+
+    pub fn dst_port(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.dst_port = Some((*ports.start(), *ports.end()));
+        self
+    }
+
+    /// Whether `packet` is an IP packet satisfying every constraint.
+    pub fn matches(&self, packet: &PacketRef) -> bool {
+        self.matches_frame(packet.data())
+    }
+
+    fn matches_frame(&self, frame: &[u8]) -> bool {
+        let Some((eth, payload)) = fluxcapacitor_proto::parse_eth(frame) else {
+            return false;
+        };
+        let (v6, src, dst, ip_proto) = match eth.eth_type() {
+            ETH_P_IP => {
+                let Some((ip, _)) = fluxcapacitor_proto::parse_ipv4(payload) else {
+                    return false;
+                };
+                (false, u32::from(ip.src_addr()) as u128, u32::from(ip.dst_addr()) as u128, Some(ip.proto))
+            }
+            ETH_P_IPV6 => {
+                let Some((ip, _)) = fluxcapacitor_proto::parse_ipv6(payload) else {
+                    return false;
+                };
+                // The upper protocol is only known after the extension headers.
+                (true, u128::from(ip.src_addr()), u128::from(ip.dst_addr()), None)
+            }
+            _ => return false,
+        };
+
+        if self.src_net.is_some_and(|net| !net.contains(v6, src))
+            || self.dst_net.is_some_and(|net| !net.contains(v6, dst))
+        {
+            return false;
+        }
+        if self.proto.is_none() && self.src_port.is_none() && self.dst_port.is_none() {
+            return true;
+        }
+
+        let transport = fluxcapacitor_proto::parse_transport(frame);
+        let (proto, ports) = match transport {
+            Some(Transport::Tcp(tcp, _)) => (Some(6), Some((tcp.src_port(), tcp.dst_port()))),
+            Some(Transport::Udp(udp, _)) => (Some(17), Some((udp.src_port(), udp.dst_port()))),
+            Some(Transport::Icmp(..)) => (Some(if v6 { 58 } else { 1 }), None),
+            Some(Transport::Other(proto)) => (Some(proto), None),
+            Some(Transport::Fragmented) | None => (ip_proto, None),
+        };
+        // IPv4 names the protocol even in fragments and truncated packets.
+        let proto = ip_proto.or(proto);
+
+        let in_range = |range: Option<(u16, u16)>, port: Option<u16>| match range {
+            Some((lo, hi)) => port.is_some_and(|p| lo <= p && p <= hi),
+            None => true,
+        };
+        self.proto.is_none_or(|want| proto == Some(want))
+            && in_range(self.src_port, ports.map(|(src, _)| src))
+            && in_range(self.dst_port, ports.map(|(_, dst)| dst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::udp_frame;
+
+    // Eth + IPv4 + UDP header with the given addresses and ports.
+    fn ipv4_udp(src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16) -> Vec<u8> {
+        udp_frame((src, sport), (dst, dport), &[])
+    }
+
+    #[test]
+    fn test_match_subnet() {
+        let matcher = FlowMatcher::new().src_net("192.168.0.0".parse().unwrap(), 16);
+        assert!(matcher.matches_frame(&ipv4_udp([192, 168, 7, 1], [10, 0, 0, 1], 1000, 53)));
+        assert!(!matcher.matches_frame(&ipv4_udp([192, 169, 0, 1], [10, 0, 0, 1], 1000, 53)));
+
+        // Host bits in the configured address are ignored; /0 matches all of IPv4
+        let matcher = FlowMatcher::new().dst_net("10.1.2.3".parse().unwrap(), 8);
+        assert!(matcher.matches_frame(&ipv4_udp([1, 1, 1, 1], [10, 200, 0, 1], 1000, 53)));
+        let any = FlowMatcher::new().dst_net("0.0.0.0".parse().unwrap(), 0);
+        assert!(any.matches_frame(&ipv4_udp([1, 1, 1, 1], [8, 8, 8, 8], 1000, 53)));
+
+        // Other address family and non-IP frames never match
+        let v6 = FlowMatcher::new().src_net("fd00::".parse().unwrap(), 8);
+        assert!(!v6.matches_frame(&ipv4_udp([192, 168, 7, 1], [10, 0, 0, 1], 1000, 53)));
+        assert!(!FlowMatcher::new().matches_frame(&[0u8; 60]));
+    }
+
+    #[test]
+    fn test_match_port_range() {
+        let matcher = FlowMatcher::new().proto(17).dst_port(5000..=5099);
+        assert!(matcher.matches_frame(&ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 1234, 5000)));
+        assert!(matcher.matches_frame(&ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 1234, 5099)));
+        assert!(!matcher.matches_frame(&ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 1234, 5100)));
+        assert!(!FlowMatcher::new().proto(6).matches_frame(&ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 1234, 5000)));
+
+        // Non-first fragments keep their protocol but have no ports
+        let mut fragment = ipv4_udp([10, 0, 0, 1], [10, 0, 0, 2], 1234, 5000);
+        fragment[20..22].copy_from_slice(&185u16.to_be_bytes());
+        assert!(FlowMatcher::new().proto(17).matches_frame(&fragment));
+        assert!(!matcher.matches_frame(&fragment));
+    }
+}
@@ -1,10 +1,18 @@
 pub mod builder;
 pub mod config;
 pub mod error;
+pub mod observer;
 pub mod packet;
 pub mod engine;
 pub mod system;
 pub mod raw;
+pub mod conntrack;
+pub mod filter;
+pub mod routing;
+pub mod rss;
+
+#[cfg(test)]
+mod test_util;
 
 #[cfg(all(feature = "simulator", not(target_os = "linux")))]
 pub mod simulator;
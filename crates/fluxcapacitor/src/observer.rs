@@ -0,0 +1,17 @@
+/// Hooks for engine diagnostics.
+///
+/// The engine calls these at the relevant points of its hot loop so that events can be
+/// bridged to `tracing`, `log` or a metrics system. Every method has an empty default,
+/// so an implementation only overrides what it cares about. When no observer is
+/// registered the engine skips the calls entirely.
+pub trait FluxObserver: Send {
+    /// A batch was committed: `rx` packets received, `tx` queued for transmit,
+    /// `dropped` returned to the Fill Ring.
+    fn on_batch(&mut self, _rx: usize, _tx: usize, _dropped: usize) {}
+
+    /// The kernel has no buffers left on the Fill Ring, so incoming packets will be dropped.
+    fn on_fill_empty(&mut self) {}
+
+    /// The TX Ring had no room; packets marked for transmit were dropped instead.
+    fn on_tx_full(&mut self) {}
+}
@@ -0,0 +1,151 @@
+use crate::packet::Packet;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::{ptr, slice};
+
+/// Up to `N` received packets stored inline, from `FluxRx::recv_array`.
+///
+/// Dereferences to `[Packet]` and iterates by value, like a `Vec<Packet>`, without
+/// allocating: the packets live wherever the array does (usually the stack, or the
+/// future of an async task). Costs `N * size_of::<Packet>()` bytes whether full or not.
+pub struct PacketArray<const N: usize> {
+    packets: [MaybeUninit<Packet>; N],
+    len: usize,
+}
+
+impl<const N: usize> PacketArray<N> {
+    pub(crate) fn new() -> Self {
+        Self { packets: [const { MaybeUninit::uninit() }; N], len: 0 }
+    }
+
+    // Callers check `len < N` first.
+    pub(crate) fn push(&mut self, packet: Packet) {
+        self.packets[self.len].write(packet);
+        self.len += 1;
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Remove and return the last packet.
+    pub fn pop(&mut self) -> Option<Packet> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { self.packets[self.len].assume_init_read() })
+    }
+}
+
+impl<const N: usize> Deref for PacketArray<N> {
+    type Target = [Packet];
+
+    fn deref(&self) -> &[Packet] {
+        unsafe { slice::from_raw_parts(self.packets.as_ptr() as *const Packet, self.len) }
+    }
+}
+
+impl<const N: usize> DerefMut for PacketArray<N> {
+    fn deref_mut(&mut self) -> &mut [Packet] {
+        unsafe { slice::from_raw_parts_mut(self.packets.as_mut_ptr() as *mut Packet, self.len) }
+    }
+}
+
+impl<const N: usize> Drop for PacketArray<N> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.deref_mut() as *mut [Packet]) };
+    }
+}
+
+impl<const N: usize> IntoIterator for PacketArray<N> {
+    type Item = Packet;
+    type IntoIter = IntoIter<N>;
+
+    fn into_iter(self) -> IntoIter<N> {
+        let array = ManuallyDrop::new(self);
+        // Ownership of the initialized packets moves to the iterator.
+        let packets = unsafe { ptr::read(&array.packets) };
+        IntoIter { packets, next: 0, len: array.len }
+    }
+}
+
+/// Owning iterator over a `PacketArray`. Drops the packets it did not yield.
+pub struct IntoIter<const N: usize> {
+    packets: [MaybeUninit<Packet>; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Iterator for IntoIter<N> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        if self.next == self.len {
+            return None;
+        }
+        self.next += 1;
+        Some(unsafe { self.packets[self.next - 1].assume_init_read() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.next, Some(self.len - self.next))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for IntoIter<N> {}
+
+impl<const N: usize> Drop for IntoIter<N> {
+    fn drop(&mut self) {
+        for packet in &mut self.packets[self.next..self.len] {
+            unsafe { packet.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::shared::SharedFrameState;
+    use fluxcapacitor_core::umem::layout::UmemLayout;
+    use fluxcapacitor_core::umem::mmap::UmemRegion;
+    use std::sync::Arc;
+
+    fn recycled(state: &SharedFrameState) -> Vec<u64> {
+        std::iter::from_fn(|| state.take()).collect()
+    }
+
+    #[test]
+    fn test_packet_array_drops_what_it_holds() {
+        let umem = Arc::new(UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem"));
+        let state = Arc::new(SharedFrameState::new(4));
+        let fill = |n: u64| {
+            let mut array = PacketArray::<4>::new();
+            for i in 0..n {
+                array.push(Packet::new(i * 2048, 60, umem.clone(), state.clone()));
+            }
+            array
+        };
+
+        // Dropping the array recycles every frame
+        let array = fill(3);
+        assert_eq!(array.iter().map(|p| p.addr()).collect::<Vec<_>>(), [0, 2048, 4096]);
+        drop(array);
+        assert_eq!(recycled(&state).len(), 3);
+
+        // Iterating by value hands packets out; unyielded ones drop with the iterator
+        let mut iter = fill(3).into_iter();
+        let first = iter.next().expect("Packet");
+        assert_eq!(iter.len(), 2);
+        drop(iter);
+        assert_eq!(recycled(&state).len(), 2);
+        drop(first);
+        assert_eq!(recycled(&state), [0]);
+
+        let mut array = fill(2);
+        assert_eq!(array.pop().map(|p| p.addr()), Some(2048));
+        assert_eq!(array.len(), 1);
+        drop(array);
+        assert_eq!(recycled(&state).len(), 2);
+    }
+}
@@ -0,0 +1,57 @@
+//! RX metadata written by the XDP program in front of each frame.
+//!
+//! The kernel exposes NIC-computed hints (RSS hash, hardware RX timestamp, stripped
+//! VLAN tag) to XDP programs through the `bpf_xdp_metadata_rx_*` kfuncs. An XDP
+//! program that calls them can reserve `XdpRxMeta::LEN` bytes with
+//! `bpf_xdp_adjust_meta` and store the results there; AF_XDP then delivers that area
+//! immediately before the packet data, where `PacketRef::rx_meta` reads it.
+//!
+//! Availability is per driver and per hint: e.g. mlx5, ice and veth implement all
+//! three, others only a subset or none (the kfunc then fails and the program should
+//! leave the `valid` bit clear). Fields whose bit is clear are reported as `None`.
+
+/// `XdpRxMeta::valid` bit: `rx_timestamp` is populated.
+pub const RX_META_TIMESTAMP: u32 = 1 << 0;
+/// `XdpRxMeta::valid` bit: `hash` is populated.
+pub const RX_META_HASH: u32 = 1 << 1;
+/// `XdpRxMeta::valid` bit: `vlan_tci` is populated.
+pub const RX_META_VLAN: u32 = 1 << 2;
+
+/// Wire layout of the metadata area, ending right where the packet data begins.
+/// An XDP program must write exactly this (native byte order).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XdpRxMeta {
+    pub rx_timestamp: u64,
+    pub hash: u32,
+    pub vlan_tci: u16,
+    pub vlan_proto: u16,
+    pub valid: u32,
+    pub _reserved: u32,
+}
+
+impl XdpRxMeta {
+    pub const LEN: usize = std::mem::size_of::<XdpRxMeta>();
+}
+
+/// Hints the NIC computed for a received frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RxMeta {
+    /// RSS hash of the flow.
+    pub hash: Option<u32>,
+    /// Hardware RX timestamp in nanoseconds.
+    pub rx_timestamp: Option<u64>,
+    /// VLAN tag control information, if the NIC stripped a tag.
+    pub vlan_tci: Option<u16>,
+}
+
+impl From<XdpRxMeta> for RxMeta {
+    fn from(raw: XdpRxMeta) -> Self {
+        let has = |bit: u32| raw.valid & bit != 0;
+        Self {
+            hash: has(RX_META_HASH).then_some(raw.hash),
+            rx_timestamp: has(RX_META_TIMESTAMP).then_some(raw.rx_timestamp),
+            vlan_tci: has(RX_META_VLAN).then_some(raw.vlan_tci),
+        }
+    }
+}
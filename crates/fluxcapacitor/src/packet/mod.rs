@@ -1,5 +1,10 @@
 pub mod raw;
 pub mod owned;
+pub mod meta;
+pub mod array;
+mod parse;
 
-pub use raw::{PacketRef, Action};
+pub use raw::{PacketRef, Action, L2Class};
+pub use meta::RxMeta;
 pub use owned::Packet;
+pub use array::PacketArray;
@@ -1,14 +1,17 @@
 use fluxcapacitor_core::umem::mmap::UmemRegion;
 use std::slice;
 use std::sync::Arc;
-// Note: In a real implementation, we need a way to return the frame to the Fill Ring on Drop.
-// This usually requires a shared handle to the ProducerRing.
-
-// For now, we will define the struct layout. 
-// Fully implementing Drop requires the System/Engine plumbing to be in place.
-
 use crate::system::shared::SharedFrameState;
+use crate::packet::{parse, L2Class};
+use std::net::SocketAddr;
 
+/// A received frame owned by the application, from `FluxRx::recv`.
+///
+/// Dropping it returns the frame to the shared free pool, from which `FluxRx`
+/// refills the Fill Ring. The packet holds its own `Arc`s to the UMEM and the pool,
+/// so it stays valid after the `FluxRx` (or `FluxTx`) it came from is dropped: the
+/// memory is unmapped only when the last packet goes, and a frame recycled by then
+/// simply stays in the pool.
 pub struct Packet {
     pub(crate) addr: u64,
     pub(crate) len: usize,
@@ -32,6 +35,11 @@ impl Packet {
         }
     }
     
+    /// UMEM address of the frame, as later reported by `FluxTx::on_complete`.
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
     pub fn data(&self) -> &[u8] {
         unsafe {
              let ptr = self.umem.as_ptr().add(self.addr as usize);
@@ -45,6 +53,85 @@ impl Packet {
              slice::from_raw_parts_mut(ptr, self.len)
         }
     }
+
+    /// Unicast, multicast or broadcast, from the destination MAC. See `PacketRef::l2_class`.
+    pub fn l2_class(&self) -> L2Class {
+        parse::l2_class(self.data())
+    }
+
+    // Header parsing helpers, as on `PacketRef`
+    pub fn ethernet(&self) -> Option<&fluxcapacitor_proto::EthHeader> {
+        parse::ethernet(self.data())
+    }
+
+    pub fn ipv4(&self) -> Option<&fluxcapacitor_proto::Ipv4Header> {
+        parse::ipv4(self.data())
+    }
+
+    /// IPv6 header of an untagged IPv6 frame.
+    pub fn ipv6(&self) -> Option<&fluxcapacitor_proto::Ipv6Header> {
+        parse::ipv6(self.data())
+    }
+
+    pub fn udp(&self) -> Option<&fluxcapacitor_proto::UdpHeader> {
+        parse::udp(self.data())
+    }
+
+    pub fn tcp(&self) -> Option<&fluxcapacitor_proto::TcpHeader> {
+        parse::tcp(self.data())
+    }
+
+    pub fn icmp(&self) -> Option<&fluxcapacitor_proto::IcmpHeader> {
+        parse::icmp(self.data())
+    }
+
+    /// Transport header over IPv4 or IPv6, skipping IPv6 extension headers.
+    /// `None` for non-IP frames. See `fluxcapacitor_proto::Transport`.
+    pub fn transport(&self) -> Option<fluxcapacitor_proto::Transport<'_>> {
+        fluxcapacitor_proto::parse_transport(self.data())
+    }
+
+    /// Walk the headers once, calling `visitor` for each layer found.
+    /// See `fluxcapacitor_proto::dissect`.
+    pub fn dissect(&self, visitor: &mut impl fluxcapacitor_proto::Dissector) {
+        fluxcapacitor_proto::dissect(self.data(), visitor)
+    }
+
+    /// `(src, dst)` socket addresses of a TCP or UDP packet over IPv4 or IPv6.
+    /// `None` for other protocols and for non-first fragments.
+    pub fn socket_addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        parse::socket_addrs(self.data())
+    }
+
+    /// Turn the packet into its reply in place, as `PacketRef::make_reply` does.
+    /// Returns `false`, leaving it alone, if it is not IPv4 or IPv6.
+    pub fn make_reply(&mut self) -> bool {
+        parse::make_reply(self.data_mut())
+    }
+
+    /// Bytes after the TCP, UDP or ICMP header, over IPv4 or IPv6.
+    /// `None` for other protocols, fragments and non-IP frames.
+    pub fn payload_l4(&self) -> Option<&[u8]> {
+        let range = self.payload_l4_range()?;
+        Some(&self.data()[range])
+    }
+
+    /// Mutable `payload_l4`. Checksums are left to the caller.
+    pub fn payload_l4_mut(&mut self) -> Option<&mut [u8]> {
+        let range = self.payload_l4_range()?;
+        Some(&mut self.data_mut()[range])
+    }
+
+    fn payload_l4_range(&self) -> Option<std::ops::Range<usize>> {
+        use fluxcapacitor_proto::Transport;
+        let data = self.data();
+        let payload = match fluxcapacitor_proto::parse_transport(data)? {
+            Transport::Tcp(_, p) | Transport::Udp(_, p) | Transport::Icmp(_, p) => p,
+            Transport::Fragmented | Transport::Other(_) => return None,
+        };
+        let start = payload.as_ptr() as usize - data.as_ptr() as usize;
+        Some(start..start + payload.len())
+    }
 }
 
 impl Drop for Packet {
@@ -52,3 +139,72 @@ impl Drop for Packet {
         self.shared_state.recycle(self.addr);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fluxcapacitor_core::umem::layout::UmemLayout;
+
+    #[test]
+    fn test_packet_outlives_its_owners() {
+        let umem = Arc::new(UmemRegion::new(UmemLayout::new(2048, 4)).unwrap());
+        let shared_state = Arc::new(SharedFrameState::new(4));
+        umem.write_frame(2048, b"still here").unwrap();
+
+        let packet = Packet::new(2048, 10, Arc::clone(&umem), Arc::clone(&shared_state));
+        let pool = Arc::downgrade(&shared_state);
+        // What `FluxRx` holds goes away first
+        drop(umem);
+        drop(shared_state);
+
+        assert_eq!(packet.data(), b"still here");
+        drop(packet);
+        // The last packet took the pool (and UMEM) with it
+        assert!(pool.upgrade().is_none());
+    }
+
+    // Eth + IPv4 + UDP 10.0.0.1:1000 -> 10.0.0.2:53 carrying "ping"
+    fn ping_frame() -> Vec<u8> {
+        crate::test_util::udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], 53), b"ping")
+    }
+
+    #[test]
+    fn test_header_helpers() {
+        let frame = ping_frame();
+        let umem = Arc::new(UmemRegion::new(UmemLayout::new(2048, 4)).unwrap());
+        let shared_state = Arc::new(SharedFrameState::new(4));
+        umem.write_frame(0, &frame).unwrap();
+        let packet = Packet::new(0, frame.len(), umem, shared_state);
+
+        assert_eq!(packet.l2_class(), L2Class::Unicast);
+        assert!(packet.ethernet().is_some());
+        assert_eq!(packet.ipv4().map(|ip| ip.protocol()), Some(fluxcapacitor_proto::IpProto::Udp));
+        assert!(packet.ipv6().is_none());
+        assert_eq!(packet.udp().map(|udp| udp.dst_port()), Some(53));
+        assert!(packet.tcp().is_none() && packet.icmp().is_none());
+        assert!(matches!(packet.transport(), Some(fluxcapacitor_proto::Transport::Udp(_, b"ping"))));
+        assert_eq!(
+            packet.socket_addrs(),
+            Some(("10.0.0.1:1000".parse().unwrap(), "10.0.0.2:53".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_payload_l4_udp() {
+        let frame = ping_frame();
+        let umem = Arc::new(UmemRegion::new(UmemLayout::new(2048, 4)).unwrap());
+        let shared_state = Arc::new(SharedFrameState::new(4));
+        umem.write_frame(0, &frame).unwrap();
+        let mut packet = Packet::new(0, frame.len(), umem, shared_state);
+
+        assert_eq!(packet.payload_l4(), Some(&b"ping"[..]));
+        packet.payload_l4_mut().unwrap().copy_from_slice(b"pong");
+        assert_eq!(&packet.data()[42..], b"pong");
+        // Headers are untouched
+        assert_eq!(&packet.data()[..42], &frame[..42]);
+
+        // Not IP: no payload
+        packet.data_mut()[12..14].copy_from_slice(&0x0806u16.to_be_bytes());
+        assert_eq!(packet.payload_l4(), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Header lookups over frame bytes, shared by `PacketRef` and `Packet`.
+
+use crate::packet::L2Class;
+use fluxcapacitor_proto::ethernet::{ETH_P_IP, ETH_P_IPV6};
+use fluxcapacitor_proto::{EthHeader, IcmpHeader, IpProto, Ipv4Header, Ipv6Header, TcpHeader, Transport, UdpHeader};
+use std::net::{IpAddr, SocketAddr};
+
+pub(crate) fn l2_class(data: &[u8]) -> L2Class {
+    if data.len() < 6 {
+        return L2Class::Unicast;
+    }
+    if data[..6] == [0xFF; 6] {
+        L2Class::Broadcast
+    } else if data[0] & 0x01 != 0 {
+        L2Class::Multicast
+    } else {
+        L2Class::Unicast
+    }
+}
+
+pub(crate) fn ethernet(data: &[u8]) -> Option<&EthHeader> {
+    fluxcapacitor_proto::parse_eth(data).map(|(h, _)| h)
+}
+
+pub(crate) fn ipv4(data: &[u8]) -> Option<&Ipv4Header> {
+    let (_, payload) = fluxcapacitor_proto::parse_eth(data)?;
+    fluxcapacitor_proto::parse_ipv4(payload).map(|(h, _)| h)
+}
+
+pub(crate) fn ipv6(data: &[u8]) -> Option<&Ipv6Header> {
+    let (eth, payload) = fluxcapacitor_proto::parse_eth(data)?;
+    if eth.eth_type() != ETH_P_IPV6 {
+        return None;
+    }
+    fluxcapacitor_proto::parse_ipv6(payload).map(|(h, _)| h)
+}
+
+// The IPv4 transport payload, if the protocol is `proto`.
+fn ipv4_l4(data: &[u8], proto: IpProto) -> Option<&[u8]> {
+    let (_, ip_payload) = fluxcapacitor_proto::parse_eth(data)?;
+    let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
+    (ip_header.protocol() == proto).then_some(l4_payload)
+}
+
+pub(crate) fn udp(data: &[u8]) -> Option<&UdpHeader> {
+    fluxcapacitor_proto::parse_udp(ipv4_l4(data, IpProto::Udp)?).map(|(h, _)| h)
+}
+
+pub(crate) fn tcp(data: &[u8]) -> Option<&TcpHeader> {
+    fluxcapacitor_proto::parse_tcp(ipv4_l4(data, IpProto::Tcp)?).map(|(h, _)| h)
+}
+
+pub(crate) fn icmp(data: &[u8]) -> Option<&IcmpHeader> {
+    fluxcapacitor_proto::parse_icmp(ipv4_l4(data, IpProto::Icmp)?).map(|(h, _)| h)
+}
+
+pub(crate) fn socket_addrs(data: &[u8]) -> Option<(SocketAddr, SocketAddr)> {
+    let (eth, ip_payload) = fluxcapacitor_proto::parse_eth(data)?;
+    let (src, dst): (IpAddr, IpAddr) = match eth.eth_type() {
+        ETH_P_IP => {
+            let (ip, _) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
+            (ip.src_addr().into(), ip.dst_addr().into())
+        }
+        ETH_P_IPV6 => {
+            let (ip, _) = fluxcapacitor_proto::parse_ipv6(ip_payload)?;
+            (ip.src_addr().into(), ip.dst_addr().into())
+        }
+        _ => return None,
+    };
+    let (src_port, dst_port) = match fluxcapacitor_proto::parse_transport(data)? {
+        Transport::Tcp(tcp, _) => (tcp.src_port(), tcp.dst_port()),
+        Transport::Udp(udp, _) => (udp.src_port(), udp.dst_port()),
+        _ => return None,
+    };
+    Some((SocketAddr::new(src, src_port), SocketAddr::new(dst, dst_port)))
+}
+
+/// See `PacketRef::make_reply`.
+pub(crate) fn make_reply(data: &mut [u8]) -> bool {
+    let Some((eth, ip_payload)) = fluxcapacitor_proto::parse_eth(data) else {
+        return false;
+    };
+    let l3 = data.len() - ip_payload.len();
+    // (L3 address offset, address length, L4 protocol and offset if present)
+    let (addrs, addr_len, l4) = match eth.eth_type() {
+        ETH_P_IP => {
+            let Some((ip, l4_payload)) = fluxcapacitor_proto::parse_ipv4(ip_payload) else {
+                return false;
+            };
+            // Non-first fragments carry no L4 header
+            let first = u16::from_be(ip.frag_off) & 0x1FFF == 0;
+            (l3 + 12, 4, first.then(|| (ip.protocol(), data.len() - l4_payload.len())))
+        }
+        ETH_P_IPV6 => {
+            let Some((ip, rest)) = fluxcapacitor_proto::parse_ipv6(ip_payload) else {
+                return false;
+            };
+            let l4 = match fluxcapacitor_proto::ipv6::skip_extension_headers(ip.next_header, rest) {
+                Some(fluxcapacitor_proto::ipv6::Ipv6Upper::Proto(proto, l4_payload)) => {
+                    Some((IpProto::from(proto), data.len() - l4_payload.len()))
+                }
+                _ => None,
+            };
+            (l3 + 8, 16, l4)
+        }
+        _ => return false,
+    };
+    let ipv4 = addr_len == 4;
+
+    swap_fields(data, 0, 6, 6);
+    swap_fields(data, addrs, addrs + addr_len, addr_len);
+    if ipv4 {
+        let header_len = ((data[l3] & 0x0F) as usize) * 4;
+        data[l3 + 10..l3 + 12].fill(0);
+        let check = fluxcapacitor_proto::checksum(&data[l3..l3 + header_len]);
+        data[l3 + 10..l3 + 12].copy_from_slice(&check.to_be_bytes());
+    }
+
+    // Swapping addresses and ports leaves the pseudo-header sums unchanged,
+    // so only the ICMP type change needs a checksum fix.
+    match l4 {
+        Some((IpProto::Tcp | IpProto::Udp, off)) if data.len() >= off + 4 => swap_fields(data, off, off + 2, 2),
+        Some((proto @ (IpProto::Icmp | IpProto::Icmpv6), off)) if data.len() >= off + 4 => {
+            let (request, reply) = if proto == IpProto::Icmp { (8, 0) } else { (128, 129) };
+            if data[off] == request {
+                let old = u16::from_be_bytes([request, data[off + 1]]);
+                let new = u16::from_be_bytes([reply, data[off + 1]]);
+                let check = u16::from_be_bytes([data[off + 2], data[off + 3]]);
+                data[off] = reply;
+                data[off + 2..off + 4].copy_from_slice(&fluxcapacitor_proto::checksum_adjust(check, old, new).to_be_bytes());
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+// Swap `data[a..a + len]` with `data[b..b + len]`, where `a + len <= b`.
+fn swap_fields(data: &mut [u8], a: usize, b: usize, len: usize) {
+    let (head, tail) = data.split_at_mut(b);
+    head[a..a + len].swap_with_slice(&mut tail[..len]);
+}
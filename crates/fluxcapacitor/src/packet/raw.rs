@@ -1,5 +1,8 @@
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 use std::slice;
+use crate::packet::meta::{RxMeta, XdpRxMeta};
+use crate::packet::parse;
 
 /// A zero-copy view into a packet existing in UMEM.
 /// 
@@ -9,15 +12,49 @@ use std::slice;
 pub struct PacketRef<'a> {
     ptr: *mut u8,
     len: usize,
+    // Bytes from `ptr` to the end of the usable frame; the upper bound for `len`.
+    room: usize,
     addr: u64,
     _marker: PhantomData<&'a mut [u8]>,
     action: &'a mut Action,
+    zerocopy: bool,
+    // Metadata area in front of the original data start, if present.
+    meta: *const XdpRxMeta,
+    // UMEM headroom at the start of the chunk, if configured.
+    scratch: *mut u8,
+    scratch_len: usize,
 }
 
+/// Verdict for a received frame, applied when the batch is committed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
+    /// Discard the packet and recycle its frame to the Fill Ring.
     Drop,
+    /// Transmit the (possibly modified) frame on the TX Ring.
     Tx,
+    /// Leave the packet to the kernel stack.
+    ///
+    /// Once a frame is in UMEM it can no longer be handed back to the kernel, so the
+    /// engine currently recycles it like `Drop`, counting it as `passed` rather than
+    /// `dropped` in `EngineStats`. It records intent and is reserved
+    /// for an in-kernel verdict path; use `redirect_ports` to keep traffic in the
+    /// kernel today.
+    Pass,
+    /// Keep the frame out of both rings until `FluxEngine::release_held`.
+    ///
+    /// Frames over the engine's hold limit (`FluxEngine::set_max_held`) are dropped.
+    Hold,
+}
+
+/// Layer 2 delivery class of a frame, derived from its destination MAC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum L2Class {
+    /// Individual address (I/G bit clear).
+    Unicast,
+    /// Group address (I/G bit set), e.g. `01:00:5e:..` for IPv4 multicast.
+    Multicast,
+    /// `ff:ff:ff:ff:ff:ff`.
+    Broadcast,
 }
 
 #[allow(dead_code)]
@@ -29,10 +66,92 @@ impl<'a> PacketRef<'a> {
         Self {
             ptr,
             len,
+            room: len,
             addr,
             _marker: PhantomData,
             action, 
+            zerocopy: false,
+            meta: std::ptr::null(),
+            scratch: std::ptr::null_mut(),
+            scratch_len: 0,
+        }
+    }
+
+    /// Expose `len` bytes at `scratch` through `metadata`/`metadata_mut`.
+    ///
+    /// # Safety
+    /// That area must be the headroom of this packet's UMEM frame.
+    pub(crate) unsafe fn with_scratch(mut self, scratch: *mut u8, len: usize) -> Self {
+        self.scratch = scratch;
+        self.scratch_len = len;
+        self
+    }
+
+    /// Allow the packet to grow to `room` bytes from its current start.
+    ///
+    /// # Safety
+    /// Those bytes must lie within the same UMEM frame.
+    pub(crate) unsafe fn with_room(mut self, room: usize) -> Self {
+        self.room = room.max(self.len);
+        self
+    }
+
+    /// Mark the `XdpRxMeta::LEN` bytes in front of the data as RX metadata.
+    ///
+    /// # Safety
+    /// That area must lie within the same UMEM frame.
+    pub(crate) unsafe fn with_rx_meta(mut self, present: bool) -> Self {
+        if present {
+            self.meta = self.ptr.sub(XdpRxMeta::LEN) as *const XdpRxMeta;
+        }
+        self
+    }
+
+    /// NIC hints delivered with this frame, or `None` if RX metadata was not enabled
+    /// with `FluxBuilder::rx_metadata`. Individual fields the driver did not supply
+    /// are `None`. See `packet::meta` for the layout contract.
+    #[inline]
+    pub fn rx_meta(&self) -> Option<RxMeta> {
+        if self.meta.is_null() {
+            return None;
+        }
+        // The area is not necessarily 8-byte aligned.
+        let raw = unsafe { std::ptr::read_unaligned(self.meta) };
+        Some(raw.into())
+    }
+
+    pub(crate) fn with_zerocopy(mut self, zerocopy: bool) -> Self {
+        self.zerocopy = zerocopy;
+        self
+    }
+
+    /// Whether this frame was delivered zero-copy. Constant for a given socket.
+    #[inline]
+    pub fn is_zerocopy(&self) -> bool {
+        self.zerocopy
+    }
+
+    /// Per-frame scratch area for annotations passed between pipeline stages,
+    /// e.g. a classification result. Empty unless `FluxBuilder::headroom` is set.
+    ///
+    /// It is the UMEM headroom in front of the frame, so it is never transmitted
+    /// and survives as long as the frame is not handed back to the kernel (a held
+    /// frame, an owned `Packet`). The kernel does not clear it between packets.
+    #[inline]
+    pub fn metadata(&self) -> &[u8] {
+        if self.scratch_len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.scratch, self.scratch_len) }
+    }
+
+    /// Writable view of `metadata`.
+    #[inline]
+    pub fn metadata_mut(&mut self) -> &mut [u8] {
+        if self.scratch_len == 0 {
+            return &mut [];
         }
+        unsafe { slice::from_raw_parts_mut(self.scratch, self.scratch_len) }
     }
 
     #[inline(always)]
@@ -50,10 +169,24 @@ impl<'a> PacketRef<'a> {
         self.len
     }
 
+    /// Largest length `set_len` accepts: the bytes from the packet start to the
+    /// end of its frame.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.room
+    }
+
+    /// Resize the packet, e.g. to append a trailer or truncate it.
+    ///
+    /// Returns `false`, leaving the length unchanged, if `len` exceeds `capacity()`.
     #[inline]
-    pub fn set_len(&mut self, len: usize) {
-        // TODO: Validate against frame size
+    #[must_use]
+    pub fn set_len(&mut self, len: usize) -> bool {
+        if len > self.room {
+            return false;
+        }
         self.len = len;
+        true
     }
 
     /// Move the start of the packet buffer by `offset` bytes.
@@ -66,6 +199,7 @@ impl<'a> PacketRef<'a> {
              if u_off <= self.len {
                  unsafe { self.ptr = self.ptr.add(u_off) };
                  self.len -= u_off;
+                 self.room -= u_off;
              } else {
                  self.len = 0;
              }
@@ -73,6 +207,7 @@ impl<'a> PacketRef<'a> {
              let u_off = (-offset) as usize;
              unsafe { self.ptr = self.ptr.sub(u_off) };
              self.len += u_off;
+             self.room += u_off;
         }
     }
 
@@ -85,6 +220,19 @@ impl<'a> PacketRef<'a> {
     pub fn drop(&mut self) {
         *self.action = Action::Drop;
     }
+
+    /// Mark the packet as belonging to the kernel stack. See `Action::Pass`.
+    #[inline]
+    pub fn pass(&mut self) {
+        *self.action = Action::Pass;
+    }
+
+    /// Hold the frame in UMEM after the batch, e.g. to copy a sample out later.
+    /// See `Action::Hold`.
+    #[inline]
+    pub fn retain(&mut self) {
+        *self.action = Action::Hold;
+    }
     
     // Internal accessors for the engine
     pub(crate) fn action(&self) -> Action {
@@ -95,47 +243,389 @@ impl<'a> PacketRef<'a> {
         self.addr
     }
     
+    /// Classify the frame as unicast, multicast or broadcast.
+    ///
+    /// Only looks at the destination MAC (first 6 bytes), so it is cheaper than
+    /// `ethernet()` when deciding flood-vs-forward. Runt frames shorter than a MAC
+    /// address are reported as `Unicast`.
+    #[inline]
+    pub fn l2_class(&self) -> L2Class {
+        parse::l2_class(self.data())
+    }
+
     // Header parsing helpers
     pub fn ethernet(&self) -> Option<&fluxcapacitor_proto::EthHeader> {
-        fluxcapacitor_proto::parse_eth(self.data()).map(|(h, _)| h)
+        parse::ethernet(self.data())
     }
     
     pub fn ipv4(&self) -> Option<&fluxcapacitor_proto::Ipv4Header> {
-        let (_, payload) = fluxcapacitor_proto::parse_eth(self.data())?;
-        fluxcapacitor_proto::parse_ipv4(payload).map(|(h, _)| h)
+        parse::ipv4(self.data())
+    }
+
+    /// IPv6 header of an untagged IPv6 frame.
+    pub fn ipv6(&self) -> Option<&fluxcapacitor_proto::Ipv6Header> {
+        parse::ipv6(self.data())
     }
 
     pub fn udp(&self) -> Option<&fluxcapacitor_proto::UdpHeader> {
-        let (_, ip_payload) = fluxcapacitor_proto::parse_eth(self.data())?;
-        let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
-        
-        if ip_header.proto != 17 { // UDP
-            return None;
-        }
-        
-        fluxcapacitor_proto::parse_udp(l4_payload).map(|(h, _)| h)
+        parse::udp(self.data())
     }
 
     pub fn tcp(&self) -> Option<&fluxcapacitor_proto::TcpHeader> {
-        let (_, ip_payload) = fluxcapacitor_proto::parse_eth(self.data())?;
-        let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
-        
-        if ip_header.proto != 6 { // TCP
-            return None;
-        }
-        
-        fluxcapacitor_proto::parse_tcp(l4_payload).map(|(h, _)| h)
+        parse::tcp(self.data())
+    }
+
+    /// Transport header over IPv4 or IPv6, skipping IPv6 extension headers.
+    /// `None` for non-IP frames. See `fluxcapacitor_proto::Transport`.
+    pub fn transport(&self) -> Option<fluxcapacitor_proto::Transport<'_>> {
+        fluxcapacitor_proto::parse_transport(self.data())
     }
 
     pub fn icmp(&self) -> Option<&fluxcapacitor_proto::IcmpHeader> {
-        let (_, ip_payload) = fluxcapacitor_proto::parse_eth(self.data())?;
-        let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
-        
-        if ip_header.proto != 1 { // ICMP
-            return None;
+        parse::icmp(self.data())
+    }
+
+    /// Rewrite the priority bits of the outer VLAN tag in place (QoS remarking),
+    /// leaving the VID and DEI alone. Returns `false` if the frame is untagged.
+    pub fn remark_vlan_pcp(&mut self, pcp: u8) -> bool {
+        let Some((eth, rest)) = fluxcapacitor_proto::parse_eth(self.data()) else {
+            return false;
+        };
+        if !fluxcapacitor_proto::vlan::is_vlan(eth.eth_type()) {
+            return false;
+        }
+        let Some((tag, _)) = fluxcapacitor_proto::parse_vlan(rest) else {
+            return false;
+        };
+        let off = self.data().len() - rest.len();
+        let mut tag = *tag;
+        tag.set_pcp(pcp);
+        let tci = tag.tci;
+        self.data_mut()[off..off + 2].copy_from_slice(&tci.to_ne_bytes());
+        true
+    }
+
+    /// Remove the options of an IPv4 packet in place and shorten the packet to match
+    /// (see `fluxcapacitor_proto::ipv4::strip_options`). The start of the frame does
+    /// not move, and any bytes past the new end are only trailing padding to a
+    /// receiver, which goes by the IP total length. Returns `false`, leaving the
+    /// packet alone, if it is not IPv4.
+    pub fn strip_ipv4_options(&mut self) -> bool {
+        use fluxcapacitor_proto::ethernet::ETH_P_IP;
+
+        let Some((eth, ip_payload)) = fluxcapacitor_proto::parse_eth(self.data()) else {
+            return false;
+        };
+        if eth.eth_type() != ETH_P_IP {
+            return false;
         }
-        
-        fluxcapacitor_proto::parse_icmp(l4_payload).map(|(h, _)| h)
+        let l3 = self.data().len() - ip_payload.len();
+        let Some(removed) = fluxcapacitor_proto::ipv4::strip_options(&mut self.data_mut()[l3..]) else {
+            return false;
+        };
+        self.len -= removed;
+        true
+    }
+
+    /// Walk the headers once, calling `visitor` for each layer found.
+    /// See `fluxcapacitor_proto::dissect`.
+    pub fn dissect(&self, visitor: &mut impl fluxcapacitor_proto::Dissector) {
+        fluxcapacitor_proto::dissect(self.data(), visitor)
+    }
+
+    /// `(src, dst)` socket addresses of a TCP or UDP packet over IPv4 or IPv6.
+    /// `None` for other protocols and for non-first fragments.
+    pub fn socket_addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        parse::socket_addrs(self.data())
+    }
+
+    /// Turn a received IPv4 or IPv6 packet into its reply, in place: swaps the
+    /// Ethernet addresses, the IP addresses and the TCP/UDP ports, and turns an
+    /// ICMP or ICMPv6 echo request into an echo reply. Checksums are kept valid,
+    /// so the packet is ready to `send()`.
+    ///
+    /// The payload is left alone (TCP replies need more than this). Returns
+    /// `false`, without touching the packet, if it is not IPv4 or IPv6.
+    pub fn make_reply(&mut self) -> bool {
+        parse::make_reply(self.data_mut())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(dst: [u8; 6]) -> L2Class {
+        let mut frame = [0u8; 14];
+        frame[0..6].copy_from_slice(&dst);
+        let mut action = Action::Drop;
+        let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        packet.l2_class()
+    }
+
+    #[test]
+    fn test_l2_class() {
+        assert_eq!(classify([0xFF; 6]), L2Class::Broadcast);
+        assert_eq!(classify([0x01, 0x00, 0x5E, 0x00, 0x00, 0xFB]), L2Class::Multicast);
+        assert_eq!(classify([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]), L2Class::Unicast);
+    }
+
+    #[test]
+    fn test_verdict_setters() {
+        let mut frame = [0u8; 14];
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+
+        packet.send();
+        assert_eq!(packet.action(), Action::Tx);
+        packet.pass();
+        assert_eq!(packet.action(), Action::Pass);
+        packet.retain();
+        assert_eq!(packet.action(), Action::Hold);
+        packet.drop();
+        assert_eq!(packet.action(), Action::Drop);
+    }
+
+    #[test]
+    fn test_set_len_bounded_by_frame() {
+        let mut frame = [0u8; 64];
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr().add(8), 20, 8, &mut action).with_room(56) };
+        assert_eq!(packet.capacity(), 56);
+
+        assert!(packet.set_len(56));
+        assert!(!packet.set_len(57));
+        assert_eq!(packet.len(), 56);
+        assert!(packet.set_len(20));
+
+        // Pushing a header gains room in front, stripping one gives it up
+        packet.adjust_head(-8);
+        assert_eq!(packet.capacity(), 64);
+        packet.adjust_head(14);
+        assert_eq!(packet.capacity(), 50);
+        assert!(!packet.set_len(51));
+    }
+
+    // Ethernet + IPv4 10.0.0.1 -> 10.0.0.2 + an L4 header of `proto` with ports 1234 -> 80.
+    fn ipv4_frame(proto: u8) -> Vec<u8> {
+        let mut frame = vec![0u8; 14 + 20 + 20];
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        frame[14] = 0x45;
+        frame[14 + 9] = proto;
+        frame[14 + 12..14 + 16].copy_from_slice(&[10, 0, 0, 1]);
+        frame[14 + 16..14 + 20].copy_from_slice(&[10, 0, 0, 2]);
+        frame[34..36].copy_from_slice(&1234u16.to_be_bytes());
+        frame[36..38].copy_from_slice(&80u16.to_be_bytes());
+        frame[34 + 12] = 0x50; // TCP data offset 5
+        frame
+    }
+
+    #[test]
+    fn test_socket_addrs() {
+        let expected: (SocketAddr, SocketAddr) = ("10.0.0.1:1234".parse().unwrap(), "10.0.0.2:80".parse().unwrap());
+        for proto in [6, 17] {
+            let mut frame = ipv4_frame(proto);
+            let mut action = Action::Drop;
+            let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+            assert_eq!(packet.socket_addrs(), Some(expected), "protocol {}", proto);
+        }
+
+        // ICMP has no ports
+        let mut frame = ipv4_frame(1);
+        let mut action = Action::Drop;
+        let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        assert_eq!(packet.socket_addrs(), None);
+    }
+
+    #[test]
+    fn test_make_reply_udp_echo() {
+        use fluxcapacitor_proto::Checksum;
+
+        let mut frame = ipv4_frame(17);
+        frame[0..6].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x02]);
+        frame[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        frame[16..18].copy_from_slice(&40u16.to_be_bytes());
+        frame[38..40].copy_from_slice(&20u16.to_be_bytes()); // UDP length: 12 payload bytes
+        frame[42..].copy_from_slice(b"hello world!");
+        let (ip, l4) = fluxcapacitor_proto::parse_ipv4(&frame[14..]).unwrap();
+        let ip_check = ip.compute(());
+        let (udp, payload) = fluxcapacitor_proto::parse_udp(l4).unwrap();
+        let udp_check = udp.compute((ip, payload));
+        frame[24..26].copy_from_slice(&ip_check.to_be_bytes());
+        frame[40..42].copy_from_slice(&udp_check.to_be_bytes());
+
+        let mut action = Action::Drop;
+        {
+            let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+            assert!(packet.make_reply());
+            packet.send();
+            let (src, dst) = packet.socket_addrs().unwrap();
+            assert_eq!((src, dst), ("10.0.0.2:80".parse().unwrap(), "10.0.0.1:1234".parse().unwrap()));
+            assert_eq!(packet.ethernet().unwrap().src, [0x02, 0, 0, 0, 0, 0x02]);
+            assert!(packet.ipv4().unwrap().is_valid());
+        }
+        assert_eq!(action, Action::Tx);
+
+        let (ip, l4) = fluxcapacitor_proto::parse_ipv4(&frame[14..]).unwrap();
+        let (udp, payload) = fluxcapacitor_proto::parse_udp(l4).unwrap();
+        assert!(udp.verify_checksum(ip, payload));
+        assert_eq!(payload, b"hello world!");
+    }
+
+    #[test]
+    fn test_make_reply_icmp_echo() {
+        let mut frame = ipv4_frame(1);
+        frame[34..38].copy_from_slice(&[8, 0, 0, 0]); // Echo request
+        frame[38..42].copy_from_slice(&[0x12, 0x34, 0x00, 0x01]); // id, sequence
+        let check = fluxcapacitor_proto::checksum(&frame[34..]);
+        frame[36..38].copy_from_slice(&check.to_be_bytes());
+
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        assert!(packet.make_reply());
+        let ip = packet.ipv4().unwrap();
+        assert_eq!((ip.src_addr(), ip.dst_addr()), ([10, 0, 0, 2].into(), [10, 0, 0, 1].into()));
+        assert_eq!(packet.icmp().unwrap().kind, 0);
+        assert_eq!(fluxcapacitor_proto::checksum(&packet.data()[34..]), 0);
+        assert_eq!(&packet.data()[38..42], &[0x12, 0x34, 0x00, 0x01]);
+
+        // Not IP: untouched
+        let mut arp = vec![0xAAu8; 42];
+        arp[12..14].copy_from_slice(&0x0806u16.to_be_bytes());
+        let before = arp.clone();
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(arp.as_mut_ptr(), arp.len(), 0, &mut action) };
+        assert!(!packet.make_reply());
+        assert_eq!(packet.data(), &before[..]);
+    }
+
+    #[test]
+    fn test_strip_ipv4_options() {
+        use fluxcapacitor_proto::Checksum;
+
+        // Eth + IPv4 with a Router Alert option (IHL 6) + UDP with 4 payload bytes
+        let mut frame = [0u8; 14 + 24 + 12];
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        frame[14] = 0x46;
+        frame[16..18].copy_from_slice(&36u16.to_be_bytes());
+        frame[22] = 64;
+        frame[23] = 17;
+        frame[26..30].copy_from_slice(&[10, 0, 0, 1]);
+        frame[30..34].copy_from_slice(&[10, 0, 0, 2]);
+        frame[34..38].copy_from_slice(&[0x94, 0x04, 0x00, 0x00]);
+        frame[38..40].copy_from_slice(&1234u16.to_be_bytes());
+        frame[40..42].copy_from_slice(&5678u16.to_be_bytes());
+        frame[42..44].copy_from_slice(&12u16.to_be_bytes());
+        frame[46..50].copy_from_slice(b"data");
+        let check = fluxcapacitor_proto::checksum(&frame[14..38]);
+        frame[24..26].copy_from_slice(&check.to_be_bytes());
+        let (ip, l4) = fluxcapacitor_proto::parse_ipv4(&frame[14..]).unwrap();
+        let (udp, payload) = fluxcapacitor_proto::parse_udp(l4).unwrap();
+        let udp_check = udp.compute((ip, payload));
+        frame[44..46].copy_from_slice(&udp_check.to_be_bytes());
+
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        assert!(packet.strip_ipv4_options());
+        assert_eq!(packet.len(), 46);
+
+        let ip = packet.ipv4().unwrap();
+        assert_eq!((ip.ihl(), ip.total_len()), (5, 32));
+        assert!(ip.is_valid());
+        let (ip, l4) = fluxcapacitor_proto::parse_ipv4(&packet.data()[14..]).unwrap();
+        let (udp, payload) = fluxcapacitor_proto::parse_udp(l4).unwrap();
+        assert_eq!(udp.dst_port(), 5678);
+        assert_eq!(payload, b"data");
+        assert!(udp.verify_checksum(ip, payload));
+    }
+
+    #[test]
+    fn test_remark_vlan_pcp() {
+        use fluxcapacitor_proto::vlan::ETH_P_8021Q;
+
+        // PCP 1, DEI set, VID 100, carrying IPv4
+        let mut frame = [0u8; 22];
+        frame[12..14].copy_from_slice(&ETH_P_8021Q.to_be_bytes());
+        frame[14..16].copy_from_slice(&0x3064u16.to_be_bytes());
+        frame[16..18].copy_from_slice(&0x0800u16.to_be_bytes());
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        assert!(packet.remark_vlan_pcp(6));
+
+        let (tag, _) = fluxcapacitor_proto::parse_vlan(&packet.data()[14..]).unwrap();
+        assert_eq!((tag.pcp(), tag.vid(), tag.dei()), (6, 100, true));
+        assert_eq!(tag.eth_type(), 0x0800);
+
+        // Untagged: untouched
+        let mut frame = [0u8; 22];
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        let before = frame;
+        let mut action = Action::Drop;
+        let mut packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        assert!(!packet.remark_vlan_pcp(6));
+        assert_eq!(packet.data(), &before[..]);
+    }
+
+    #[test]
+    fn test_dissect_udp_stack() {
+        use fluxcapacitor_proto::{Dissector, EthHeader, Ipv4Header, UdpHeader};
+
+        #[derive(Default)]
+        struct Layers(Vec<&'static str>);
+        impl Dissector for Layers {
+            fn on_ethernet(&mut self, _eth: &EthHeader) { self.0.push("eth"); }
+            fn on_ipv4(&mut self, _ip: &Ipv4Header) { self.0.push("ipv4"); }
+            fn on_udp(&mut self, _udp: &UdpHeader, _payload: &[u8]) { self.0.push("udp"); }
+        }
+
+        let mut frame = ipv4_frame(17);
+        let mut action = Action::Drop;
+        let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        let mut layers = Layers::default();
+        packet.dissect(&mut layers);
+        assert_eq!(layers.0, ["eth", "ipv4", "udp"]);
+    }
+
+    #[test]
+    fn test_socket_addrs_ipv6_udp() {
+        let mut frame = vec![0u8; 14 + 40 + 8];
+        frame[12..14].copy_from_slice(&0x86DDu16.to_be_bytes());
+        frame[14] = 0x60;
+        frame[14 + 6] = 17;
+        frame[14 + 8..14 + 24].copy_from_slice(&"2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        frame[14 + 24..14 + 40].copy_from_slice(&"2001:db8::2".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        frame[54..56].copy_from_slice(&5353u16.to_be_bytes());
+        frame[56..58].copy_from_slice(&53u16.to_be_bytes());
+
+        let mut action = Action::Drop;
+        let packet = unsafe { PacketRef::new(frame.as_mut_ptr(), frame.len(), 0, &mut action) };
+        let (src, dst) = packet.socket_addrs().expect("UDP over IPv6");
+        assert_eq!(src, "[2001:db8::1]:5353".parse().unwrap());
+        assert_eq!(dst, "[2001:db8::2]:53".parse().unwrap());
+    }
+
+    #[test]
+    fn test_rx_meta() {
+        use crate::packet::meta::{RX_META_HASH, RX_META_VLAN};
+
+        let raw = XdpRxMeta {
+            hash: 0xDEAD_BEEF,
+            vlan_tci: 100,
+            valid: RX_META_HASH | RX_META_VLAN,
+            ..Default::default()
+        };
+        let mut frame = vec![0u8; XdpRxMeta::LEN + 14];
+        unsafe { std::ptr::write_unaligned(frame.as_mut_ptr() as *mut XdpRxMeta, raw) };
+
+        let mut action = Action::Drop;
+        let data = unsafe { frame.as_mut_ptr().add(XdpRxMeta::LEN) };
+        let packet = unsafe { PacketRef::new(data, 14, XdpRxMeta::LEN as u64, &mut action) };
+        assert_eq!(packet.rx_meta(), None);
+
+        let packet = unsafe { packet.with_rx_meta(true) };
+        let meta = packet.rx_meta().expect("metadata enabled");
+        assert_eq!(meta.hash, Some(0xDEAD_BEEF));
+        assert_eq!(meta.vlan_tci, Some(100));
+        assert_eq!(meta.rx_timestamp, None);
+    }
+}
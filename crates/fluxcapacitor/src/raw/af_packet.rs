@@ -0,0 +1,126 @@
+//! AF_PACKET backend: drives the XDP-style rings from userspace.
+//!
+//! The four rings and the UMEM live in ordinary memory. The pump plays the kernel's
+//! part, moving frames between them and a TPACKET_V3 socket, so `FluxRaw` and
+//! `FluxEngine` work unchanged on top of it.
+
+use fluxcapacitor_core::ring::{ConsumerRing, ProducerRing, XDPDesc};
+use fluxcapacitor_core::sys::mmap::MmapArea;
+use fluxcapacitor_core::sys::packet::PacketSocket;
+use fluxcapacitor_core::umem::mmap::UmemRegion;
+use std::io;
+
+use crate::raw::FluxRaw;
+
+// Producer and consumer indices on separate cache lines, descriptors after them.
+const PRODUCER_OFF: usize = 0;
+const CONSUMER_OFF: usize = 64;
+const DESC_OFF: usize = 128;
+
+pub(crate) struct AfPacketPump {
+    socket: PacketSocket,
+    umem: *mut u8,
+    frame_size: u32,
+    // Kernel-side views of the userspace rings.
+    fill: ConsumerRing<u64>,
+    rx: ProducerRing<XDPDesc>,
+    tx: ConsumerRing<XDPDesc>,
+    comp: ProducerRing<u64>,
+    // Frames completed without being sent because `send` failed.
+    tx_errors: u64,
+}
+
+unsafe impl Send for AfPacketPump {}
+
+impl AfPacketPump {
+    /// Move received frames into Fill Ring buffers and publish them on the RX Ring.
+    /// Frames arriving with no Fill buffer or no RX slot are dropped, as on a NIC.
+    pub(crate) fn pump_rx(&mut self) -> usize {
+        let Self { socket, umem, frame_size, fill, rx, .. } = self;
+        let mut delivered = 0;
+        socket.recv(|data| {
+            let Some(idx) = rx.reserve(1) else { return };
+            if fill.peek(1) == 0 {
+                return;
+            }
+            let addr = unsafe { fill.read_at(fill.consumer_idx()) };
+            fill.release(1);
+
+            let len = data.len().min(*frame_size as usize);
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), umem.add(addr as usize), len);
+                rx.write_at(idx, XDPDesc { addr, len: len as u32, options: 0 });
+            }
+            rx.submit(idx.wrapping_add(1));
+            delivered += 1;
+        });
+        delivered
+    }
+
+    /// Send every queued TX descriptor and post its frame to the Completion Ring.
+    pub(crate) fn pump_tx(&mut self) -> io::Result<usize> {
+        let pending = self.tx.peek(self.tx.len());
+        let mut sent = 0;
+        for i in 0..pending {
+            let Some(comp_idx) = self.comp.reserve(1) else { break };
+            let desc = unsafe { self.tx.read_at(self.tx.consumer_idx() + i as u32) };
+            let frame = unsafe { std::slice::from_raw_parts(self.umem.add(desc.addr as usize), desc.len as usize) };
+
+            match self.socket.send(frame) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // Like a NIC, a frame that cannot be sent is still completed.
+                Err(_) => self.tx_errors += 1,
+                Ok(()) => {}
+            }
+            unsafe { self.comp.write_at(comp_idx, desc.addr) };
+            self.comp.submit(comp_idx.wrapping_add(1));
+            sent += 1;
+        }
+        self.tx.release(sent as u32);
+        Ok(sent)
+    }
+
+    pub(crate) fn tx_errors(&self) -> u64 {
+        self.tx_errors
+    }
+}
+
+/// Build a `FluxRaw` whose rings are serviced by an AF_PACKET socket.
+pub(crate) fn build(umem: UmemRegion, ifindex: u32, ring_size: u32) -> io::Result<FluxRaw> {
+    let frame_size = umem.layout().frame_size;
+    let socket = PacketSocket::open(ifindex, frame_size)?;
+    let fd = socket.fd();
+
+    let (fill_map, fill, fill_k) = ring_pair::<u64>(ring_size)?;
+    let (comp_map, comp_k, comp) = ring_pair::<u64>(ring_size)?;
+    let (rx_map, rx_k, rx) = ring_pair::<XDPDesc>(ring_size)?;
+    let (tx_map, tx, tx_k) = ring_pair::<XDPDesc>(ring_size)?;
+
+    let pump = AfPacketPump {
+        socket,
+        umem: umem.as_ptr(),
+        frame_size,
+        fill: fill_k,
+        rx: rx_k,
+        tx: tx_k,
+        comp: comp_k,
+        tx_errors: 0,
+    };
+
+    let mut raw = FluxRaw::new(umem, rx, rx_map, fill, fill_map, tx, tx_map, comp, comp_map, fd);
+    raw.af_packet = Some(std::sync::Mutex::new(pump));
+    Ok(raw)
+}
+
+/// Allocate one ring and return its producer and consumer ends.
+pub(crate) fn ring_pair<T: Copy>(size: u32) -> io::Result<(MmapArea, ProducerRing<T>, ConsumerRing<T>)> {
+    let len = DESC_OFF + size as usize * std::mem::size_of::<T>();
+    let map = MmapArea::anonymous(len)?;
+    let base = map.as_ptr();
+    unsafe {
+        let producer = base.add(PRODUCER_OFF) as *mut u32;
+        let consumer = base.add(CONSUMER_OFF) as *mut u32;
+        let desc = base.add(DESC_OFF) as *mut T;
+        Ok((map, ProducerRing::new(producer, consumer, desc, size), ConsumerRing::new(producer, consumer, desc, size)))
+    }
+}
@@ -1,2 +1,4 @@
 pub mod socket;
-pub use socket::FluxRaw;
+#[cfg(target_os = "linux")]
+pub(crate) mod af_packet;
+pub use socket::{FluxRaw, RingOccupancy};
@@ -1,7 +1,9 @@
+use crate::builder::LinkWarning;
 use fluxcapacitor_core::sys::mmap::MmapArea;
 use fluxcapacitor_core::umem::mmap::UmemRegion;
 use fluxcapacitor_core::ring::{ConsumerRing, ProducerRing, XDPDesc};
-use fluxcapacitor_core::sys::socket::RawFd;
+use fluxcapacitor_core::sys::socket::{PollStatus, RawFd};
+use fluxcapacitor_core::sys::if_xdp::XdpStatistics;
 
 pub struct FluxRaw {
     pub umem: UmemRegion,
@@ -14,8 +16,33 @@ pub struct FluxRaw {
     pub comp: ConsumerRing<u64>,
     pub comp_map: MmapArea,
     fd: RawFd,
+    // Where the socket is bound, as resolved by the builder.
+    pub(crate) interface: String,
+    pub(crate) if_index: u32,
+    pub(crate) queue_id: u32,
+    pub(crate) zerocopy: bool,
+    pub(crate) multi_buffer: bool,
+    // Bound with XDP_USE_NEED_WAKEUP: the ring flags say when a syscall is due.
+    pub(crate) need_wakeup: bool,
+    pub(crate) rx_meta: bool,
+    pub(crate) auto_fill: bool,
+    // Frames `system::split` keeps out of the Fill Ring for TX.
+    pub(crate) tx_reserve: u32,
+    // Frames per Fill Ring reservation in `FluxRx::refill`.
+    pub(crate) fill_batch: u32,
+    // UMEM registered with TX metadata room for launch times.
+    pub(crate) tx_launch_time: bool,
+    // Set if the build turned promiscuous mode on.
+    pub(crate) promisc: Option<PromiscGuard>,
+    // What the build found wrong with the link.
+    pub(crate) link_warnings: Vec<LinkWarning>,
     #[cfg(target_os = "linux")]
     pub bpf: Option<aya::Bpf>,
+    // Set for `Backend::AfPacket`: rings are serviced in userspace on wakeup.
+    #[cfg(target_os = "linux")]
+    pub(crate) af_packet: Option<std::sync::Mutex<crate::raw::af_packet::AfPacketPump>>,
+    #[cfg(target_os = "linux")]
+    rx_poll: fluxcapacitor_core::sys::socket::RxPoll,
 }
 
 impl FluxRaw {
@@ -34,8 +61,25 @@ impl FluxRaw {
             tx, tx_map,
             comp, comp_map,
             fd,
+            interface: String::new(),
+            if_index: 0,
+            queue_id: 0,
+            zerocopy: false,
+            multi_buffer: false,
+            need_wakeup: false,
+            rx_meta: false,
+            auto_fill: true,
+            tx_reserve: 0,
+            fill_batch: 32,
+            tx_launch_time: false,
+            promisc: None,
+            link_warnings: Vec::new(),
             #[cfg(target_os = "linux")]
             bpf: None,
+            #[cfg(target_os = "linux")]
+            af_packet: None,
+            #[cfg(target_os = "linux")]
+            rx_poll: fluxcapacitor_core::sys::socket::RxPoll::new(fd),
         }
     }
     
@@ -43,36 +87,172 @@ impl FluxRaw {
         self.fd
     }
 
+    /// Name of the interface the socket is bound to, as given to `FluxBuilder::new`.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// Index of `interface()`, resolved when the socket was built.
+    pub fn if_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// RX/TX queue the socket is bound to. The AF_PACKET backend sees the whole
+    /// interface and only records the configured value.
+    pub fn queue_id(&self) -> u32 {
+        self.queue_id
+    }
+
+    /// Whether the kernel delivers frames zero-copy (as negotiated at bind time)
+    /// rather than copying them into UMEM.
+    pub fn is_zerocopy(&self) -> bool {
+        self.zerocopy
+    }
+
+    /// Whether the socket was bound with `XDP_USE_SG`, so a packet may span
+    /// several frames (see `FluxTx::send_sg`).
+    pub fn is_multi_buffer(&self) -> bool {
+        self.multi_buffer
+    }
+
+    /// Link problems found at build time that did not stop the build, such as
+    /// the interface being down. See `FluxBuilder::check_link`.
+    pub fn link_warnings(&self) -> &[LinkWarning] {
+        &self.link_warnings
+    }
+
+    /// Kernel drop and ring counters for this socket (`XDP_STATISTICS`).
+    /// Fails on the AF_PACKET backend, which has no such counters.
+    pub fn kernel_stats(&self) -> std::io::Result<XdpStatistics> {
+        fluxcapacitor_core::sys::socket::get_xdp_statistics(self.fd)
+    }
+
+    /// Packets the XDP program saw land on a different queue than the
+    /// `FluxBuilder::rss_key` hash predicts, summed over CPUs. Fails with
+    /// `NotFound` if the build did not load the program.
+    #[cfg(target_os = "linux")]
+    pub fn rss_mismatches(&self) -> std::io::Result<u64> {
+        use aya::maps::PerCpuArray;
+
+        let bpf = self.bpf.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no XDP program loaded")
+        })?;
+        let counts: PerCpuArray<_, u64> = bpf.map("RSS_MISMATCH").ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "RSS_MISMATCH not found")
+        })?.try_into().map_err(std::io::Error::other)?;
+        Ok(counts.get(&0, 0).map_err(std::io::Error::other)?.iter().sum())
+    }
+
     pub fn needs_wakeup_rx(&self) -> bool {
-        // TODO: check flags
-        false
+        // The AF_PACKET backend only makes progress when pumped.
+        #[cfg(target_os = "linux")]
+        if self.af_packet.is_some() {
+            return true;
+        }
+        // Without XDP_USE_NEED_WAKEUP the driver keeps polling the Fill Ring itself.
+        self.need_wakeup && self.fill.needs_wakeup()
     }
     
     pub fn wakeup_rx(&self) -> std::io::Result<()> {
         #[cfg(target_os = "linux")]
         {
+             if let Some(pump) = &self.af_packet {
+                 pump.lock().unwrap_or_else(|e| e.into_inner()).pump_rx();
+                 return Ok(());
+             }
              let _ = fluxcapacitor_core::sys::socket::wait_rx(self.fd, 0)?;
         }
         Ok(())
     }
     
+    /// Block until the socket has RX data or `timeout` elapses.
+    ///
+    /// `PollStatus::Error` means the socket is closed or broken; waiting again
+    /// would return immediately, forever. The simulator cannot signal readiness,
+    /// so there this only naps briefly and reports a timeout.
+    pub fn wait_rx(&mut self, timeout: std::time::Duration) -> std::io::Result<PollStatus> {
+        #[cfg(target_os = "linux")]
+        {
+            let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+            self.rx_poll.wait(timeout_ms)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            std::thread::sleep(timeout.min(std::time::Duration::from_millis(1)));
+            Ok(PollStatus::Timeout)
+        }
+    }
+
     pub fn needs_wakeup_tx(&self) -> bool {
-         // TODO: check flags
-         false
+         #[cfg(target_os = "linux")]
+         if self.af_packet.is_some() {
+             return true;
+         }
+         // Without XDP_USE_NEED_WAKEUP every TX submission needs a kick.
+         !self.need_wakeup || self.tx.needs_wakeup()
     }
     
     pub fn wakeup_tx(&self) -> std::io::Result<()> {
         #[cfg(target_os = "linux")]
-        fluxcapacitor_core::sys::socket::kick_tx(self.fd)?;
+        {
+            if let Some(pump) = &self.af_packet {
+                pump.lock().unwrap_or_else(|e| e.into_inner()).pump_tx()?;
+                return Ok(());
+            }
+            fluxcapacitor_core::sys::socket::kick_tx(self.fd)?;
+        }
         Ok(())
     }
 
-    pub fn debug_rings(&self) {
-        println!("--- FluxRaw Ring Debug ---");
-        println!("RX Ring:   {}/{}", self.rx.available(), self.rx.len());
-        println!("TX Ring:   {}/{}", self.tx.available(), self.tx.len());
-        println!("Fill Ring: {}/{}", self.fill.available(), self.fill.len());
-        println!("Comp Ring: {}/{}", self.comp.available(), self.comp.len());
+    /// Frames the AF_PACKET backend completed without sending them because the
+    /// send failed (e.g. a malformed or oversized frame). Always 0 on the XDP
+    /// backend, where the kernel counts its own drops (`kernel_stats`).
+    pub fn tx_errors(&self) -> u64 {
+        #[cfg(target_os = "linux")]
+        if let Some(pump) = &self.af_packet {
+            return pump.lock().unwrap_or_else(|e| e.into_inner()).tx_errors();
+        }
+        0
+    }
+
+    /// How full each ring is right now, for debugging. Print it with `{}`.
+    pub fn debug_rings(&self) -> RingOccupancy {
+        RingOccupancy {
+            rx: (self.rx.available(), self.rx.len()),
+            tx: (self.tx.available(), self.tx.len()),
+            fill: (self.fill.available(), self.fill.len()),
+            comp: (self.comp.available(), self.comp.len()),
+        }
+    }
+}
+
+/// `(available, size)` of each ring of a `FluxRaw`, from `FluxRaw::debug_rings`.
+///
+/// For the RX and Completion rings `available` is entries waiting to be read; for
+/// the TX and Fill rings it is free slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingOccupancy {
+    pub rx: (u32, u32),
+    pub tx: (u32, u32),
+    pub fill: (u32, u32),
+    pub comp: (u32, u32),
+}
+
+impl std::fmt::Display for RingOccupancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "RX Ring:   {}/{}", self.rx.0, self.rx.1)?;
+        writeln!(f, "TX Ring:   {}/{}", self.tx.0, self.tx.1)?;
+        writeln!(f, "Fill Ring: {}/{}", self.fill.0, self.fill.1)?;
+        write!(f, "Comp Ring: {}/{}", self.comp.0, self.comp.1)
+    }
+}
+
+/// Turns promiscuous mode off again on the interface with this index when dropped.
+pub(crate) struct PromiscGuard(pub(crate) u32);
+
+impl Drop for PromiscGuard {
+    fn drop(&mut self) {
+        let _ = fluxcapacitor_core::sys::utils::set_promiscuous(self.0, false);
     }
 }
 
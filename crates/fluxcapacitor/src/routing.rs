@@ -0,0 +1,205 @@
+//! IPv4 longest-prefix-match route lookup for L3 forwarding.
+//!
+//! `RouteTable` is a binary trie stored in one `Vec`: inserting a route may
+//! allocate, lookups never do and touch at most 33 nodes. Build the table before
+//! starting the engine and only read it on the fast path.
+//!
+//! ```ignore
+//! let mut routes = RouteTable::new();
+//! routes.insert(Ipv4Addr::UNSPECIFIED, 0, NextHop::via(WAN, gateway));
+//! routes.insert(Ipv4Addr::new(192, 168, 1, 0), 24, NextHop::direct(LAN));
+//! engine.run(&stop, |batch| {
+//!     for mut pkt in batch.iter_mut() {
+//!         match pkt.ipv4().and_then(|ip| routes.lookup(ip.dst_addr())) {
+//!             Some(hop) if hop.ifindex == LAN => pkt.send(),
+//!             _ => pkt.drop(),
+//!         }
+//!     }
+//! })?;
+//! ```
+
+use std::net::Ipv4Addr;
+
+const NIL: u32 = u32::MAX;
+
+/// Where to send a packet matching a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NextHop {
+    /// Outgoing interface index.
+    pub ifindex: u32,
+    /// Gateway to forward to, or `None` if the destination is on-link.
+    pub gateway: Option<Ipv4Addr>,
+}
+
+impl NextHop {
+    /// Destination reachable directly on `ifindex`.
+    pub fn direct(ifindex: u32) -> Self {
+        Self { ifindex, gateway: None }
+    }
+
+    /// Destination reached through `gateway` on `ifindex`.
+    pub fn via(ifindex: u32, gateway: Ipv4Addr) -> Self {
+        Self { ifindex, gateway: Some(gateway) }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    children: [u32; 2],
+    route: Option<NextHop>,
+}
+
+impl Node {
+    const EMPTY: Node = Node { children: [NIL, NIL], route: None };
+}
+
+/// IPv4 routing table with longest-prefix-match lookup.
+#[derive(Debug, Clone)]
+pub struct RouteTable {
+    // nodes[0] is the root, i.e. the /0 prefix.
+    nodes: Vec<Node>,
+    routes: usize,
+}
+
+impl Default for RouteTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Self { nodes: vec![Node::EMPTY], routes: 0 }
+    }
+
+    /// Add a route for `prefix/prefix_len`, replacing any route for the same prefix.
+    /// Host bits of `prefix` beyond `prefix_len` are ignored.
+    ///
+    /// Returns the route it replaced. Panics if `prefix_len` is over 32.
+    pub fn insert(&mut self, prefix: Ipv4Addr, prefix_len: u8, next_hop: NextHop) -> Option<NextHop> {
+        assert!(prefix_len <= 32, "IPv4 prefix length {} is over 32", prefix_len);
+        let bits = u32::from(prefix);
+        let mut node = 0usize;
+        for depth in 0..prefix_len {
+            let bit = ((bits >> (31 - depth)) & 1) as usize;
+            let child = self.nodes[node].children[bit];
+            node = if child == NIL {
+                self.nodes.push(Node::EMPTY);
+                let idx = self.nodes.len() - 1;
+                self.nodes[node].children[bit] = idx as u32;
+                idx
+            } else {
+                child as usize
+            };
+        }
+
+        let old = self.nodes[node].route.replace(next_hop);
+        if old.is_none() {
+            self.routes += 1;
+        }
+        old
+    }
+
+    /// Remove the route for exactly `prefix/prefix_len`. Trie nodes are kept, so
+    /// re-adding the route later does not allocate.
+    pub fn remove(&mut self, prefix: Ipv4Addr, prefix_len: u8) -> Option<NextHop> {
+        assert!(prefix_len <= 32, "IPv4 prefix length {} is over 32", prefix_len);
+        let bits = u32::from(prefix);
+        let mut node = 0usize;
+        for depth in 0..prefix_len {
+            let child = self.nodes[node].children[((bits >> (31 - depth)) & 1) as usize];
+            if child == NIL {
+                return None;
+            }
+            node = child as usize;
+        }
+
+        let old = self.nodes[node].route.take();
+        if old.is_some() {
+            self.routes -= 1;
+        }
+        old
+    }
+
+    /// Next hop of the most specific route covering `addr`, if any.
+    pub fn lookup(&self, addr: Ipv4Addr) -> Option<NextHop> {
+        let bits = u32::from(addr);
+        let mut node = &self.nodes[0];
+        let mut best = node.route;
+        for depth in 0..32 {
+            let child = node.children[((bits >> (31 - depth)) & 1) as usize];
+            if child == NIL {
+                break;
+            }
+            node = &self.nodes[child as usize];
+            if node.route.is_some() {
+                best = node.route;
+            }
+        }
+        best
+    }
+
+    /// Number of routes in the table.
+    pub fn len(&self) -> usize {
+        self.routes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> Ipv4Addr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_route() {
+        let mut table = RouteTable::new();
+        assert_eq!(table.lookup(ip("8.8.8.8")), None);
+
+        let gw = NextHop::via(1, ip("10.0.0.1"));
+        assert_eq!(table.insert(Ipv4Addr::UNSPECIFIED, 0, gw), None);
+        assert_eq!(table.lookup(ip("8.8.8.8")), Some(gw));
+        assert_eq!(table.lookup(ip("255.255.255.255")), Some(gw));
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let mut table = RouteTable::new();
+        table.insert(ip("192.168.1.7"), 32, NextHop::direct(3));
+        table.insert(ip("192.168.1.0"), 24, NextHop::direct(2));
+
+        assert_eq!(table.lookup(ip("192.168.1.7")), Some(NextHop::direct(3)));
+        assert_eq!(table.lookup(ip("192.168.1.8")), Some(NextHop::direct(2)));
+        assert_eq!(table.lookup(ip("192.168.2.7")), None);
+
+        // Replacing and removing
+        assert_eq!(table.insert(ip("192.168.1.7"), 32, NextHop::direct(4)), Some(NextHop::direct(3)));
+        assert_eq!(table.remove(ip("192.168.1.7"), 32), Some(NextHop::direct(4)));
+        assert_eq!(table.lookup(ip("192.168.1.7")), Some(NextHop::direct(2)));
+        assert_eq!(table.remove(ip("10.0.0.0"), 8), None);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_overlapping_prefixes() {
+        let mut table = RouteTable::new();
+        table.insert(Ipv4Addr::UNSPECIFIED, 0, NextHop::direct(0));
+        table.insert(ip("10.0.0.0"), 8, NextHop::direct(8));
+        // Host bits are ignored
+        table.insert(ip("10.1.255.255"), 16, NextHop::direct(16));
+        table.insert(ip("10.1.2.0"), 24, NextHop::direct(24));
+
+        assert_eq!(table.lookup(ip("10.1.2.3")).unwrap().ifindex, 24);
+        assert_eq!(table.lookup(ip("10.1.3.3")).unwrap().ifindex, 16);
+        assert_eq!(table.lookup(ip("10.2.0.1")).unwrap().ifindex, 8);
+        assert_eq!(table.lookup(ip("11.0.0.1")).unwrap().ifindex, 0);
+        assert_eq!(table.len(), 4);
+    }
+}
@@ -7,10 +7,16 @@ use fluxcapacitor_core::windows_stubs::SOCKETS;
 pub mod control {
     use super::*;
     use fluxcapacitor_core::sys::socket::RawFd;
+    use fluxcapacitor_core::windows_stubs::PENDING_RX;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
     
     /// Inject a packet into the RX ring of the specified socket.
     /// This mimics a packet arriving from the network card.
     /// 
+    /// If `set_rx_latency` configured a delay, the packet is queued and only shows
+    /// up on the RX ring once the delay has elapsed.
+    /// 
     /// # Arguments
     /// * `fd` - The socket file descriptor (mocked)
     /// * `data` - The raw packet bytes
@@ -20,68 +26,39 @@ pub mod control {
         
         let sock = sockets.get_mut(&fd_idx).ok_or("Socket not found")?;
         
-        // 1. Get a frame from UMEM (Simulated mechanism)
-        // In reality, the user must have put frames in the FILL RING.
-        // We need to check the FILL RING to see if user gave us buffers.
+        if sock.rx_latency.is_zero() && sock.rx_jitter.is_zero() {
+            return sock.deliver_rx(data);
+        }
         
-        // pointers for fill ring
-        // Layout: Prod(0), Cons(4)
-        let fill_prod_ptr = sock.fill_ring.as_ptr() as *const u32;
-        let fill_cons_ptr = unsafe { sock.fill_ring.as_ptr().add(4) } as *mut u32;
-        let fill_desc_ptr = unsafe { sock.fill_ring.as_ptr().add(8) } as *const u64; // Fill ring contains u64 addrs
+        // Latency model: hold the packet until its delay elapses.
+        // The RX ring's `peek` publishes it once it is due.
+        let visible_at = sock.rx_visible_at(Instant::now());
+        sock.rx_pending.push_back((visible_at, data.to_vec()));
+        PENDING_RX.fetch_add(1, Ordering::Relaxed);
         
+        Ok(())
+    }
+    
+    /// Publish `desc` on the RX ring as-is, without taking a frame from the Fill Ring.
+    ///
+    /// Simulates a misbehaving "NIC" (or a desynchronized ring) handing out a
+    /// descriptor that does not describe a valid frame.
+    pub fn inject_rx_desc(fd: RawFd, desc: fluxcapacitor_core::ring::XDPDesc) -> Result<(), String> {
+        let fd_idx = fd as usize;
+        let mut sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get_mut(&fd_idx).ok_or("Socket not found")?;
+
+        let rx_prod_ptr = sock.rx_ring.as_mut_ptr() as *mut u32;
+        let rx_desc_ptr = unsafe { sock.rx_ring.as_mut_ptr().add(8) } as *mut fluxcapacitor_core::ring::XDPDesc;
+
         unsafe {
-            let fill_prod = *fill_prod_ptr;
-            let fill_cons = *fill_cons_ptr;
-            
-            if fill_cons == fill_prod {
-                return Err("RX Dropped: No buffers in Fill Ring".to_string());
-            }
-            
-            // Consume one buffer from Fill Ring
-            let mask = 4096 - 1; // Assuming size 4096 for mock
-            let idx = fill_cons & mask;
-            let addr = *fill_desc_ptr.add(idx as usize);
-            
-            // Update Fill Consumer
-            *fill_cons_ptr = fill_cons + 1;
-            
-            // 2. Write data to UMEM
-            if (addr as usize) + data.len() > sock.umem.len() {
-               // Resize UMEM if needed (simple mock behavior)
-               // In reality, UMEM is fixed. Mock allows dynamic for ease.
-               if (addr as usize) + data.len() > sock.umem.len() {
-                   sock.umem.resize((addr as usize) + data.len() + 4096, 0);
-               }
-            }
-            
-            // Copy data
-            let dest = sock.umem.as_mut_ptr().add(addr as usize);
-            std::ptr::copy_nonoverlapping(data.as_ptr(), dest, data.len());
-            
-            // 3. Publish to RX Ring
-            // Layout: Prod(0), Cons(4), Desc(8)
-            let rx_prod_ptr = sock.rx_ring.as_mut_ptr() as *mut u32;
-            let rx_desc_ptr = sock.rx_ring.as_mut_ptr().add(8) as *mut fluxcapacitor_core::ring::XDPDesc;
-            
             let rx_prod = *rx_prod_ptr;
-            let rx_idx = rx_prod & mask;
-            
-            let desc = fluxcapacitor_core::ring::XDPDesc {
-                addr,
-                len: data.len() as u32,
-                options: 0,
-            };
-            
-            *rx_desc_ptr.add(rx_idx as usize) = desc;
-            
-            // Update RX Producer
+            *rx_desc_ptr.add((rx_prod & (sock.rx_size - 1)) as usize) = desc;
             *rx_prod_ptr = rx_prod + 1;
         }
-        
         Ok(())
     }
-    
+
     /// Peek at the next packet in the TX ring (sent by the user).
     /// Does NOT consume it (Consumption happens via complete_tx).
     pub fn read_tx_packet(fd: RawFd) -> Result<Vec<u8>, String> {
@@ -101,10 +78,20 @@ pub mod control {
                 return Err("No packets in TX Ring".to_string());
             }
             
-            let mask = 4096 - 1;
-            let idx = tx_cons & mask;
+            let idx = tx_cons & (sock.tx_size - 1);
             let desc = *tx_desc_ptr.add(idx as usize);
             
+            // Injected failure: the descriptor is consumed and its frame completed
+            // without the data going out.
+            sock.tx_failure_acc += sock.tx_failure_rate;
+            if sock.tx_failure_acc >= 1.0 {
+                sock.tx_failure_acc -= 1.0;
+                sock.tx_failures += 1;
+                *tx_cons_ptr = tx_cons + 1;
+                complete_tx(sock, desc.addr);
+                return Err("TX Dropped: Simulated transmit failure".to_string());
+            }
+            
             let start = desc.addr as usize;
             let end = start + desc.len as usize;
             
@@ -117,18 +104,138 @@ pub mod control {
             
             // Auto-complete the TX (Simulate transmission success)
             *tx_cons_ptr = tx_cons + 1;
-            
-            // Push to Completion Ring
-             let comp_prod_ptr = sock.comp_ring.as_mut_ptr() as *mut u32;
-             let comp_desc_ptr = sock.comp_ring.as_mut_ptr().add(8) as *mut u64;
-             
-             let comp_prod = *comp_prod_ptr;
-             let comp_idx = comp_prod & mask;
-             
-             *comp_desc_ptr.add(comp_idx as usize) = desc.addr;
-             *comp_prod_ptr = comp_prod + 1;
-             
+            complete_tx(sock, desc.addr);
+
             Ok(data)
         }
     }
+
+    // Push `addr` to the Completion Ring, handing the frame back to the application.
+    fn complete_tx(sock: &mut fluxcapacitor_core::windows_stubs::MockSocketState, addr: u64) {
+        let comp_prod_ptr = sock.comp_ring.as_mut_ptr() as *mut u32;
+        let comp_desc_ptr = unsafe { sock.comp_ring.as_mut_ptr().add(8) } as *mut u64;
+
+        unsafe {
+            let comp_prod = *comp_prod_ptr;
+            *comp_desc_ptr.add((comp_prod & (sock.comp_size - 1)) as usize) = addr;
+            *comp_prod_ptr = comp_prod + 1;
+        }
+    }
+
+    /// Descriptor at the head of the TX ring, without consuming it.
+    ///
+    /// Lets tests check the `options` the application set on a transmit.
+    pub fn peek_tx_desc(fd: RawFd) -> Result<fluxcapacitor_core::ring::XDPDesc, String> {
+        let fd_idx = fd as usize;
+        let sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get(&fd_idx).ok_or("Socket not found")?;
+
+        let tx_prod_ptr = sock.tx_ring.as_ptr() as *const u32;
+        let tx_cons_ptr = unsafe { sock.tx_ring.as_ptr().add(4) } as *const u32;
+        let tx_desc_ptr = unsafe { sock.tx_ring.as_ptr().add(8) } as *const fluxcapacitor_core::ring::XDPDesc;
+
+        unsafe {
+            let (prod, cons) = (*tx_prod_ptr, *tx_cons_ptr);
+            if cons == prod {
+                return Err("No packets in TX Ring".to_string());
+            }
+            Ok(*tx_desc_ptr.add((cons & (sock.tx_size - 1)) as usize))
+        }
+    }
+
+    /// Launch time (`CLOCK_TAI` ns) requested by the descriptor at the head of the
+    /// TX ring, without consuming it.
+    ///
+    /// `None` unless the descriptor carries `XDP_TX_METADATA`, the UMEM was
+    /// registered with TX metadata and the metadata sets `XDP_TXMD_FLAGS_LAUNCH_TIME`.
+    pub fn peek_tx_launch_time(fd: RawFd) -> Result<Option<u64>, String> {
+        use fluxcapacitor_core::sys::if_xdp::{XskTxMetadata, XDP_TX_METADATA, XDP_TXMD_FLAGS_LAUNCH_TIME};
+
+        let desc = peek_tx_desc(fd)?;
+        let sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get(&(fd as usize)).ok_or("Socket not found")?;
+
+        let meta_len = sock.tx_metadata_len as u64;
+        if desc.options & XDP_TX_METADATA == 0 || meta_len == 0 {
+            return Ok(None);
+        }
+        if desc.addr < meta_len || desc.addr as usize > sock.umem.len() {
+            return Err("TX metadata out of bounds of UMEM".to_string());
+        }
+        let meta = unsafe {
+            std::ptr::read_unaligned(sock.umem.as_ptr().add((desc.addr - meta_len) as usize) as *const XskTxMetadata)
+        };
+        Ok((meta.flags & XDP_TXMD_FLAGS_LAUNCH_TIME != 0).then_some(meta.launch_time))
+    }
+
+    /// Addresses currently posted on the Fill Ring and not yet consumed by the "NIC".
+    ///
+    /// Lets tests check which frames the application has handed to the kernel, e.g.
+    /// that a frame queued for TX is not refilled before its completion.
+    pub fn fill_ring_addrs(fd: RawFd) -> Result<Vec<u64>, String> {
+        let fd_idx = fd as usize;
+        let sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get(&fd_idx).ok_or("Socket not found")?;
+
+        let fill_prod_ptr = sock.fill_ring.as_ptr() as *const u32;
+        let fill_cons_ptr = unsafe { sock.fill_ring.as_ptr().add(4) } as *const u32;
+        let fill_desc_ptr = unsafe { sock.fill_ring.as_ptr().add(8) } as *const u64;
+
+        unsafe {
+            let (prod, cons) = (*fill_prod_ptr, *fill_cons_ptr);
+            Ok((cons..prod)
+                .map(|i| *fill_desc_ptr.add((i & (sock.fill_size - 1)) as usize))
+                .collect())
+        }
+    }
+
+    /// Delay injected packets before they become visible on the RX ring.
+    ///
+    /// Each packet is held for `base` plus a pseudo-random extra delay in `0..=jitter`.
+    /// The jitter sequence is seeded per socket, so runs are reproducible. Packets are
+    /// still delivered in injection order. Passing zero for both disables the model.
+    ///
+    /// # Arguments
+    /// * `fd` - The socket file descriptor (mocked)
+    /// * `base` - Fixed delay applied to every packet
+    /// * `jitter` - Upper bound on the additional random delay
+    pub fn set_rx_latency(fd: RawFd, base: Duration, jitter: Duration) -> Result<(), String> {
+        let fd_idx = fd as usize;
+        let mut sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get_mut(&fd_idx).ok_or("Socket not found")?;
+        
+        sock.rx_latency = base;
+        sock.rx_jitter = jitter;
+        Ok(())
+    }
+    
+    /// Make a fraction of TX descriptors fail.
+    ///
+    /// A failed descriptor is consumed from the TX Ring by `read_tx_packet` and its
+    /// frame pushed to the Completion Ring without the data going out, mimicking a
+    /// NIC that drops it. Failures show up as `tx_invalid_descs` in the socket's
+    /// `XDP_STATISTICS`. They are spread deterministically: a rate of `0.5` fails
+    /// every second TX.
+    ///
+    /// # Arguments
+    /// * `fd` - The socket file descriptor (mocked)
+    /// * `rate` - Fraction of TX attempts to fail, clamped to `0.0..=1.0`
+    pub fn set_tx_failure_rate(fd: RawFd, rate: f32) -> Result<(), String> {
+        let fd_idx = fd as usize;
+        let mut sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get_mut(&fd_idx).ok_or("Socket not found")?;
+        
+        sock.tx_failure_rate = rate.clamp(0.0, 1.0);
+        sock.tx_failure_acc = 0.0;
+        Ok(())
+    }
+    
+    /// Number of TX descriptors dropped by `set_tx_failure_rate`.
+    pub fn tx_failures(fd: RawFd) -> Result<u64, String> {
+        let fd_idx = fd as usize;
+        let sockets = SOCKETS.lock().map_err(|e| e.to_string())?;
+        let sock = sockets.get(&fd_idx).ok_or("Socket not found")?;
+        
+        Ok(sock.tx_failures)
+    }
 }
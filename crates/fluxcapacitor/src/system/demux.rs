@@ -0,0 +1,55 @@
+use crate::packet::Packet;
+use crate::system::FluxRx;
+use fluxcapacitor_proto::IpProto;
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+
+/// Routes received packets to per-port worker channels.
+///
+/// Packets are matched on their IPv4 TCP/UDP destination port and moved into the
+/// channel registered for it. Ownership travels with the `Packet`, so the frame is
+/// recycled when the worker drops it. Unmatched packets, and packets whose channel
+/// has hung up, are dropped on the spot and their frames recycled.
+#[derive(Default)]
+pub struct Demux {
+    routes: HashMap<u16, Sender<Packet>>,
+}
+
+impl Demux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send packets destined to `port` to `sender`. Replaces any previous route.
+    pub fn route(mut self, port: u16, sender: Sender<Packet>) -> Self {
+        self.routes.insert(port, sender);
+        self
+    }
+
+    /// Receive up to `max` packets from `rx` and dispatch them.
+    /// Returns the number of packets delivered to a channel.
+    pub fn dispatch(&self, rx: &mut FluxRx, max: usize) -> usize {
+        let mut routed = 0;
+        for packet in rx.recv(max) {
+            let sender = dst_port(packet.data()).and_then(|port| self.routes.get(&port));
+            if let Some(sender) = sender {
+                // A closed channel hands the packet back; dropping it recycles the frame.
+                if sender.send(packet).is_ok() {
+                    routed += 1;
+                }
+            }
+        }
+        routed
+    }
+}
+
+fn dst_port(frame: &[u8]) -> Option<u16> {
+    let (_, ip_payload) = fluxcapacitor_proto::parse_eth(frame)?;
+    let (ip_header, l4_payload) = fluxcapacitor_proto::parse_ipv4(ip_payload)?;
+
+    match ip_header.protocol() {
+        IpProto::Tcp => fluxcapacitor_proto::parse_tcp(l4_payload).map(|(h, _)| h.dst_port()),
+        IpProto::Udp => fluxcapacitor_proto::parse_udp(l4_payload).map(|(h, _)| h.dst_port()),
+        _ => None,
+    }
+}
@@ -1,11 +1,15 @@
 pub mod rx;
 pub mod tx;
 pub mod shared;
+pub mod demux;
+pub mod worker;
 #[cfg(feature = "async")]
 pub mod reactor;
 
 pub use rx::FluxRx;
-pub use tx::FluxTx;
+pub use tx::{CompletionIter, FluxTx};
+pub use demux::Demux;
+pub use worker::{FluxWorker, Verdict};
 #[cfg(feature = "async")]
 pub use reactor::{AsyncFluxRx, AsyncFluxTx};
 
@@ -16,11 +20,26 @@ use std::io;
 pub fn split(socket: FluxRaw) -> (FluxRx, FluxTx) {
     let fd = socket.fd();
     let umem = Arc::new(socket.umem);
-    let shared_state = Arc::new(shared::SharedFrameState::new());
+    let shared_state = Arc::new(shared::SharedFrameState::new(umem.layout().frame_count));
+    let auto_fill = socket.auto_fill;
+    let multi_buffer = socket.multi_buffer;
+    let layout = umem.layout();
+    // The last `tx_reserve` frames never enter the Fill Ring.
+    let rx_frames = layout.frame_count.saturating_sub(socket.tx_reserve);
+    let reserve = (rx_frames..layout.frame_count).map(|i| i as u64 * layout.chunk_size as u64).collect();
     
     // Perform partial partial moves to extract fields
-    let rx = FluxRx::new(socket.rx, socket.rx_map, socket.fill, socket.fill_map, umem.clone(), fd, shared_state);
-    let tx = FluxTx::new(socket.tx, socket.tx_map, socket.comp, socket.comp_map, umem, fd);
+    let mut rx = FluxRx::new(socket.rx, socket.rx_map, socket.fill, socket.fill_map, umem.clone(), fd, shared_state.clone());
+    rx.set_fill_batch(socket.fill_batch);
+    rx.set_promisc(socket.promisc);
+    if auto_fill {
+        rx.fill_all(rx_frames);
+    }
+    let mut tx = FluxTx::new(socket.tx, socket.tx_map, socket.comp, socket.comp_map, umem, fd, shared_state);
+    tx.set_multi_buffer(multi_buffer);
+    tx.set_reserve(reserve);
+    tx.set_launch_time(socket.tx_launch_time);
+    tx.set_need_wakeup(socket.need_wakeup);
     
     (rx, tx)
 }
@@ -1,6 +1,6 @@
 use crate::system::rx::FluxRx;
 use crate::system::tx::FluxTx;
-use crate::packet::Packet;
+use crate::packet::{Packet, PacketArray};
 use std::io;
 use std::task::{Context, Poll};
 
@@ -49,6 +49,26 @@ impl AsyncFluxRx {
         }
     }
 
+    /// `recv` without the `Vec`: at most `N` packets in a `PacketArray` (see
+    /// `FluxRx::recv_array`). Waits until at least one packet is there.
+    pub async fn recv_array<const N: usize>(&mut self) -> io::Result<PacketArray<N>> {
+        #[cfg(all(target_os = "linux", feature = "async"))]
+        {
+            loop {
+                let mut guard = self.async_fd.readable().await?;
+                let packets = self.inner.recv_array::<N>();
+                if !packets.is_empty() {
+                    return Ok(packets);
+                }
+                guard.clear_ready();
+            }
+        }
+        #[cfg(all(not(target_os = "linux"), feature = "async"))]
+        {
+            Ok(self.inner.recv_array::<N>())
+        }
+    }
+
     pub fn poll_recv(&mut self, cx: &mut Context<'_>, max: usize) -> Poll<io::Result<Vec<Packet>>> {
          #[cfg(all(target_os = "linux", feature = "async"))]
          {
@@ -100,19 +120,26 @@ impl AsyncFluxTx {
         self.inner.send(packet);
     }
 
-    // Flush TX ring to NIC
+    /// See `FluxTx::set_buffered`. Sends then only go out on `flush`.
+    pub fn set_buffered(&mut self, buffered: bool) {
+        self.inner.set_buffered(buffered);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.inner.pending()
+    }
+
+    // Flush TX ring to NIC: one kick for everything queued (`FluxTx::flush`).
     pub async fn flush(&mut self) -> io::Result<()> {
         #[cfg(all(target_os = "linux", feature = "async"))]
         {
-            let mut guard = self.async_fd.writable().await?;
-            // On Linux, we might need a syscall if NEEDS_WAKEUP is set.
-            // FluxTx doesn't have a public wakeup() yet, but we should add it.
-            guard.clear_ready();
-            Ok(())
+            // Readiness is kept: the socket stays writable for the next flush.
+            let _guard = self.async_fd.writable().await?;
+            self.inner.flush()
         }
         #[cfg(all(not(target_os = "linux"), feature = "async"))]
         {
-            Ok(())
+            self.inner.flush()
         }
     }
 }
@@ -2,10 +2,17 @@ use fluxcapacitor_core::sys::mmap::MmapArea;
 use fluxcapacitor_core::ring::{ConsumerRing, ProducerRing, XDPDesc};
 use fluxcapacitor_core::umem::mmap::UmemRegion;
 use std::sync::Arc;
-use crate::packet::Packet;
+use crate::packet::{Packet, PacketArray};
 use fluxcapacitor_core::sys::socket::RawFd;
 use crate::system::shared::SharedFrameState;
+use crate::raw::socket::PromiscGuard;
 
+/// Receive half of a socket from `system::split`.
+///
+/// Dropping it releases the RX and Fill Ring mappings only. `Packet`s already
+/// received keep the UMEM and the shared free pool alive through their own `Arc`s,
+/// so they can still be read, written and sent afterwards; frames they recycle
+/// wait in the pool for a `FluxRx` that never comes, or for `FluxTx::alloc_frame`.
 pub struct FluxRx {
     rx: ConsumerRing<XDPDesc>,
     #[allow(dead_code)]
@@ -16,6 +23,13 @@ pub struct FluxRx {
     umem: Arc<UmemRegion>,
     fd: RawFd,
     shared_state: Arc<SharedFrameState>,
+    // Userspace side of the frame lifecycle, for debugging RX starvation.
+    fills_submitted: u64,
+    frames_recycled: u64,
+    fill_batch: u32,
+    // Promiscuous mode stays on while the receiving half lives.
+    #[allow(dead_code)]
+    promisc: Option<PromiscGuard>,
 }
 
 unsafe impl Send for FluxRx {}
@@ -23,41 +37,68 @@ unsafe impl Send for FluxRx {}
 impl FluxRx {
     pub(crate) fn new(
         rx: ConsumerRing<XDPDesc>, rx_map: MmapArea,
-        mut fill: ProducerRing<u64>, fill_map: MmapArea,
+        fill: ProducerRing<u64>, fill_map: MmapArea,
         umem: Arc<UmemRegion>, fd: RawFd, shared_state: Arc<SharedFrameState>
     ) -> Self {
-        // Initialize Fill Ring with all available frames
-        let frame_count = umem.layout().frame_count;
-        let frame_size = umem.layout().frame_size;
+        Self {
+            rx, rx_map, fill, fill_map, umem, fd, shared_state,
+            fills_submitted: 0,
+            frames_recycled: 0,
+            fill_batch: 32,
+            promisc: None,
+        }
+    }
+
+    pub(crate) fn set_fill_batch(&mut self, frames: u32) {
+        self.fill_batch = frames.max(1);
+    }
+
+    pub(crate) fn set_promisc(&mut self, guard: Option<PromiscGuard>) {
+        self.promisc = guard;
+    }
+
+    /// Initialize Fill Ring with the first `frame_count` frames.
+    /// Frames that don't fit in the ring wait on the free list for `refill`.
+    pub(crate) fn fill_all(&mut self, frame_count: u32) {
+        let chunk_size = self.umem.layout().chunk_size as u64;
+        let to_fill = frame_count.min(self.fill.available());
         
-        if let Some(mut prod) = fill.reserve(frame_count) {
-             for i in 0..frame_count {
-                 let addr = (i * frame_size) as u64;
-                 unsafe { fill.write_at(prod, addr) };
+        if let Some(mut prod) = self.fill.reserve(to_fill) {
+             for i in 0..to_fill {
+                 let addr = i as u64 * chunk_size;
+                 unsafe { self.fill.write_at(prod, addr) };
                  prod += 1;
              }
-             fill.submit(prod);
+             self.fill.submit(prod);
+             self.fills_submitted += to_fill as u64;
+        }
+        
+        for i in to_fill..frame_count {
+            self.shared_state.recycle(i as u64 * chunk_size);
         }
-
-        Self { rx, rx_map, fill, fill_map, umem, fd, shared_state }
     }
     
     pub fn fd(&self) -> RawFd {
         self.fd
     }
     
-    /// Refill the Fill Ring with frames returned by dropped Packets.
+    /// Refill the Fill Ring with frames returned by dropped Packets, until the ring
+    /// is full or the free list is empty. Returns how many frames were handed over.
     /// This is called automatically by recv(), but can be called manually.
-    pub fn refill(&mut self) {
-        // We take up to 32 frames at a time to batch updates
-        // In a real impl, we'd check ring space first.
-        let batch_size = 32;
-        let mut count = 0;
-        
-        let reserve = self.fill.reserve(batch_size);
-        if let Some(mut idx) = reserve {
+    pub fn refill(&mut self) -> usize {
+        let mut total = 0;
+        loop {
+            // Reserve `fill_batch` frames at a time to batch index updates, bounded by ring space
+            let batch_size = self.fill.available().min(self.fill_batch);
+            if batch_size == 0 {
+                break;
+            }
+            let Some(mut idx) = self.fill.reserve(batch_size) else {
+                break;
+            };
+            let mut count = 0;
             while count < batch_size {
-                 if let Some(frame) = self.shared_state.free_frames.pop() {
+                 if let Some(frame) = self.shared_state.take() {
                      unsafe { self.fill.write_at(idx, frame) };
                      idx += 1;
                      count += 1;
@@ -67,10 +108,64 @@ impl FluxRx {
             }
             if count > 0 {
                 self.fill.submit(idx);
+                self.fills_submitted += count as u64;
+                self.frames_recycled += count as u64;
+                total += count as usize;
+            }
+            if count < batch_size {
+                break;
             }
         }
+        total
+    }
+
+    /// Hand specific frames to the kernel for RX.
+    ///
+    /// For use with `FluxBuilder::auto_fill(false)`, where the Fill Ring starts empty.
+    /// `addrs` are UMEM offsets of whole frames not owned by anyone else. All or none
+    /// are submitted; returns `false` if the Fill Ring lacks room for them.
+    pub fn fill_frames(&mut self, addrs: &[u64]) -> bool {
+        let Some(mut idx) = self.fill.reserve(addrs.len() as u32) else {
+            return false;
+        };
+        for &addr in addrs {
+            unsafe { self.fill.write_at(idx, addr) };
+            idx += 1;
+        }
+        self.fill.submit(idx);
+        self.fills_submitted += addrs.len() as u64;
+        true
+    }
+
+    /// Free slots on the Fill Ring, i.e. how many frames `refill`/`fill_frames` can
+    /// hand over right now. Equal to the ring size when the kernel has no buffers left.
+    pub fn fill_capacity(&self) -> usize {
+        self.fill.available() as usize
+    }
+
+    /// Total frames handed to the kernel via the Fill Ring, including the initial fill.
+    pub fn fills_submitted(&self) -> u64 {
+        self.fills_submitted
+    }
+
+    /// Frames returned by dropped `Packet`s and put back on the Fill Ring.
+    pub fn frames_recycled(&self) -> u64 {
+        self.frames_recycled
+    }
+
+    /// Frames lost because more were recycled than the UMEM holds, which only a
+    /// frame-accounting bug can cause. Debug builds panic instead.
+    pub fn frames_overflowed(&self) -> u64 {
+        self.shared_state.overflowed()
     }
     
+    /// Whether the RX ring holds packets for `recv`, read straight from the ring's
+    /// shared producer index. Costs no syscall, so busy loops can check it before
+    /// falling back to a blocking poll.
+    pub fn ready(&self) -> bool {
+        self.rx.available() > 0
+    }
+
     pub fn recv(&mut self, max: usize) -> Vec<Packet> {
         // 1. Routine maintenance: put recycled frames back into Fill Ring
         self.refill();
@@ -99,4 +194,25 @@ impl FluxRx {
         
         packets
     }
+
+    /// `recv` into a fixed-capacity array instead of a `Vec`, for callers that
+    /// must not allocate per receive.
+    ///
+    /// Takes at most `N` packets; the rest stay on the RX Ring for the next call.
+    /// An `N` above the ring size gains nothing, since one call never sees more than
+    /// a full ring, and a large `N` makes a large return value.
+    pub fn recv_array<const N: usize>(&mut self) -> PacketArray<N> {
+        self.refill();
+
+        let mut packets = PacketArray::new();
+        let count = self.rx.peek(N as u32);
+        let start = self.rx.consumer_idx();
+        for i in 0..count {
+            let desc = unsafe { self.rx.read_at(start.wrapping_add(i as u32)) };
+            packets.push(Packet::new(desc.addr, desc.len as usize, self.umem.clone(), self.shared_state.clone()));
+        }
+        self.rx.release(count as u32);
+
+        packets
+    }
 }
@@ -1,4 +1,9 @@
 use crossbeam_queue::SegQueue;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(debug_assertions)]
+use std::collections::HashSet;
+#[cfg(debug_assertions)]
+use std::sync::Mutex;
 
 
 /// Shared state between FluxRx (Consumer) and all Packet (Owned) instances.
@@ -7,17 +12,130 @@ use crossbeam_queue::SegQueue;
 pub(crate) struct SharedFrameState {
     /// Lock-free queue of frame indices that are "free" (dropped by user)
     /// but not yet returned to the kernel.
-    pub(crate) free_frames: SegQueue<u64>,
+    free_frames: SegQueue<u64>,
+    /// Total frames in the UMEM. The free list can never legitimately hold more.
+    capacity: usize,
+    /// Frames not queued because the free list was already at `capacity`.
+    overflowed: AtomicU64,
+    /// Addresses currently on the free list, to catch double recycles.
+    #[cfg(debug_assertions)]
+    queued: Mutex<HashSet<u64>>,
 }
 
 impl SharedFrameState {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(frame_count: u32) -> Self {
         Self {
             free_frames: SegQueue::new(),
+            capacity: frame_count as usize,
+            overflowed: AtomicU64::new(0),
+            #[cfg(debug_assertions)]
+            queued: Mutex::new(HashSet::new()),
         }
     }
 
+    /// Return a frame to the free list.
+    ///
+    /// Recycling a frame that is already queued, or more frames than the UMEM has,
+    /// is a frame-accounting bug: it panics in debug builds. In release builds the
+    /// free list is capped at `frame_count` and the excess is counted in `overflowed`,
+    /// so such a bug cannot grow it without bound.
     pub(crate) fn recycle(&self, frame_idx: u64) {
+        #[cfg(debug_assertions)]
+        {
+            let mut queued = self.queued.lock().unwrap_or_else(|e| e.into_inner());
+            assert!(queued.insert(frame_idx), "frame {:#x} recycled twice", frame_idx);
+        }
+
+        if self.free_frames.len() >= self.capacity {
+            self.overflow(1);
+            return;
+        }
         self.free_frames.push(frame_idx);
     }
+
+    /// Return a batch of frames to the free list, with a single debug-check lock.
+    pub(crate) fn recycle_all(&self, frames: &[u64]) {
+        #[cfg(debug_assertions)]
+        {
+            let mut queued = self.queued.lock().unwrap_or_else(|e| e.into_inner());
+            for &frame_idx in frames {
+                assert!(queued.insert(frame_idx), "frame {:#x} recycled twice", frame_idx);
+            }
+        }
+
+        let room = self.capacity.saturating_sub(self.free_frames.len());
+        for &frame_idx in frames.iter().take(room) {
+            self.free_frames.push(frame_idx);
+        }
+        if frames.len() > room {
+            self.overflow((frames.len() - room) as u64);
+        }
+    }
+
+    /// Frames dropped because the free list was full. Non-zero means frames were
+    /// recycled that the free list can never legitimately hold.
+    pub(crate) fn overflowed(&self) -> u64 {
+        self.overflowed.load(Ordering::Relaxed)
+    }
+
+    fn overflow(&self, count: u64) {
+        self.overflowed.fetch_add(count, Ordering::Relaxed);
+        debug_assert!(false, "free list full: {} frame(s) past capacity {}", count, self.capacity);
+    }
+
+    /// Pop a frame to hand back to the kernel.
+    pub(crate) fn take(&self) -> Option<u64> {
+        let frame = self.free_frames.pop()?;
+        #[cfg(debug_assertions)]
+        self.queued.lock().unwrap_or_else(|e| e.into_inner()).remove(&frame);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recycle_and_take() {
+        let state = SharedFrameState::new(4);
+        state.recycle(0);
+        state.recycle(2048);
+
+        assert_eq!(state.take(), Some(0));
+        // Taken frames may be recycled again on their next trip
+        state.recycle(0);
+        assert_eq!(state.take(), Some(2048));
+        assert_eq!(state.take(), Some(0));
+        assert_eq!(state.take(), None);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_recycle_all_is_capped() {
+        let state = SharedFrameState::new(2);
+        state.recycle_all(&[0, 2048, 4096]);
+        state.recycle(6144);
+        assert_eq!(state.overflowed(), 2);
+        assert_eq!(state.take(), Some(0));
+        assert_eq!(state.take(), Some(2048));
+        assert_eq!(state.take(), None);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "free list full")]
+    fn test_recycle_past_capacity_panics() {
+        let state = SharedFrameState::new(2);
+        state.recycle_all(&[0, 2048, 4096]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "recycled twice")]
+    fn test_double_recycle_panics() {
+        let state = SharedFrameState::new(4);
+        state.recycle(2048);
+        state.recycle(2048);
+    }
 }
@@ -4,6 +4,11 @@ use fluxcapacitor_core::umem::mmap::UmemRegion;
 use std::sync::Arc;
 use crate::packet::Packet;
 use fluxcapacitor_core::sys::socket::RawFd;
+use crate::system::shared::SharedFrameState;
+use fluxcapacitor_core::sys::if_xdp::{XskTxMetadata, XDP_PKT_CONTD, XDP_TX_METADATA, XDP_TXMD_FLAGS_LAUNCH_TIME};
+use fluxcapacitor_core::sys::utils::clock_tai_ns;
+use std::io;
+use std::time::Instant;
 
 pub struct FluxTx {
     tx: ProducerRing<XDPDesc>,
@@ -12,9 +17,30 @@ pub struct FluxTx {
     comp: ConsumerRing<u64>,
     #[allow(dead_code)]
     comp_map: MmapArea,
-    #[allow(dead_code)]
     umem: Arc<UmemRegion>,
     fd: RawFd,
+    shared_state: Arc<SharedFrameState>,
+    // Frames the kernel reported as sent, for debugging frame leaks.
+    completions_reclaimed: u64,
+    on_complete: Option<Box<dyn FnMut(u64) + Send>>,
+    // Reused by `reclaim` to batch addresses into the shared pool.
+    reclaim_buf: Vec<u64>,
+    // Bound with XDP_USE_SG: `send_sg` may chain frames.
+    multi_buffer: bool,
+    // TX-only frames (`FluxBuilder::tx_reserve_frames`), kept topped up to
+    // `reserve_target` from completions.
+    reserve: Vec<u64>,
+    reserve_target: usize,
+    // UMEM registered with room for `XskTxMetadata` (`FluxBuilder::tx_launch_time`).
+    launch_time: bool,
+    // Leave the kick to `flush` (`set_buffered`).
+    buffered: bool,
+    // Descriptors submitted since the last successful kick.
+    pending: usize,
+    // Kicks that failed after an unbuffered send; the descriptors stay pending.
+    kick_errors: u64,
+    // Bound with XDP_USE_NEED_WAKEUP: only kick when the TX Ring asks for it.
+    need_wakeup: bool,
 }
 
 unsafe impl Send for FluxTx {}
@@ -23,16 +49,113 @@ impl FluxTx {
     pub(crate) fn new(
         tx: ProducerRing<XDPDesc>, tx_map: MmapArea,
         comp: ConsumerRing<u64>, comp_map: MmapArea,
-        umem: Arc<UmemRegion>, fd: RawFd
+        umem: Arc<UmemRegion>, fd: RawFd, shared_state: Arc<SharedFrameState>
     ) -> Self {
-        Self { tx, tx_map, comp, comp_map, umem, fd }
+        Self { tx, tx_map, comp, comp_map, umem, fd, shared_state, completions_reclaimed: 0, on_complete: None, reclaim_buf: Vec::new(), multi_buffer: false, reserve: Vec::new(), reserve_target: 0, launch_time: false, buffered: false, pending: 0, kick_errors: 0, need_wakeup: false }
+    }
+
+    pub(crate) fn set_reserve(&mut self, frames: Vec<u64>) {
+        self.reserve_target = frames.len();
+        self.reserve = frames;
+    }
+
+    pub(crate) fn set_multi_buffer(&mut self, enabled: bool) {
+        self.multi_buffer = enabled;
+    }
+
+    pub(crate) fn set_launch_time(&mut self, enabled: bool) {
+        self.launch_time = enabled;
+    }
+
+    pub(crate) fn set_need_wakeup(&mut self, enabled: bool) {
+        self.need_wakeup = enabled;
+    }
+
+    /// Call `f` with the UMEM address of every frame `reclaim` reads back from the
+    /// Completion Ring, e.g. to release application state tied to that frame.
+    ///
+    /// Runs before the frame is recycled, so the frame still holds the sent data.
+    /// Replaces any previous callback.
+    pub fn on_complete<F>(&mut self, f: F)
+    where
+        F: FnMut(u64) + Send + 'static,
+    {
+        self.on_complete = Some(Box::new(f));
     }
 
     pub fn fd(&self) -> RawFd {
         self.fd
     }
+
+    /// Only queue packets on the TX Ring when sending, and leave waking the kernel
+    /// to `flush` (default off: every send kicks).
+    ///
+    /// A burst then costs one `sendto` instead of one per packet. Nothing is
+    /// transmitted until the next `flush`, so call it after each burst.
+    pub fn set_buffered(&mut self, buffered: bool) {
+        self.buffered = buffered;
+    }
+
+    /// Descriptors queued on the TX Ring since the last successful `flush`.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Wake the kernel to transmit everything queued, with a single syscall.
+    /// Does nothing if nothing is pending. On error the descriptors stay pending,
+    /// so the next `flush` (or unbuffered send) kicks for them again.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+        self.kick_if_needed()?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Kicks that failed after an unbuffered send. The descriptors were queued
+    /// regardless and go out with the next kick that succeeds.
+    pub fn kick_errors(&self) -> u64 {
+        self.kick_errors
+    }
+
+    /// Wake the kernel to transmit, unless it doesn't need it. Returns whether
+    /// a `sendto` was issued.
+    ///
+    /// With `XDP_USE_NEED_WAKEUP` (`FluxBuilder::bind_flags`) the kernel sets a
+    /// flag on the TX Ring when it stopped processing it, and only then is the
+    /// syscall needed. Without it, every call kicks.
+    pub fn kick_if_needed(&mut self) -> io::Result<bool> {
+        if self.need_wakeup && !self.tx.needs_wakeup() {
+            return Ok(false);
+        }
+        // The simulator transmits whatever is on the ring when asked.
+        #[cfg(target_os = "linux")]
+        fluxcapacitor_core::sys::socket::kick_tx(self.fd)?;
+        Ok(true)
+    }
+
+    // Publish TX descriptors up to `idx`, `count` of them, and kick unless buffered.
+    fn submit(&mut self, idx: u32, count: usize) {
+        self.tx.submit(idx);
+        self.pending += count;
+        if !self.buffered && self.flush().is_err() {
+            self.kick_errors += 1;
+        }
+    }
     
+    /// Queue `packet` for transmit with no descriptor options.
+    /// See `send_with_options`.
     pub fn send(&mut self, packet: Packet) {
+        self.send_with_options(packet, 0)
+    }
+
+    /// Queue `packet` for transmit with `options` in its TX descriptor, e.g.
+    /// `XDP_TX_METADATA` or `XDP_PKT_CONTD` (see `sys::if_xdp`).
+    ///
+    /// The options are passed to the kernel as-is. The packet is dropped (its frame
+    /// recycled) if the TX Ring is full.
+    pub fn send_with_options(&mut self, packet: Packet, options: u32) {
         // 1. Reclaim completed frames
         self.reclaim();
         
@@ -41,35 +164,410 @@ impl FluxTx {
             let desc = XDPDesc {
                 addr: packet.addr,
                 len: packet.len as u32,
-                options: 0,
+                options,
             };
             
             unsafe { self.tx.write_at(idx, desc) };
-            self.tx.submit(idx.wrapping_add(1));
+            self.submit(idx.wrapping_add(1), 1);
             
             std::mem::forget(packet);
         } else {
             drop(packet); 
         }
     }
+
+    /// Take a frame from the shared free pool (dropped packets and completed TX)
+    /// for staging an outbound packet, falling back to the TX reserve
+    /// (`FluxBuilder::tx_reserve_frames`). `None` if every frame is in use.
+    ///
+    /// The frame belongs to the caller until it is passed to `send_frame` or
+    /// returned with `free_frame`.
+    pub fn alloc_frame(&mut self) -> Option<u64> {
+        self.reclaim();
+        self.shared_state.take().or_else(|| self.reserve.pop())
+    }
+
+    /// Return a frame from `alloc_frame` that will not be sent.
+    pub fn free_frame(&mut self, addr: u64) {
+        if self.reserve.len() < self.reserve_target {
+            self.reserve.push(addr);
+        } else {
+            self.shared_state.recycle(addr);
+        }
+    }
+
+    /// Frames currently waiting in the TX reserve.
+    pub fn reserved_frames(&self) -> usize {
+        self.reserve.len()
+    }
+
+    /// Copy `data` into `addr` (from `alloc_frame`) and queue it for transmit.
+    ///
+    /// Returns `false` if `data` does not fit in the frame or the TX Ring is full;
+    /// the frame then still belongs to the caller.
+    pub fn send_frame(&mut self, addr: u64, data: &[u8]) -> bool {
+        self.send_frame_parts(addr, &[data])
+    }
+
+    // `send_frame` with the frame assembled from consecutive `parts`.
+    fn send_frame_parts(&mut self, addr: u64, parts: &[&[u8]]) -> bool {
+        let Some(idx) = self.tx.reserve(1) else {
+            return false;
+        };
+        let mut len = 0;
+        for part in parts {
+            if self.umem.write_frame(addr + len as u64, part).is_err() {
+                return false;
+            }
+            len += part.len();
+        }
+
+        let desc = XDPDesc {
+            addr,
+            len: len as u32,
+            options: 0,
+        };
+        unsafe { self.tx.write_at(idx, desc) };
+        self.submit(idx.wrapping_add(1), 1);
+        true
+    }
+
+    /// Copy `data` into a free UMEM frame and queue it for transmit.
+    ///
+    /// Returns `false` if no frame is free (see `alloc_frame`), if `data` does not
+    /// fit in a frame, or if the TX Ring is full.
+    pub fn send_bytes(&mut self, data: &[u8]) -> bool {
+        let Some(addr) = self.alloc_frame() else {
+            return false;
+        };
+        if !self.send_frame(addr, data) {
+            self.free_frame(addr);
+            return false;
+        }
+        true
+    }
     
-    pub fn reclaim(&mut self) {
-        let n = self.comp.peek(32); // Batch 32
-        if n > 0 {
-             // Read completed frames
-             for i in 0..n {
-                 let _addr = unsafe { self.comp.read_at(self.comp.consumer_idx() + i as u32) };
-                 // Here we would normally return _addr to the free pool / fill ring.
-                 // But FluxTx doesn't have access to Fill Ring! (Rx has it).
-                 
-                 // Design constraint: "FluxTx: Exclusive owner of Transmit Ring and Completion Ring"
-                 // "FluxRx: Exclusive owner of Receive Ring and Fill Ring"
-                 
-                 // So completed TX frames must be sent back to FluxRx to be put into Fill Ring?
-                 // This requires a channel between Tx and Rx.
-                 // Or a shared Free List.
-             }
-             self.comp.release(n as u32);
+    /// Build an Ethernet frame (destination, source, EtherType, then `payload`) in a
+    /// free UMEM frame and queue it for transmit, for L2-only traffic like LLDP or
+    /// custom EtherTypes. No padding is added for short payloads.
+    ///
+    /// Returns `false` like `send_bytes`.
+    pub fn send_eth(&mut self, dst: [u8; 6], src: [u8; 6], eth_type: u16, payload: &[u8]) -> bool {
+        let mut header = [0u8; 14];
+        header[0..6].copy_from_slice(&dst);
+        header[6..12].copy_from_slice(&src);
+        header[12..14].copy_from_slice(&eth_type.to_be_bytes());
+
+        let Some(addr) = self.alloc_frame() else {
+            return false;
+        };
+        if !self.send_frame_parts(addr, &[&header, payload]) {
+            self.free_frame(addr);
+            return false;
         }
+        true
+    }
+
+    /// Whether `send_at` hands launch times to the NIC (`FluxBuilder::tx_launch_time`
+    /// and a kernel that accepted TX metadata) instead of sleeping.
+    pub fn hw_launch_time(&self) -> bool {
+        self.launch_time
+    }
+
+    /// Copy `data` into a free UMEM frame and queue it to leave the NIC at `when`,
+    /// for paced or scheduled traffic.
+    ///
+    /// With `hw_launch_time`, the frame carries `XskTxMetadata` asking for a launch
+    /// time on `CLOCK_TAI` (the NIC needs ETF/launch-time offload configured on the
+    /// queue) and this returns at once. Otherwise it sleeps until `when` and sends,
+    /// so the pacing is only as precise as the scheduler. A `when` in the past sends
+    /// immediately.
+    ///
+    /// Fails with `InvalidInput` if `data` does not fit in a frame (less the
+    /// metadata), and with `WouldBlock` if no frame or TX Ring slot is free.
+    pub fn send_at(&mut self, data: &[u8], when: Instant) -> io::Result<()> {
+        let meta_len = if self.launch_time { std::mem::size_of::<XskTxMetadata>() } else { 0 };
+        if data.len() + meta_len > self.umem.layout().frame_size as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "packet does not fit in a frame"));
+        }
+        if !self.launch_time {
+            std::thread::sleep(when.saturating_duration_since(Instant::now()));
+            return if self.send_bytes(data) { Ok(()) } else { Err(io::ErrorKind::WouldBlock.into()) };
+        }
+
+        let Some(addr) = self.alloc_frame() else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        let Some(idx) = self.tx.reserve(1) else {
+            self.free_frame(addr);
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        // The kernel reads the metadata right before the frame data, in the same chunk.
+        let base = addr - addr % self.umem.layout().chunk_size as u64;
+        let meta = XskTxMetadata {
+            flags: XDP_TXMD_FLAGS_LAUNCH_TIME,
+            launch_time: clock_tai_ns() + when.saturating_duration_since(Instant::now()).as_nanos() as u64,
+            ..Default::default()
+        };
+        let meta_bytes = unsafe { std::slice::from_raw_parts(&meta as *const XskTxMetadata as *const u8, meta_len) };
+        // Fits: checked against frame_size above.
+        let _ = self.umem.write_frame(base, meta_bytes);
+        let _ = self.umem.write_frame(base + meta_len as u64, data);
+
+        let desc = XDPDesc {
+            addr: base + meta_len as u64,
+            len: data.len() as u32,
+            options: XDP_TX_METADATA,
+        };
+        unsafe { self.tx.write_at(idx, desc) };
+        self.submit(idx.wrapping_add(1), 1);
+        Ok(())
+    }
+
+    /// Send one packet made of `chunks`, each copied into its own UMEM frame and
+    /// chained with `XDP_PKT_CONTD`, so the NIC transmits them as a single frame.
+    ///
+    /// Needs a socket bound with `XDP_USE_SG` (`ErrorKind::Unsupported` otherwise).
+    /// Either the whole chain is queued or nothing is: fails with `InvalidInput` if
+    /// there are no chunks or one does not fit in a frame, and with `WouldBlock` if
+    /// there are not enough free frames or TX Ring slots.
+    pub fn send_sg(&mut self, chunks: &[&[u8]]) -> io::Result<()> {
+        if !self.multi_buffer {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "socket not bound with XDP_USE_SG"));
+        }
+        let frame_size = self.umem.layout().frame_size as usize;
+        if chunks.is_empty() || chunks.iter().any(|c| c.len() > frame_size) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunks must be 1 to frame_size bytes each"));
+        }
+        if self.tx.available() < chunks.len() as u32 {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let chunk_size = self.umem.layout().chunk_size as u64;
+        let mut frames = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let Some(addr) = self.alloc_frame() else {
+                for addr in frames {
+                    self.free_frame(addr);
+                }
+                return Err(io::ErrorKind::WouldBlock.into());
+            };
+            // Completed frames can come back offset into their chunk; start each
+            // chunk at the base so a full frame_size chunk fits.
+            let addr = addr - addr % chunk_size;
+            if let Err(e) = self.umem.write_frame(addr, chunk) {
+                self.free_frame(addr);
+                for addr in frames {
+                    self.free_frame(addr);
+                }
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+            frames.push(addr);
+        }
+
+        let Some(start) = self.tx.reserve(chunks.len() as u32) else {
+            for addr in frames {
+                self.free_frame(addr);
+            }
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        let last = chunks.len() - 1;
+        for (i, (addr, chunk)) in frames.into_iter().zip(chunks).enumerate() {
+            let desc = XDPDesc {
+                addr,
+                len: chunk.len() as u32,
+                options: if i < last { XDP_PKT_CONTD } else { 0 },
+            };
+            unsafe { self.tx.write_at(start.wrapping_add(i as u32), desc) };
+        }
+        self.submit(start.wrapping_add(chunks.len() as u32), chunks.len());
+        Ok(())
+    }
+
+    /// Iterate the addresses on the Completion Ring, for custom reclaim policies.
+    ///
+    /// Yielded frames belong to the caller, who must eventually hand them back
+    /// (`free_frame`, `send_frame`) or keep them out of circulation for good. Only
+    /// yielded entries are released from the ring, when the iterator is dropped, so
+    /// stopping early leaves the rest for the next call or `reclaim`. `on_complete`
+    /// is not called, and the TX reserve is not topped up. Methods that reclaim
+    /// implicitly (`alloc_frame`, `send`) still drain the ring into the pool.
+    pub fn completions(&mut self) -> CompletionIter<'_> {
+        let available = self.comp.peek(self.comp.len()) as u32;
+        let start = self.comp.consumer_idx();
+        CompletionIter { comp: &mut self.comp, start, available, taken: 0, reclaimed: &mut self.completions_reclaimed }
+    }
+
+    /// Drain the Completion Ring into the shared free pool.
+    /// Returns the number of frames reclaimed.
+    pub fn reclaim(&mut self) -> usize {
+        self.reclaim_buf.clear();
+        loop {
+            let n = self.comp.peek(self.comp.len());
+            if n == 0 {
+                break;
+            }
+            let start = self.comp.consumer_idx();
+            for i in 0..n {
+                let addr = unsafe { self.comp.read_at(start.wrapping_add(i as u32)) };
+                if let Some(on_complete) = self.on_complete.as_mut() {
+                    on_complete(addr);
+                }
+                self.reclaim_buf.push(addr);
+            }
+            self.comp.release(n as u32);
+        }
+
+        // FluxTx doesn't own the Fill Ring (FluxRx does), so completed
+        // frames go back through the shared free list, once the reserve is full.
+        let n = self.reclaim_buf.len();
+        let top_up = (self.reserve_target - self.reserve.len()).min(n);
+        self.reserve.extend(self.reclaim_buf.drain(..top_up));
+        self.shared_state.recycle_all(&self.reclaim_buf);
+        self.completions_reclaimed += n as u64;
+        n
+    }
+
+    /// Free slots on the TX Ring: how many frames `send` can queue before the
+    /// kernel consumes some.
+    pub fn tx_capacity(&self) -> usize {
+        self.tx.available() as usize
+    }
+
+    /// Total frames read back from the Completion Ring by `reclaim` and `completions`.
+    pub fn completions_reclaimed(&self) -> u64 {
+        self.completions_reclaimed
+    }
+}
+
+/// Completed TX frame addresses from `FluxTx::completions`. Releases the yielded
+/// entries from the Completion Ring on drop.
+pub struct CompletionIter<'a> {
+    comp: &'a mut ConsumerRing<u64>,
+    start: u32,
+    available: u32,
+    taken: u32,
+    reclaimed: &'a mut u64,
+}
+
+impl Iterator for CompletionIter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.taken == self.available {
+            return None;
+        }
+        let addr = unsafe { self.comp.read_at(self.start.wrapping_add(self.taken)) };
+        self.taken += 1;
+        Some(addr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.available - self.taken) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for CompletionIter<'_> {}
+
+impl Drop for CompletionIter<'_> {
+    fn drop(&mut self) {
+        self.comp.release(self.taken);
+        *self.reclaimed += self.taken as u64;
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::raw::af_packet::ring_pair;
+    use fluxcapacitor_core::sys::if_xdp::XDP_RING_NEED_WAKEUP;
+    use fluxcapacitor_core::umem::layout::UmemLayout;
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+
+    // Kicks received by `sink`: each `sendto` on the connected socket is an empty datagram.
+    fn kicks(sink: &UdpSocket) -> usize {
+        let mut buf = [0u8; 1];
+        std::iter::from_fn(|| sink.recv(&mut buf).ok()).count()
+    }
+
+    #[test]
+    fn test_kick_if_needed_follows_ring_flag() {
+        let sink = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind");
+        sink.set_nonblocking(true).expect("Failed to set nonblocking");
+        let kicker = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind");
+        kicker.connect(sink.local_addr().unwrap()).expect("Failed to connect");
+
+        let mut flags = Box::new(0u32);
+        let flags_ptr: *mut u32 = &mut *flags;
+        let umem = UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem");
+        let (tx_map, tx, _) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(4).expect("Failed to map ring");
+        let tx = unsafe { tx.with_flags(flags_ptr) };
+        let mut tx = FluxTx::new(
+            tx, tx_map, comp, comp_map,
+            Arc::new(umem), kicker.as_raw_fd(), Arc::new(SharedFrameState::new(4)),
+        );
+
+        // Without XDP_USE_NEED_WAKEUP every call kicks
+        assert!(tx.kick_if_needed().unwrap());
+        assert_eq!(kicks(&sink), 1);
+
+        // With it, only while the kernel has the flag set
+        tx.set_need_wakeup(true);
+        assert!(!tx.kick_if_needed().unwrap());
+        assert_eq!(kicks(&sink), 0);
+
+        unsafe { std::ptr::write_volatile(flags_ptr, XDP_RING_NEED_WAKEUP) };
+        assert!(tx.kick_if_needed().unwrap());
+        assert!(tx.kick_if_needed().unwrap());
+        assert_eq!(kicks(&sink), 2);
+
+        unsafe { std::ptr::write_volatile(flags_ptr, 0) };
+        assert!(!tx.kick_if_needed().unwrap());
+        assert_eq!(kicks(&sink), 0);
+    }
+
+    #[test]
+    fn test_failed_kick_keeps_descriptors_pending() {
+        let umem = UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem");
+        let (tx_map, tx, _) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(4).expect("Failed to map ring");
+        // Not a socket: every kick fails
+        let mut tx = FluxTx::new(tx, tx_map, comp, comp_map, Arc::new(umem), -1, Arc::new(SharedFrameState::new(4)));
+        tx.set_reserve(vec![0, 2048]);
+
+        assert!(tx.send_bytes(b"first"));
+        assert!(tx.send_bytes(b"second"));
+        assert_eq!(tx.kick_errors(), 2);
+        assert_eq!(tx.pending(), 2);
+        assert!(tx.flush().is_err());
+        assert_eq!(tx.pending(), 2);
+    }
+
+    #[test]
+    fn test_send_sg_writes_offset_frames_at_chunk_base() {
+        let sink = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind");
+        let kicker = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind");
+        kicker.connect(sink.local_addr().unwrap()).expect("Failed to connect");
+
+        let umem = UmemRegion::new(UmemLayout::new(2048, 4)).expect("Failed to create umem");
+        let (tx_map, tx, mut tx_ring) = ring_pair::<XDPDesc>(4).expect("Failed to map ring");
+        let (comp_map, _, comp) = ring_pair::<u64>(4).expect("Failed to map ring");
+        let shared = Arc::new(SharedFrameState::new(4));
+        let mut tx = FluxTx::new(tx, tx_map, comp, comp_map, Arc::new(umem), kicker.as_raw_fd(), shared.clone());
+        tx.set_multi_buffer(true);
+
+        // Completed frames come back with their headroom offset
+        shared.recycle(256);
+        shared.recycle(2048 + 256);
+        let full = [0xAB; 2048];
+        tx.send_sg(&[&full, b"tail"]).expect("send_sg failed");
+
+        assert_eq!(tx_ring.peek(2), 2);
+        let descs = unsafe { [tx_ring.read_at(tx_ring.consumer_idx()), tx_ring.read_at(tx_ring.consumer_idx().wrapping_add(1))] };
+        assert_eq!(descs.map(|d| d.addr % 2048), [0, 0]);
+        assert_eq!(descs.map(|d| d.len), [2048, 4]);
     }
 }
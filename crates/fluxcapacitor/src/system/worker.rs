@@ -0,0 +1,105 @@
+use crate::config::AdaptiveIdle;
+use crate::packet::Packet;
+use crate::raw::FluxRaw;
+use crate::system::{FluxRx, FluxTx};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// What `FluxWorker` does with a received packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Transmit the packet, with any changes the callback made.
+    Forward,
+    /// Turn the packet into its reply (`Packet::make_reply`) and transmit it.
+    /// Packets that are not IP are dropped instead.
+    Reply,
+    /// Recycle the frame.
+    Drop,
+}
+
+/// The receive, process, send loop over a `FluxRx`/`FluxTx` pair.
+///
+/// Each packet goes to the callback, whose `Verdict` decides its fate; frames move
+/// between the Fill Ring, the TX Ring and the shared pool without the callback
+/// seeing them. Sends are buffered (`FluxTx::set_buffered`) and flushed once per
+/// batch.
+///
+/// ```ignore
+/// let mut worker = FluxWorker::new(FluxBuilder::new("eth0").build_raw()?);
+/// worker.run(&stop, |_pkt| Verdict::Reply)?;
+/// ```
+pub struct FluxWorker {
+    rx: FluxRx,
+    tx: FluxTx,
+    batch_size: usize,
+}
+
+impl FluxWorker {
+    /// Split `socket` and drive both halves.
+    pub fn new(socket: FluxRaw) -> Self {
+        let (rx, tx) = crate::system::split(socket);
+        Self::from_parts(rx, tx)
+    }
+
+    pub fn from_parts(rx: FluxRx, mut tx: FluxTx) -> Self {
+        tx.set_buffered(true);
+        Self { rx, tx, batch_size: 64 }
+    }
+
+    /// Most packets handled per `poll` (default 64, at least 1).
+    pub fn set_batch_size(&mut self, size: usize) {
+        self.batch_size = size.max(1);
+    }
+
+    /// Receive one batch, apply `callback`'s verdicts and kick the TX Ring once.
+    /// Returns the number of packets received.
+    pub fn poll<F>(&mut self, callback: &mut F) -> io::Result<usize>
+    where
+        F: FnMut(&mut Packet) -> Verdict,
+    {
+        // Completed frames go back to the pool, for `recv` to refill from.
+        self.tx.reclaim();
+        let packets = self.rx.recv(self.batch_size);
+        let count = packets.len();
+        for mut packet in packets {
+            match callback(&mut packet) {
+                Verdict::Forward => self.tx.send(packet),
+                Verdict::Reply if packet.make_reply() => self.tx.send(packet),
+                Verdict::Reply | Verdict::Drop => {}
+            }
+        }
+        self.tx.flush()?;
+        Ok(count)
+    }
+
+    /// `poll` until `stop` is set. When idle, yields for a moment, then naps for a
+    /// millisecond at a time, like `Poller::Adaptive`.
+    pub fn run<F>(&mut self, stop: &AtomicBool, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(&mut Packet) -> Verdict,
+    {
+        let mut adaptive = AdaptiveIdle::new(Instant::now());
+        while !stop.load(Ordering::Relaxed) {
+            if self.poll(&mut callback)? > 0 {
+                adaptive.packet(Instant::now());
+            } else {
+                adaptive.idle(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rx(&mut self) -> &mut FluxRx {
+        &mut self.rx
+    }
+
+    pub fn tx(&mut self) -> &mut FluxTx {
+        &mut self.tx
+    }
+
+    /// Give the halves back. The `FluxTx` stays buffered.
+    pub fn into_parts(self) -> (FluxRx, FluxTx) {
+        (self.rx, self.tx)
+    }
+}
@@ -0,0 +1,25 @@
+//! Frame fixtures shared by the unit tests and `tests/simulated_traffic.rs`.
+
+/// Eth + IPv4 + UDP from `src` to `dst` (address, port) carrying `payload`.
+///
+/// Lengths and TTL are filled in; MAC addresses and checksums are left zero.
+pub(crate) fn udp_frame(src: ([u8; 4], u16), dst: ([u8; 4], u16), payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0u8; 14 + 20 + 8 + payload.len()];
+    frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+
+    let ip = &mut frame[14..34];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&((20 + 8 + payload.len()) as u16).to_be_bytes());
+    ip[8] = 64;
+    ip[9] = 17;
+    ip[12..16].copy_from_slice(&src.0);
+    ip[16..20].copy_from_slice(&dst.0);
+
+    let udp = &mut frame[34..42];
+    udp[0..2].copy_from_slice(&src.1.to_be_bytes());
+    udp[2..4].copy_from_slice(&dst.1.to_be_bytes());
+    udp[4..6].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+
+    frame[42..].copy_from_slice(payload);
+    frame
+}
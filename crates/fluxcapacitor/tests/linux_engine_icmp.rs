@@ -82,15 +82,12 @@ mod linux_engine_icmp {
 
         // Write to TX UMEM
         let tx_addr = 0;
-        unsafe {
-            let dest = tx_raw.umem.as_ptr().add(tx_addr);
-            std::ptr::copy_nonoverlapping(pkt.as_ptr(), dest, pkt.len());
-        }
+        tx_raw.umem.write_frame(tx_addr, &pkt).expect("Failed to stage TX frame");
         
         // Send
         let idx = tx_raw.tx.reserve(1).unwrap();
         unsafe {
-            tx_raw.tx.write_at(idx, XDPDesc { addr: tx_addr as u64, len: pkt.len() as u32, options: 0 });
+            tx_raw.tx.write_at(idx, XDPDesc { addr: tx_addr, len: pkt.len() as u32, options: 0 });
         }
         tx_raw.tx.submit(idx + 1);
         tx_raw.wakeup_tx().unwrap();
@@ -106,15 +106,12 @@ mod linux_system_echo {
         // Send Packet
         let payload = vec![0xAA; 100];
         let tx_addr = 0;
-        unsafe {
-            let dest = client_raw.umem.as_ptr().add(tx_addr);
-            std::ptr::copy_nonoverlapping(payload.as_ptr(), dest, payload.len());
-        }
+        client_raw.umem.write_frame(tx_addr, &payload).expect("Failed to stage TX frame");
         
         // Send
         let idx = client_raw.tx.reserve(1).unwrap();
         unsafe {
-             client_raw.tx.write_at(idx, XDPDesc { addr: tx_addr as u64, len: payload.len() as u32, options: 0 });
+             client_raw.tx.write_at(idx, XDPDesc { addr: tx_addr, len: payload.len() as u32, options: 0 });
         }
         client_raw.tx.submit(idx + 1);
         client_raw.wakeup_tx().unwrap();
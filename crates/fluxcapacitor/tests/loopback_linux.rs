@@ -59,10 +59,7 @@ mod linux_loopback {
         frame_data.extend_from_slice(payload);
 
         let tx_addr = 0; // Use first frame of TX UMEM
-        unsafe {
-            let dest = tx_raw.umem.as_ptr().add(tx_addr as usize);
-            std::ptr::copy_nonoverlapping(frame_data.as_ptr(), dest, frame_data.len());
-        }
+        tx_raw.umem.write_frame(tx_addr, &frame_data).expect("Failed to stage TX frame");
 
         // 4. Send Packet
         let tx_idx = tx_raw.tx.reserve(1).expect("TX Ring full");
@@ -103,4 +100,52 @@ mod linux_loopback {
 
         assert!(received, "Did not receive the packet on veth1");
     }
+
+    #[test]
+    fn test_redirect_ports_written_to_map() {
+        // Needs root and the built eBPF object, like the veth test.
+        use aya::maps::{Array, HashMap};
+
+        let mut raw = FluxBuilder::new("veth1")
+            .queue_id(0)
+            .bind_flags(XDP_FLAGS_SKB_MODE)
+            .umem_pages(16)
+            .load_xdp(true)
+            .redirect_ports(&[53, 4789])
+            .build_raw()
+            .expect("Failed to load XDP on veth1");
+
+        let bpf = raw.bpf.as_mut().expect("XDP program not loaded");
+        let ports: HashMap<_, u16, u8> = bpf.map("REDIRECT_PORTS").expect("REDIRECT_PORTS missing").try_into().unwrap();
+        let mut wanted: Vec<u16> = ports.keys().map(|k| k.unwrap()).collect();
+        wanted.sort();
+        assert_eq!(wanted, [53, 4789]);
+        let config: Array<_, u32> = bpf.map("FILTER_CONFIG").expect("FILTER_CONFIG missing").try_into().unwrap();
+        assert_eq!(config.get(&0, 0).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rss_key_written_to_map() {
+        // Needs root and the built eBPF object, like the veth test.
+        use aya::maps::Array;
+        use fluxcapacitor::rss::RSS_KEY_LEN;
+
+        let key = [0x5Au8; RSS_KEY_LEN];
+        let mut raw = FluxBuilder::new("veth1")
+            .queue_id(0)
+            .bind_flags(XDP_FLAGS_SKB_MODE)
+            .umem_pages(16)
+            .load_xdp(true)
+            .rss_key(&key)
+            .build_raw()
+            .expect("Failed to load XDP on veth1");
+
+        let bpf = raw.bpf.as_mut().expect("XDP program not loaded");
+        let keys: Array<_, [u8; RSS_KEY_LEN]> = bpf.map("RSS_KEY").expect("RSS_KEY missing").try_into().unwrap();
+        assert_eq!(keys.get(&0, 0).unwrap(), key);
+        let config: Array<_, u32> = bpf.map("RSS_CONFIG").expect("RSS_CONFIG missing").try_into().unwrap();
+        assert!(config.get(&0, 0).unwrap() >= 1);
+        // Nothing received yet, so nothing landed on the wrong queue.
+        assert_eq!(raw.rss_mismatches().unwrap(), 0);
+    }
 }
@@ -13,7 +13,9 @@ mod raw_tests {
         assert_eq!(raw.tx.len(), 64);
 
         // Call debug helper
-        raw.debug_rings();
+        let rings = raw.debug_rings();
+        assert_eq!((rings.rx, rings.tx), ((0, 64), (64, 64)));
+        assert!(rings.to_string().starts_with("RX Ring:   0/64"));
         
         // Ensure initial state is sane in simulator
         // In simulator, producer/consumer are initialized to 0 usually.
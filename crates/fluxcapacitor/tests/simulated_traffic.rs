@@ -1,6 +1,11 @@
+#[cfg(all(feature = "simulator", not(target_os = "linux")))]
+#[path = "../src/test_util.rs"]
+mod test_util;
+
 #[cfg(all(feature = "simulator", not(target_os = "linux")))]
 #[cfg(test)]
 mod tests {
+    use crate::test_util::udp_frame;
     use fluxcapacitor::builder::FluxBuilder;
     use fluxcapacitor::engine::FluxEngine;
     use fluxcapacitor::simulator::control;
@@ -8,42 +13,921 @@ mod tests {
     use std::time::Duration;
 
     #[test]
-    fn test_simulated_echo_traffic() {
-        // 1. Setup Engine using FluxRaw
-        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+    fn test_simulated_echo_traffic() {
+        // 1. Setup Engine using FluxRaw
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+
+        // Engine consumes FluxRaw
+        let mut engine = FluxEngine::new(flux_raw, 16);
+        
+        let engine_thread = thread::spawn(move || {
+            // Run a few steps by manually polling `process_batch`
+            for _ in 0..10 {
+                let _ = engine.process_batch(&mut |batch| {
+                     let mut processed = false;
+                     for _packet in batch.iter_mut() {
+                         processed = true;
+                     }
+                     if processed {
+                         // println!("Processed batch");
+                     }
+                });
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        // 2. Inject Packet (Simulate Network Arrival)
+        let payload = vec![0xAA, 0xBB, 0xCC, 0xDD];
+        control::inject_packet(fd, &payload).expect("Failed to inject packet");
+
+        // 3. Wait for processing 
+        thread::sleep(Duration::from_millis(200));
+        
+        // 4. Verification
+        assert!(true, "Simulation logic executed without error");
+        
+        let _ = engine_thread.join();
+    }
+
+    #[test]
+    fn test_tx_failure_injection() {
+        use fluxcapacitor::engine::StatsPoller;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut engine = FluxEngine::new(flux_raw, 16);
+        let mut poller = StatsPoller::new(fd, engine.stats(), 1);
+        assert!(poller.poll().expect("Failed to poll stats").is_none());
+
+        control::set_tx_failure_rate(fd, 0.5).expect("Failed to set failure rate");
+
+        // 1. Echo a batch of packets back out
+        let sent = 8;
+        for i in 0..sent {
+            control::inject_packet(fd, &[i as u8; 64]).expect("Failed to inject packet");
+        }
+        let processed = engine.process_batch(&mut |batch| {
+            for mut packet in batch.iter_mut() {
+                packet.send();
+            }
+        }).expect("Batch processing failed");
+        assert_eq!(processed, sent);
+
+        // 2. Drain the TX ring: every descriptor is either delivered or counted as failed
+        let mut delivered = 0;
+        let mut failed = 0;
+        for _ in 0..sent {
+            match control::read_tx_packet(fd) {
+                Ok(_) => delivered += 1,
+                Err(_) => failed += 1,
+            }
+        }
+        assert_eq!(delivered + failed, sent);
+        assert_eq!(failed, sent / 2);
+        assert_eq!(control::tx_failures(fd).unwrap(), failed as u64);
+        assert!(control::read_tx_packet(fd).is_err(), "TX ring should be drained");
+
+        // 3. Failed frames are completed like sent ones: all 16 end up back on Fill
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        let mut fill = control::fill_ring_addrs(fd).unwrap();
+        fill.sort_unstable();
+        fill.dedup();
+        assert_eq!(fill.len(), 16, "TX failures leaked frames");
+
+        // 4. The engine's stats sample shows every send, and the failures beside them
+        let sample = poller.poll().expect("Failed to poll stats").expect("No sample");
+        assert_eq!(sample.engine.tx_packets, sent as u64);
+        assert_eq!(sample.kernel.tx_invalid_descs, failed as u64);
+    }
+
+    #[test]
+    fn test_rx_latency_delays_visibility() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut engine = FluxEngine::new(flux_raw, 16);
+
+        let delay = Duration::from_millis(100);
+        control::set_rx_latency(fd, delay, Duration::ZERO).expect("Failed to set latency");
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+
+        // 1. Not yet due: the RX ring must look empty
+        let count = engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(count, 0, "Packet surfaced before its delay elapsed");
+
+        // 2. After the delay it shows up
+        thread::sleep(delay);
+        let count = engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_frame_lifecycle_counters() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        // Initial fill hands every frame to the kernel
+        assert_eq!(rx.fills_submitted(), 16);
+
+        // 1. Receive a batch, echo some and drop the rest
+        let echoed = 4;
+        let dropped = 3;
+        for i in 0..(echoed + dropped) {
+            control::inject_packet(fd, &[i as u8; 64]).expect("Failed to inject packet");
+        }
+        let packets = rx.recv(16);
+        assert_eq!(packets.len(), echoed + dropped);
+
+        for (i, packet) in packets.into_iter().enumerate() {
+            if i < echoed {
+                tx.send(packet);
+            } else {
+                drop(packet);
+            }
+        }
+
+        // 2. Let the "NIC" transmit, then run the housekeeping paths
+        for _ in 0..echoed {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+        tx.reclaim();
+        rx.refill();
+
+        // Completed TX frames come back through the shared free list too
+        assert_eq!(tx.completions_reclaimed(), echoed as u64);
+        assert_eq!(rx.frames_recycled(), (echoed + dropped) as u64);
+        assert_eq!(rx.fills_submitted(), 16 + (echoed + dropped) as u64);
+    }
+
+    #[test]
+    fn test_demux_routes_by_port() {
+        use fluxcapacitor::system::{self, Demux};
+        use std::sync::mpsc;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split(flux_raw);
+
+        let (dns_tx, dns_rx) = mpsc::channel();
+        let (http_tx, http_rx) = mpsc::channel();
+        let demux = Demux::new().route(53, dns_tx).route(80, http_tx);
+
+        for port in [53, 80, 53, 9999] {
+            let frame = udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], port), &[]);
+            control::inject_packet(fd, &frame).expect("Failed to inject packet");
+        }
+        assert_eq!(demux.dispatch(&mut rx, 16), 3);
+
+        assert_eq!(dns_rx.try_iter().count(), 2);
+        assert_eq!(http_rx.try_iter().count(), 1);
+
+        // Every frame, including the unmatched one, comes back to the Fill Ring
+        rx.refill();
+        assert_eq!(rx.frames_recycled(), 4);
+    }
+
+    #[test]
+    fn test_simulator_is_copy_mode() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        assert!(!flux_raw.is_zerocopy());
+        let mut engine = FluxEngine::new(flux_raw, 16);
+
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        let mut seen = 0;
+        engine.process_batch(&mut |batch| {
+            for packet in batch.iter_mut() {
+                assert!(!packet.is_zerocopy());
+                seen += 1;
+            }
+        }).expect("Batch processing failed");
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn test_send_with_options() {
+        use fluxcapacitor::system;
+        use fluxcapacitor_core::sys::if_xdp::XDP_TX_METADATA;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        control::inject_packet(fd, &[0xAB; 64]).expect("Failed to inject packet");
+        control::inject_packet(fd, &[0xCD; 64]).expect("Failed to inject packet");
+        let mut packets = rx.recv(2).into_iter();
+
+        // Options reach the TX descriptor unchanged; plain `send` clears them
+        tx.send_with_options(packets.next().unwrap(), XDP_TX_METADATA);
+        let desc = control::peek_tx_desc(fd).expect("Failed to peek TX");
+        assert_eq!(desc.options, XDP_TX_METADATA);
+        assert_eq!(desc.len, 64);
+        control::read_tx_packet(fd).expect("Failed to read TX");
+
+        tx.send(packets.next().unwrap());
+        assert_eq!(control::peek_tx_desc(fd).expect("Failed to peek TX").options, 0);
+    }
+
+    #[test]
+    fn test_send_sg_chains_frames() {
+        use fluxcapacitor::system;
+        use fluxcapacitor_core::sys::if_xdp::{XDP_PKT_CONTD, XDP_USE_SG};
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).bind_flags(XDP_USE_SG);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        assert!(flux_raw.is_multi_buffer());
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        // Free three frames by dropping received packets
+        for _ in 0..3 {
+            control::inject_packet(fd, &[0u8; 64]).expect("Failed to inject packet");
+        }
+        drop(rx.recv(16));
+
+        let chunks: [&[u8]; 3] = [b"head", b"middle", b"tail"];
+        let too_many: [&[u8]; 4] = [&[0u8; 64]; 4];
+        assert_eq!(tx.send_sg(&too_many).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+        tx.send_sg(&chunks).expect("send_sg failed");
+
+        // CONTD on every descriptor but the last
+        for (i, chunk) in chunks.iter().enumerate() {
+            let desc = control::peek_tx_desc(fd).expect("Failed to peek TX");
+            let expected = if i < chunks.len() - 1 { XDP_PKT_CONTD } else { 0 };
+            assert_eq!(desc.options, expected, "descriptor {}", i);
+            assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), *chunk);
+        }
+    }
+
+    #[test]
+    fn test_send_sg_needs_multi_buffer() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let (_rx, mut tx) = system::split(builder.build_raw().expect("Failed to build raw socket"));
+        assert_eq!(tx.send_sg(&[&b"data"[..]]).unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_packet_survives_rx_drop() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        control::inject_packet(fd, &[0x5A; 64]).expect("Failed to inject packet");
+        let mut packet = rx.recv(1).pop().expect("No packet received");
+        drop(rx);
+
+        // The packet still reads and writes its frame
+        assert_eq!(packet.data(), &[0x5A; 64][..]);
+        packet.data_mut()[0] = 0xA5;
+        assert_eq!(packet.data()[0], 0xA5);
+
+        // Dropping it recycles the frame into the pool the TX half still shares
+        let addr = packet.addr();
+        drop(packet);
+        assert_eq!(tx.alloc_frame(), Some(addr));
+    }
+
+    #[test]
+    fn test_send_bytes() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        // Every frame starts out on the Fill Ring
+        assert!(!tx.send_bytes(b"hello"), "No free frame should be available yet");
+
+        // Dropping a received packet frees a frame
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        let packets = rx.recv(16);
+        assert_eq!(packets.len(), 1);
+        drop(packets);
+
+        assert!(!tx.send_bytes(&[0u8; 4096]), "Oversized data must be rejected");
+        assert!(tx.send_bytes(b"hello"));
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"hello");
+    }
+
+    #[test]
+    fn test_send_eth() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).tx_reserve_frames(1);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (_rx, mut tx) = system::split(flux_raw);
+
+        let src = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+        assert!(tx.send_eth([0xFF; 6], src, 0xAABB, b"custom"));
+        let frame = control::read_tx_packet(fd).expect("Failed to read TX");
+        let (eth, payload) = fluxcapacitor_proto::parse_eth(&frame).expect("Should parse eth");
+        assert_eq!(eth.dst, [0xFF; 6]);
+        assert_eq!(eth.src, src);
+        assert_eq!(eth.eth_type(), 0xAABB);
+        assert_eq!(payload, b"custom");
+
+        // Too big for a frame once the header is added: the frame is not lost
+        tx.reclaim();
+        assert!(!tx.send_eth([0xFF; 6], src, 0xAABB, &[0u8; 2040]));
+        assert_eq!(tx.reserved_frames(), 1);
+    }
+
+    #[test]
+    fn test_send_at_launch_time() {
+        use fluxcapacitor::system;
+        use std::time::{Duration, Instant};
+
+        // The simulator accepts TX metadata: the launch time rides with the frame
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).tx_reserve_frames(2).tx_launch_time(true);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (_rx, mut tx) = system::split(flux_raw);
+        assert!(tx.hw_launch_time());
+
+        let before = fluxcapacitor_core::sys::utils::clock_tai_ns();
+        let start = Instant::now();
+        tx.send_at(b"paced", start + Duration::from_millis(500)).expect("send_at failed");
+        // Queued at once rather than slept on
+        assert!(start.elapsed() < Duration::from_millis(500));
+        let launch = control::peek_tx_launch_time(fd).expect("Failed to peek TX").expect("No launch time");
+        assert!(launch >= before + 400_000_000 && launch <= before + 2_000_000_000);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"paced");
+
+        // Metadata takes room from the frame
+        assert_eq!(tx.send_at(&[0u8; 2048], Instant::now()).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_send_at_software_pacing() {
+        use fluxcapacitor::system;
+        use std::time::{Duration, Instant};
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).tx_reserve_frames(1);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (_rx, mut tx) = system::split(flux_raw);
+        assert!(!tx.hw_launch_time());
+
+        let start = Instant::now();
+        tx.send_at(b"late", start + Duration::from_millis(20)).expect("send_at failed");
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(control::peek_tx_launch_time(fd).expect("Failed to peek TX"), None);
+
+        // The only frame is in flight until the "NIC" completes it
+        assert_eq!(tx.send_at(b"again", Instant::now()).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"late");
+        tx.send_at(b"again", Instant::now()).expect("send_at failed");
+    }
+
+    #[test]
+    fn test_completions_manual() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).tx_reserve_frames(3);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (_rx, mut tx) = system::split(flux_raw);
+
+        let sent: Vec<u64> = (0..3).map(|_| tx.alloc_frame().expect("Reserve frame")).collect();
+        for &addr in &sent {
+            assert!(tx.send_frame(addr, b"done"));
+        }
+        for _ in 0..3 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+
+        // Stop after one: the rest stay on the ring
+        let mut completions = tx.completions();
+        assert_eq!(completions.len(), 3);
+        let first = completions.next().expect("Completion");
+        drop(completions);
+        assert_eq!(tx.completions_reclaimed(), 1);
+
+        let mut done = vec![first];
+        done.extend(tx.completions());
+        assert_eq!(done, sent);
+        assert_eq!(tx.completions().next(), None);
+        assert_eq!(tx.completions_reclaimed(), 3);
+
+        // Nothing went back to the pool: the frames are ours until returned
+        assert_eq!(tx.reserved_frames(), 0);
+        for addr in done {
+            tx.free_frame(addr);
+        }
+        assert_eq!(tx.reserved_frames(), 3);
+    }
+
+    #[test]
+    fn test_alloc_frame_distinct() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+        assert_eq!(tx.alloc_frame(), None);
+
+        // Two dropped packets free two frames
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+        drop(rx.recv(16));
+
+        let first = tx.alloc_frame().expect("Frame should be free");
+        let second = tx.alloc_frame().expect("Frame should be free");
+        assert_ne!(first, second);
+        assert_eq!(tx.alloc_frame(), None);
+
+        // Stage both; they must not overwrite each other
+        assert!(tx.send_frame(first, b"first"));
+        assert!(tx.send_frame(second, b"second"));
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"first");
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"second");
+
+        // A frame that is not sent goes back to the pool
+        tx.reclaim();
+        let frame = tx.alloc_frame().expect("Completed frame should be free");
+        assert!(!tx.send_frame(frame, &[0u8; 4096]));
+        tx.free_frame(frame);
+        let free = [tx.alloc_frame(), tx.alloc_frame()];
+        assert!(free.contains(&Some(frame)));
+        assert_eq!(tx.alloc_frame(), None);
+    }
+
+    #[test]
+    fn test_buffered_send_flushes_once() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(128).tx_reserve_frames(100);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (_rx, mut tx) = system::split(flux_raw);
+        tx.set_buffered(true);
+
+        for i in 0..100u8 {
+            assert!(tx.send_bytes(&[i; 60]));
+        }
+        assert_eq!(tx.pending(), 100);
+        tx.flush().expect("Flush failed");
+        assert_eq!(tx.pending(), 0);
+        // Flushing again has nothing to do
+        tx.flush().expect("Flush failed");
+
+        for i in 0..100u8 {
+            assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![i; 60]);
+        }
+        assert_eq!(tx.reclaim(), 100);
+        assert_eq!(tx.reserved_frames(), 100);
+
+        // Unbuffered sends kick as they go
+        tx.set_buffered(false);
+        assert!(tx.send_bytes(&[0xEE; 60]));
+        assert_eq!(tx.pending(), 0);
+    }
+
+    #[test]
+    fn test_worker_echo_server() {
+        use fluxcapacitor::system::{FluxWorker, Verdict};
+
+        let flux_raw = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut worker = FluxWorker::new(flux_raw);
+
+        // Eth + IPv4 + UDP 10.0.0.1:1000 -> 10.0.0.2:7
+        let mut frame = udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], 7), b"ping");
+        frame[0..6].copy_from_slice(&[2, 0, 0, 0, 0, 2]);
+        frame[6..12].copy_from_slice(&[2, 0, 0, 0, 0, 1]);
+
+        // The whole echo server: reply to UDP port 7, drop everything else
+        let mut echo = |pkt: &mut fluxcapacitor::packet::Packet| match pkt.udp() {
+            Some(udp) if udp.dst_port() == 7 => Verdict::Reply,
+            _ => Verdict::Drop,
+        };
+
+        for _ in 0..32 {
+            control::inject_packet(fd, &frame).expect("Failed to inject packet");
+            control::inject_packet(fd, &[0u8; 60]).expect("Failed to inject packet");
+            assert_eq!(worker.poll(&mut echo).expect("Poll failed"), 2);
+
+            let reply = control::read_tx_packet(fd).expect("Failed to read TX");
+            assert_eq!(&reply[0..6], &frame[6..12]);
+            assert_eq!(&reply[26..30], &[10, 0, 0, 2]);
+            assert_eq!(u16::from_be_bytes([reply[36], reply[37]]), 1000);
+            assert_eq!(&reply[42..], b"ping");
+            assert!(control::read_tx_packet(fd).is_err(), "Only the UDP packet is echoed");
+        }
+        // 32 rounds through 16 frames: replies and drops were all recycled
+        assert_eq!(worker.tx().pending(), 0);
+    }
+
+    #[test]
+    fn test_tx_reserve_frames() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).tx_reserve_frames(2);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+        assert_eq!(rx.fills_submitted(), 14);
+        assert_eq!(tx.reserved_frames(), 2);
+
+        // Hold every RX frame; the reserve still lets TX originate a packet
+        for _ in 0..14 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        assert!(control::inject_packet(fd, &[0xBB; 64]).is_err(), "Reserved frames must stay off the Fill Ring");
+        let held = rx.recv(16);
+        assert_eq!(held.len(), 14);
+
+        let frame = tx.alloc_frame().expect("Reserve should supply a frame");
+        assert!(tx.send_frame(frame, b"origin"));
+        assert_eq!(tx.reserved_frames(), 1);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), b"origin");
+
+        // The completed frame tops the reserve back up
+        tx.reclaim();
+        assert_eq!(tx.reserved_frames(), 2);
+        drop(held);
+    }
+
+    #[test]
+    fn test_rx_ready_without_poll() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split(flux_raw);
+        assert!(!rx.ready());
+
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        assert!(rx.ready());
+        assert!(rx.ready(), "Checking must not consume the packet");
+
+        assert_eq!(rx.recv(16).len(), 1);
+        assert!(!rx.ready());
+    }
+
+    #[test]
+    fn test_refill_drains_backlog() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(256).fill_batch(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split(flux_raw);
+
+        for _ in 0..200 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        let packets = rx.recv(256);
+        assert_eq!(packets.len(), 200);
+        drop(packets);
+
+        // 200 frames on the free list, far more than one 16-frame reservation
+        assert_eq!(rx.refill(), 200);
+        assert_eq!(rx.fill_capacity(), 0);
+        assert_eq!(rx.refill(), 0);
+    }
+
+    #[test]
+    fn test_cloned_builder_binds_two_queues() {
+        let base = FluxBuilder::new("eth0").umem_pages(16).batch_size(8);
+        let raw0 = base.clone().queue_id(0).build_raw().expect("Failed to build queue 0");
+        let raw1 = base.clone().queue_id(1).build_raw().expect("Failed to build queue 1");
+        assert_ne!(raw0.fd(), raw1.fd());
+
+        // Each socket has its own UMEM and rings
+        let (mut rx0, _tx0) = fluxcapacitor::system::split(raw0);
+        let (mut rx1, _tx1) = fluxcapacitor::system::split(raw1);
+        control::inject_packet(rx1.fd(), &[0xAA; 64]).expect("Failed to inject packet");
+        assert_eq!(rx0.recv(16).len(), 0);
+        assert_eq!(rx1.recv(16).len(), 1);
+
+        // The original is still usable after cloning
+        assert!(base.build_raw().is_ok());
+    }
+
+    #[test]
+    fn test_bound_interface_and_queue() {
+        let raw = FluxBuilder::new("eth0").queue_id(3).umem_pages(16).build_raw().expect("Failed to build raw socket");
+        assert_eq!(raw.interface(), "eth0");
+        assert_eq!(raw.queue_id(), 3);
+        // The simulator resolves every name to index 1
+        assert_eq!(raw.if_index(), 1);
+
+        let engine = FluxBuilder::new("eth1").queue_id(2).umem_pages(16).build_engine().expect("Failed to build engine");
+        assert_eq!((engine.interface(), engine.if_index(), engine.queue_id()), ("eth1", 1, 2));
+    }
+
+    #[test]
+    fn test_promiscuous_is_noop() {
+        use fluxcapacitor::system;
+
+        // The simulated link ignores the flag; the socket builds and splits as usual
+        let raw = FluxBuilder::new("eth0").promiscuous(true).umem_pages(16).build_raw().expect("Failed to build raw socket");
+        let fd = raw.fd();
+        let (mut rx, _tx) = system::split(raw);
+        control::inject_packet(fd, &[0xAB; 60]).expect("Failed to inject packet");
+        assert_eq!(rx.recv(4).len(), 1);
+    }
+
+    #[test]
+    fn test_socket_buffers_are_noop() {
+        use fluxcapacitor::system;
+
+        let raw = FluxBuilder::new("eth0").rcvbuf(1 << 20).sndbuf(1 << 20).umem_pages(16).build_raw().expect("Failed to build raw socket");
+        let fd = raw.fd();
+        let (mut rx, _tx) = system::split(raw);
+        control::inject_packet(fd, &[0xAB; 60]).expect("Failed to inject packet");
+        assert_eq!(rx.recv(4).len(), 1);
+    }
+
+    #[test]
+    fn test_ring_capacity() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        // Every frame is on the Fill Ring, the TX Ring is empty
+        assert_eq!(rx.fill_capacity(), 0);
+        assert_eq!(tx.tx_capacity(), 16);
+
+        for i in 0..3u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+        // The "NIC" took three buffers
+        assert_eq!(rx.fill_capacity(), 3);
+
+        for packet in rx.recv(16) {
+            tx.send(packet);
+        }
+        assert_eq!(tx.tx_capacity(), 13);
+
+        // Consumed by the "NIC": room again
+        control::read_tx_packet(fd).expect("Failed to read TX");
+        assert_eq!(tx.tx_capacity(), 14);
+    }
+
+    #[test]
+    fn test_reclaim_drains_completion_ring() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(128);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        // Echo far more packets than one 32-entry peek used to cover
+        for i in 0..100u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+        let packets = rx.recv(128);
+        assert_eq!(packets.len(), 100);
+        for packet in packets {
+            tx.send(packet);
+        }
+        for _ in 0..100 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+
+        assert_eq!(tx.reclaim(), 100);
+        assert_eq!(tx.reclaim(), 0);
+        assert_eq!(tx.completions_reclaimed(), 100);
+
+        // Every frame is back in the pool
+        rx.refill();
+        assert_eq!(rx.frames_recycled(), 100);
+    }
+
+    #[test]
+    fn test_on_complete_callback() {
+        use fluxcapacitor::system;
+        use std::sync::{Arc, Mutex};
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split(flux_raw);
+
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&completed);
+        tx.on_complete(move |addr| sink.lock().unwrap().push(addr));
+
+        // Echo 5 packets
+        for i in 0..5u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+        let mut sent = Vec::new();
+        for packet in rx.recv(16) {
+            sent.push(packet.addr());
+            tx.send(packet);
+        }
+        assert_eq!(sent.len(), 5);
+
+        // Nothing completes before the "NIC" transmits
+        tx.reclaim();
+        assert!(completed.lock().unwrap().is_empty());
+
+        for _ in 0..3 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+        tx.reclaim();
+        assert_eq!(*completed.lock().unwrap(), sent[..3]);
+
+        for _ in 0..2 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+        tx.reclaim();
+        assert_eq!(*completed.lock().unwrap(), sent);
+
+        // Recycling still happens alongside the callback
+        assert_eq!(tx.completions_reclaimed(), 5);
+    }
+
+    #[test]
+    fn test_manual_fill() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).auto_fill(false);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split(flux_raw);
+        assert_eq!(rx.fills_submitted(), 0);
+
+        // 1. Nothing on the Fill Ring: the "NIC" has nowhere to put packets
+        assert!(control::inject_packet(fd, &[0xAA; 64]).is_err());
+        assert!(rx.recv(16).is_empty());
+
+        // 2. Hand over two frames, keeping the rest for ourselves
+        assert!(rx.fill_frames(&[0, 2048]));
+        assert_eq!(rx.fills_submitted(), 2);
+        for _ in 0..2 {
+            control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+        }
+        assert!(control::inject_packet(fd, &[0xCC; 64]).is_err());
+
+        let packets = rx.recv(16);
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|p| p.data() == [0xBB; 64]));
+    }
+
+    #[test]
+    fn test_ring_smaller_than_umem() {
+        use fluxcapacitor::system;
+
+        // 1. Split API: the Fill Ring only takes 8, the other 8 wait on the free list
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).ring_size(8);
         let flux_raw = builder.build_raw().expect("Failed to build raw socket");
         let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split(flux_raw);
+        assert_eq!(rx.fills_submitted(), 8);
 
-        // Engine consumes FluxRaw
-        let mut engine = FluxEngine::new(flux_raw, 16);
-        
-        let engine_thread = thread::spawn(move || {
-            // Run a few steps by manually polling `process_batch`
-            for _ in 0..10 {
-                let _ = engine.process_batch(&mut |batch| {
-                     let mut processed = false;
-                     for _packet in batch.iter_mut() {
-                         processed = true;
-                     }
-                     if processed {
-                         // println!("Processed batch");
-                     }
-                });
-                thread::sleep(Duration::from_millis(10));
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        assert_eq!(rx.recv(16).len(), 8);
+        // recv refilled from the spares before consuming
+        control::inject_packet(fd, &[0xBB; 64]).expect("RX should still receive");
+        assert_eq!(rx.recv(16).len(), 1);
+
+        // 2. Engine: same, spares are handed over as the ring drains
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).ring_size(8);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        let mut received = engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xBB; 64]).expect("RX should still receive");
+        }
+        received += engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(received, 16);
+    }
+
+    #[test]
+    fn test_small_ring_keeps_every_frame_in_use() {
+        use std::collections::HashSet;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).ring_size(8);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        let chunk = engine.socket.umem.layout().chunk_size as u64;
+
+        // Each pass tops the Fill Ring up from the spares before committing, so the
+        // dropped frames no longer fit and must wait their turn instead of leaking
+        let mut rounds = Vec::new();
+        for _ in 0..6 {
+            for _ in 0..8 {
+                control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
             }
-        });
+            let mut frames = HashSet::new();
+            let received = engine.process_batch(&mut |batch| {
+                let (descs, _, _) = batch.as_slices_mut();
+                frames.extend(descs.iter().map(|d| d.addr / chunk));
+            }).expect("Batch processing failed");
+            assert_eq!(received, 8);
+            rounds.push(frames);
+        }
 
-        // 2. Inject Packet (Simulate Network Arrival)
-        let payload = vec![0xAA, 0xBB, 0xCC, 0xDD];
-        control::inject_packet(fd, &payload).expect("Failed to inject packet");
+        // Every later pair of passes still cycles through all 16 frames
+        for pair in rounds.windows(2).skip(2) {
+            assert_eq!(pair[0].union(&pair[1]).count(), 16, "frames went missing: {:?}", pair);
+        }
+    }
 
-        // 3. Wait for processing 
-        thread::sleep(Duration::from_millis(200));
-        
-        // 4. Verification
-        assert!(true, "Simulation logic executed without error");
-        
-        let _ = engine_thread.join();
+    #[test]
+    fn test_drain_rx_until_empty() {
+        let batch_size = 4;
+        let builder = FluxBuilder::new("eth0")
+            .queue_id(0)
+            .umem_pages(16)
+            .batch_size(batch_size)
+            .drain_rx(true);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+
+        for i in 0..(3 * batch_size) {
+            control::inject_packet(fd, &[i as u8; 64]).expect("Failed to inject packet");
+        }
+
+        // One call drains the whole backlog in batch_size chunks
+        let mut batches = 0;
+        let count = engine.process_batch(&mut |batch| {
+            assert!(batch.len() <= batch_size);
+            batches += 1;
+        }).expect("Batch processing failed");
+
+        assert_eq!(count, 3 * batch_size);
+        assert_eq!(batches, 3);
+    }
+
+    #[test]
+    fn test_observer_receives_events() {
+        use fluxcapacitor::observer::FluxObserver;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Events {
+            batches: Vec<(usize, usize, usize)>,
+            fill_empty: usize,
+        }
+
+        struct MockObserver(Arc<Mutex<Events>>);
+
+        impl FluxObserver for MockObserver {
+            fn on_batch(&mut self, rx: usize, tx: usize, dropped: usize) {
+                self.0.lock().unwrap().batches.push((rx, tx, dropped));
+            }
+            fn on_fill_empty(&mut self) {
+                self.0.lock().unwrap().fill_empty += 1;
+            }
+        }
+
+        let events = Arc::new(Mutex::new(Events::default()));
+        let builder = FluxBuilder::new("eth0")
+            .queue_id(0)
+            .umem_pages(16)
+            .batch_size(16)
+            .observer(MockObserver(events.clone()));
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+
+        // Use up every frame so the kernel side of the Fill Ring runs dry
+        for i in 0..16 {
+            control::inject_packet(fd, &[i as u8; 64]).expect("Failed to inject packet");
+        }
+
+        // Echo one packet, drop the rest
+        engine.process_batch(&mut |batch| {
+            if let Some(mut packet) = batch.iter_mut().next() {
+                packet.send();
+            }
+        }).expect("Batch processing failed");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.batches, vec![(16, 1, 15)]);
+        assert_eq!(events.fill_empty, 1);
     }
 
     #[test]
@@ -76,6 +960,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_switch_poller_while_running() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use fluxcapacitor::config::Poller;
+        use fluxcapacitor::observer::FluxObserver;
+
+        // With an empty Fill Ring every loop iteration reports it: count iterations
+        struct Iterations(Arc<AtomicUsize>);
+        impl FluxObserver for Iterations {
+            fn on_fill_empty(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let iterations = Arc::new(AtomicUsize::new(0));
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).auto_fill(false)
+            .poller(Poller::Wait)
+            .observer(Iterations(iterations.clone()));
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        assert_eq!(engine.poller(), Poller::Wait);
+        let control = engine.poller_control();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        let engine_thread = thread::spawn(move || {
+            engine.run(&stop_clone, |_batch| {}).expect("Engine run failed");
+            engine
+        });
+
+        // Each idle Wait iteration naps; Busy spins
+        thread::sleep(Duration::from_millis(50));
+        let waiting = iterations.swap(0, Ordering::Relaxed);
+        control.set(Poller::Busy);
+        thread::sleep(Duration::from_millis(50));
+        let busy = iterations.load(Ordering::Relaxed);
+
+        stop.store(true, Ordering::Relaxed);
+        let engine = engine_thread.join().expect("Engine thread panicked");
+        assert_eq!(engine.poller(), Poller::Busy);
+        assert!(busy > waiting * 10, "Busy looped {} times vs {} waiting", busy, waiting);
+    }
+
+    #[test]
+    fn test_wait_poller_stops_without_traffic() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Instant;
+        use fluxcapacitor::config::Poller;
+
+        let timeout = Duration::from_millis(100);
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16)
+            .poller(Poller::Wait)
+            .poll_timeout(timeout);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let engine_thread = scope.spawn(|| {
+                engine.run(&stop, |_batch| {}).expect("Engine run failed");
+                Instant::now()
+            });
+
+            thread::sleep(Duration::from_millis(20));
+            let stopped_at = Instant::now();
+            stop.store(true, Ordering::Relaxed);
+            let returned_at = engine_thread.join().expect("Engine thread panicked");
+
+            // One wait at most, plus scheduling slack
+            assert!(returned_at - stopped_at < timeout + Duration::from_millis(50));
+        });
+    }
+
     #[tokio::test]
     #[cfg(feature = "async")]
     async fn test_async_system_echo() {
@@ -106,4 +1062,547 @@ mod tests {
         let out = control::read_tx_packet(fd).expect("Failed to read TX");
         assert_eq!(out, payload);
     }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_recv_array() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, mut tx) = system::split_async(flux_raw).expect("Failed to split async");
+
+        for i in 0..3u8 {
+            control::inject_packet(fd, &[i; 32]).expect("Failed to inject");
+        }
+
+        // Capacity bounds one receive; the rest waits on the ring
+        let packets = rx.recv_array::<2>().await.expect("Recv failed");
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets.capacity(), 2);
+        assert_eq!(packets[1].data(), &[1; 32]);
+        for p in packets {
+            tx.send(p);
+        }
+
+        let mut rest = rx.recv_array::<2>().await.expect("Recv failed");
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest.pop().expect("Packet").data(), &[2; 32]);
+
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![0; 32]);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![1; 32]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_recv_parses_udp() {
+        use fluxcapacitor::system;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let (mut rx, _tx) = system::split_async(flux_raw).expect("Failed to split async");
+
+        // Eth + IPv4 + UDP 10.0.0.1:1000 -> 10.0.0.2:53 carrying "ping"
+        let frame = udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], 53), b"ping");
+        control::inject_packet(fd, &frame).expect("Failed to inject");
+
+        let packets = rx.recv(4).await.expect("Recv failed");
+        let packet = &packets[0];
+        assert_eq!(packet.ipv4().map(|ip| ip.dst_addr()), Some([10, 0, 0, 2].into()));
+        assert_eq!(packet.udp().map(|udp| (udp.src_port(), udp.dst_port())), Some((1000, 53)));
+        assert!(packet.tcp().is_none());
+        assert_eq!(packet.socket_addrs().map(|(_, dst)| dst), Some("10.0.0.2:53".parse().unwrap()));
+        assert_eq!(packet.payload_l4(), Some(&b"ping"[..]));
+    }
+
+    #[test]
+    fn test_udp_payloads_skips_non_udp() {
+        use fluxcapacitor::packet::Action;
+
+        // Eth + IPv4 + TCP to `port` + 4 payload bytes
+        fn tcp_frame(port: u16, payload: [u8; 4]) -> Vec<u8> {
+            let mut frame = vec![0u8; 14 + 20 + 20 + 4];
+            frame[12..14].copy_from_slice(&[0x08, 0x00]);
+            frame[14] = 0x45;
+            frame[14 + 2..14 + 4].copy_from_slice(&44u16.to_be_bytes());
+            frame[14 + 9] = 6;
+            frame[34 + 2..34 + 4].copy_from_slice(&port.to_be_bytes());
+            frame[34 + 12] = 0x50; // Data offset 5
+            frame[54..].copy_from_slice(&payload);
+            frame
+        }
+        let udp = |port: u16, payload: &[u8]| udp_frame(([10, 0, 0, 1], 1000), ([10, 0, 0, 2], port), payload);
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut engine = FluxEngine::new(flux_raw, 16);
+
+        control::inject_packet(fd, &udp(53, b"dns?")).expect("Failed to inject packet");
+        control::inject_packet(fd, &tcp_frame(80, *b"http")).expect("Failed to inject packet");
+        control::inject_packet(fd, &udp(123, b"ntp?")).expect("Failed to inject packet");
+
+        let mut seen = Vec::new();
+        engine.process_batch(&mut |batch| {
+            for (header, payload, action) in batch.udp_payloads() {
+                seen.push((header.dst_port(), payload.to_vec()));
+                payload[3] = b'!';
+                *action = Action::Tx;
+            }
+        }).expect("Batch processing failed");
+
+        assert_eq!(seen, [(53, b"dns?".to_vec()), (123, b"ntp?".to_vec())]);
+
+        // Only the UDP packets were echoed, with their edited payloads
+        let out = control::read_tx_packet(fd).expect("Failed to read TX");
+        assert_eq!(&out[42..], b"dns!");
+        let out = control::read_tx_packet(fd).expect("Failed to read TX");
+        assert_eq!(&out[42..], b"ntp!");
+        assert!(control::read_tx_packet(fd).is_err());
+    }
+
+    #[test]
+    fn test_multi_engine_two_sockets() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let wan = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).build_raw().expect("Failed to build WAN socket");
+        let lan = FluxBuilder::new("eth1").queue_id(0).umem_pages(16).build_raw().expect("Failed to build LAN socket");
+        let (wan_fd, lan_fd) = (wan.fd(), lan.fd());
+        let mut engine = FluxEngine::multi(vec![wan, lan], 16);
+
+        control::inject_packet(wan_fd, &[0xAA; 64]).expect("Failed to inject packet");
+        control::inject_packet(lan_fd, &[0xBB; 64]).expect("Failed to inject packet");
+        control::inject_packet(lan_fd, &[0xBB; 64]).expect("Failed to inject packet");
+
+        // 1. One pass services both sockets, tagged by index
+        let mut seen = [0usize; 2];
+        let total = engine.process_batch(&mut |idx, batch| {
+            for packet in batch.iter_mut() {
+                assert_eq!(packet.data()[0], [0xAA, 0xBB][idx]);
+                seen[idx] += 1;
+            }
+        }).expect("Batch processing failed");
+        assert_eq!(total, 3);
+        assert_eq!(seen, [1, 2]);
+
+        // 2. `run` echoes LAN traffic until stopped; WAN packets are dropped
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopper = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                stop.store(true, Ordering::Relaxed);
+            })
+        };
+        control::inject_packet(lan_fd, &[0xCC; 64]).expect("Failed to inject packet");
+        engine.run(&stop, |idx, batch| {
+            for mut packet in batch.iter_mut() {
+                if idx == 1 {
+                    packet.send();
+                }
+            }
+        }).expect("Run failed");
+        stopper.join().unwrap();
+
+        assert_eq!(control::read_tx_packet(lan_fd).expect("Failed to read TX"), vec![0xCC; 64]);
+        assert!(control::read_tx_packet(wan_fd).is_err());
+    }
+
+    #[test]
+    fn test_tx_frame_not_refilled_before_completion() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut engine = FluxEngine::new(flux_raw, 16);
+
+        // 1. Echo one packet and drop another
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+        let mut addrs = Vec::new();
+        engine.process_batch(&mut |batch| {
+            let (descs, actions, _) = batch.as_slices_mut();
+            addrs = descs.iter().map(|d| d.addr).collect();
+            actions[0] = fluxcapacitor::packet::Action::Tx;
+        }).expect("Batch processing failed");
+        let (tx_addr, drop_addr) = (addrs[0], addrs[1]);
+
+        // 2. The dropped frame is back on Fill, the in-flight TX frame is not,
+        //    no matter how often the engine polls
+        for _ in 0..3 {
+            let fill = control::fill_ring_addrs(fd).unwrap();
+            assert!(fill.contains(&drop_addr));
+            assert!(!fill.contains(&tx_addr), "TX frame refilled before completion");
+            engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        }
+
+        // 3. Once the "NIC" completes it, the next poll recycles it
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![0xAA; 64]);
+        assert!(!control::fill_ring_addrs(fd).unwrap().contains(&tx_addr));
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert!(control::fill_ring_addrs(fd).unwrap().contains(&tx_addr));
+    }
+
+    #[test]
+    fn test_completions_wait_for_fill_room() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).ring_size(8);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+
+        // 1. Echo a full ring of packets; the spares then refill the Fill Ring to the brim
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        let mut sent = Vec::new();
+        engine.process_batch(&mut |batch| {
+            let (descs, actions, _) = batch.as_slices_mut();
+            sent.extend(descs.iter().map(|d| d.addr));
+            actions.fill(fluxcapacitor::packet::Action::Tx);
+        }).expect("Batch processing failed");
+        assert_eq!(sent.len(), 8);
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(control::fill_ring_addrs(fd).unwrap().len(), 8);
+
+        // 2. All eight complete while the Fill Ring has no room: they must wait
+        for _ in 0..8 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        let fill = control::fill_ring_addrs(fd).unwrap();
+        assert!(sent.iter().all(|addr| !fill.contains(addr)));
+
+        // 3. Once RX consumes Fill entries, the completed frames go back on it
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+        }
+        let echoed = engine.process_batch(&mut |batch| {
+            let (_, actions, _) = batch.as_slices_mut();
+            actions.fill(fluxcapacitor::packet::Action::Tx);
+        }).expect("Batch processing failed");
+        assert_eq!(echoed, 8);
+        let fill = control::fill_ring_addrs(fd).unwrap();
+        assert!(sent.iter().all(|addr| fill.contains(addr)), "completed TX frames were lost");
+    }
+
+    #[test]
+    fn test_comp_batch_independent_of_rx_batch() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).rx_batch(4).comp_batch(2);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+
+        // RX takes four at a time
+        for _ in 0..8 {
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        }
+        for _ in 0..2 {
+            let echoed = engine.process_batch(&mut |batch| {
+                let (_, actions, _) = batch.as_slices_mut();
+                actions.fill(fluxcapacitor::packet::Action::Tx);
+            }).expect("Batch processing failed");
+            assert_eq!(echoed, 4);
+        }
+        assert_eq!(control::fill_ring_addrs(fd).unwrap().len(), 8);
+
+        // Eight completions, recycled two per pass
+        for _ in 0..8 {
+            control::read_tx_packet(fd).expect("Failed to read TX");
+        }
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(control::fill_ring_addrs(fd).unwrap().len(), 10);
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(control::fill_ring_addrs(fd).unwrap().len(), 12);
+
+        // A larger completion batch takes the rest in one pass
+        engine.set_comp_batch(64);
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert_eq!(control::fill_ring_addrs(fd).unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_invalid_rx_descriptors_rejected() {
+        use fluxcapacitor_core::ring::XDPDesc;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        let chunk = engine.socket.umem.layout().chunk_size as u64;
+
+        // A good packet between a descriptor running past its chunk and an empty one
+        control::inject_rx_desc(fd, XDPDesc { addr: 3 * chunk - 10, len: 64, options: 0 }).unwrap();
+        control::inject_packet(fd, &[0x42; 64]).expect("Failed to inject packet");
+        control::inject_rx_desc(fd, XDPDesc { addr: 5 * chunk, len: 0, options: 0 }).unwrap();
+
+        let mut seen = Vec::new();
+        let count = engine.process_batch(&mut |batch| {
+            for packet in batch.iter_mut() {
+                seen.push(packet.data().to_vec());
+            }
+        }).expect("Batch processing failed");
+
+        assert_eq!(count, 1);
+        assert_eq!(seen, vec![vec![0x42; 64]]);
+        let snap = engine.stats().snapshot();
+        assert_eq!(snap.rx_packets, 1);
+        assert_eq!(snap.rx_invalid, 2);
+
+        // Frame 2 is still on the Fill Ring: the bad descriptor must not add it again
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        let fill = control::fill_ring_addrs(fd).unwrap();
+        let mut unique = fill.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), fill.len(), "a frame is on the Fill Ring twice: {:?}", fill);
+    }
+
+    #[test]
+    fn test_pass_counted_apart_from_drop() {
+        use fluxcapacitor::packet::Action;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        for i in 0..5u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+
+        let mut addrs = Vec::new();
+        engine.process_batch(&mut |batch| {
+            let (descs, actions, _) = batch.as_slices_mut();
+            addrs = descs.iter().map(|d| d.addr).collect();
+            actions.copy_from_slice(&[Action::Pass, Action::Drop, Action::Pass, Action::Tx, Action::Drop]);
+        }).expect("Batch processing failed");
+
+        let snap = engine.stats().snapshot();
+        assert_eq!((snap.passed, snap.dropped, snap.tx_packets), (2, 2, 1));
+
+        // Mechanically the same: passed frames are back on the Fill Ring too
+        let fill = control::fill_ring_addrs(fd).unwrap();
+        assert!(fill.contains(&addrs[0]) && fill.contains(&addrs[2]));
+        assert!(!fill.contains(&addrs[3]));
+    }
+
+    #[test]
+    fn test_peek_then_commit() {
+        use fluxcapacitor::packet::Action;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        for i in 1..=3u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+
+        // 1. Peeking leaves the packets on the RX ring
+        let mut first = Vec::new();
+        assert_eq!(engine.peek_batch(|packet| first.push(packet.data()[0])).unwrap(), 3);
+        let mut second = Vec::new();
+        assert_eq!(engine.peek_batch(|packet| second.push(packet.data()[0])).unwrap(), 3);
+        assert_eq!(first, [1, 2, 3]);
+        assert_eq!(first, second);
+        assert_eq!(engine.stats().snapshot().rx_packets, 0);
+
+        // 2. Committing applies the verdicts decided "later"
+        let verdicts: Vec<Action> = first.iter().map(|b| if b % 2 == 1 { Action::Tx } else { Action::Drop }).collect();
+        assert_eq!(engine.commit(&verdicts).unwrap(), 3);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![1; 64]);
+        assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![3; 64]);
+        assert!(control::read_tx_packet(fd).is_err());
+
+        let snap = engine.stats().snapshot();
+        assert_eq!((snap.rx_packets, snap.tx_packets, snap.dropped), (3, 2, 1));
+
+        // 3. Nothing left to peek or commit
+        assert_eq!(engine.peek_batch(|_| {}).unwrap(), 0);
+        assert_eq!(engine.commit(&[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_stats_read_from_monitor_thread() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        let stats = engine.stats();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let monitor = scope.spawn(|| {
+                let mut last = stats.snapshot();
+                while !done.load(Ordering::Relaxed) {
+                    let snap = stats.snapshot();
+                    assert!(snap.rx_packets >= last.rx_packets);
+                    last = snap;
+                }
+                stats.snapshot()
+            });
+
+            // Echo even packets, drop odd ones
+            for i in 0..20u8 {
+                control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+                engine.process_batch(&mut |batch| {
+                    for mut packet in batch.iter_mut() {
+                        if packet.data()[0] % 2 == 0 {
+                            packet.send();
+                        }
+                    }
+                }).expect("Batch processing failed");
+                if i % 2 == 0 {
+                    control::read_tx_packet(fd).expect("Failed to read TX");
+                }
+            }
+            done.store(true, Ordering::Relaxed);
+
+            let snap = monitor.join().expect("Monitor thread panicked");
+            assert_eq!(snap.rx_packets, 20);
+            assert_eq!(snap.rx_bytes, 20 * 64);
+            assert_eq!(snap.tx_packets, 10);
+            assert_eq!(snap.tx_bytes, 10 * 64);
+            assert_eq!(snap.dropped, 10);
+        });
+    }
+
+    #[test]
+    fn test_set_len_rejects_growth_past_frame() {
+        // Frames are 2048 bytes on a 4096-byte stride: the stride is not usable
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).chunk_size(4096);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        let mut checked = false;
+        engine.process_batch(&mut |batch| {
+            for mut packet in batch.iter_mut() {
+                assert_eq!(packet.capacity(), 2048);
+                assert!(!packet.set_len(2049));
+                assert_eq!(packet.len(), 64);
+
+                // Growing into the frame's tailroom is fine
+                assert!(packet.set_len(2048));
+                assert_eq!(packet.data().len(), 2048);
+                checked = true;
+            }
+        }).expect("Batch processing failed");
+        assert!(checked);
+    }
+
+    #[test]
+    fn test_held_frame_released_later() {
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let mut engine = builder.build_engine().expect("Failed to build engine");
+        let fd = engine.socket_fd();
+        engine.set_max_held(1);
+
+        // 1. Sample both packets; only one hold fits, the other is dropped
+        control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+        control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+        let mut addrs = Vec::new();
+        engine.process_batch(&mut |batch| {
+            addrs = batch.as_slices_mut().0.iter().map(|d| d.addr).collect();
+            for mut packet in batch.iter_mut() {
+                packet.retain();
+            }
+        }).expect("Batch processing failed");
+        let (held_addr, dropped_addr) = (addrs[0], addrs[1]);
+        assert_eq!(engine.held_count(), 1);
+        assert_eq!(engine.held_frame(held_addr), Some(&[0xAA; 64][..]));
+        assert_eq!(engine.held_frame(dropped_addr), None);
+
+        // 2. The held frame is neither transmitted nor refilled, however often we poll
+        for _ in 0..3 {
+            let fill = control::fill_ring_addrs(fd).unwrap();
+            assert!(fill.contains(&dropped_addr));
+            assert!(!fill.contains(&held_addr), "Held frame refilled");
+            engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        }
+        assert!(control::read_tx_packet(fd).is_err());
+
+        // 3. Once released, the next poll refills it
+        assert!(engine.release_held(held_addr));
+        assert!(!engine.release_held(held_addr));
+        assert_eq!(engine.held_count(), 0);
+        engine.process_batch(&mut |_batch| {}).expect("Batch processing failed");
+        assert!(control::fill_ring_addrs(fd).unwrap().contains(&held_addr));
+    }
+
+    #[test]
+    fn test_commit_order_tx_first_defers_reclaim() {
+        use fluxcapacitor::config::CommitOrder;
+        use fluxcapacitor::packet::Action;
+
+        // Echo one packet, let the "NIC" complete it, then echo a second one.
+        // Returns whether the first frame was back on Fill while the second
+        // batch's callback ran, and whether it is once the batch is committed.
+        fn run(order: CommitOrder) -> (bool, bool) {
+            let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16).commit_order(order);
+            let mut engine = builder.build_engine().expect("Failed to build engine");
+            let fd = engine.socket_fd();
+
+            control::inject_packet(fd, &[0xAA; 64]).expect("Failed to inject packet");
+            let mut first = 0;
+            engine.process_batch(&mut |batch| {
+                let (descs, actions, _) = batch.as_slices_mut();
+                first = descs[0].addr;
+                actions[0] = Action::Tx;
+            }).expect("Batch processing failed");
+            control::read_tx_packet(fd).expect("Failed to read TX");
+
+            control::inject_packet(fd, &[0xBB; 64]).expect("Failed to inject packet");
+            let mut during = false;
+            engine.process_batch(&mut |batch| {
+                during = control::fill_ring_addrs(fd).unwrap().contains(&first);
+                for mut packet in batch.iter_mut() {
+                    packet.send();
+                }
+            }).expect("Batch processing failed");
+            let after = control::fill_ring_addrs(fd).unwrap().contains(&first);
+
+            // The second echo went out either way
+            assert_eq!(control::read_tx_packet(fd).expect("Failed to read TX"), vec![0xBB; 64]);
+            (during, after)
+        }
+
+        // Reclaim runs before RX: the frame is refilled before the callback sees the batch.
+        assert_eq!(run(CommitOrder::ReclaimFirst), (true, true));
+        // TX first: the completion is only reclaimed after the TX kick.
+        assert_eq!(run(CommitOrder::TxFirst), (false, true));
+    }
+
+    #[test]
+    fn test_run_until_packet_budget() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let builder = FluxBuilder::new("eth0").queue_id(0).umem_pages(16);
+        let flux_raw = builder.build_raw().expect("Failed to build raw socket");
+        let fd = flux_raw.fd();
+        let mut engine = FluxEngine::new(flux_raw, 4);
+
+        for i in 0..10u8 {
+            control::inject_packet(fd, &[i; 64]).expect("Failed to inject packet");
+        }
+
+        // 1. The budget stops the loop mid-burst, even across batch boundaries
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut seen = Vec::new();
+        let total = engine.run_until(&stop, Some(7), |batch| {
+            seen.extend(batch.iter_mut().map(|p| p.data()[0]));
+        }).expect("Run failed");
+        assert_eq!(total, 7);
+        assert_eq!(seen, (0..7).collect::<Vec<u8>>());
+
+        // 2. Without enough traffic, the stop flag ends the run with what was processed
+        let stopper = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                stop.store(true, Ordering::Relaxed);
+            })
+        };
+        let total = engine.run_until(&stop, Some(100), |_batch| {}).expect("Run failed");
+        stopper.join().unwrap();
+        assert_eq!(total, 3);
+    }
 }